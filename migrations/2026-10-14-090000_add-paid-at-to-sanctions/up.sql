@@ -0,0 +1 @@
+ALTER TABLE sanctions ADD COLUMN paid_at DATE
\ No newline at end of file
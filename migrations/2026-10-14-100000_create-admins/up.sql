@@ -0,0 +1,9 @@
+CREATE TABLE admins (
+    id UUID PRIMARY KEY,
+    team_id UUID NOT NULL,
+    username VARCHAR NOT NULL,
+    password_hash VARCHAR NOT NULL,
+
+    CONSTRAINT team_id FOREIGN KEY (team_id) REFERENCES teams (id) ON DELETE CASCADE,
+    CONSTRAINT username UNIQUE (team_id, username)
+)
\ No newline at end of file
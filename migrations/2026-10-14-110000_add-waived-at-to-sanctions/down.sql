@@ -0,0 +1 @@
+ALTER TABLE sanctions DROP COLUMN waived_at
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- The hashed passwords can't be turned back into plain text
+SELECT 1
\ No newline at end of file
@@ -0,0 +1,3 @@
+CREATE EXTENSION IF NOT EXISTS pgcrypto;
+
+UPDATE teams SET admin_password = crypt(admin_password, gen_salt('bf'))
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- The admins created from the team passwords can't be told apart from the others
+SELECT 1
\ No newline at end of file
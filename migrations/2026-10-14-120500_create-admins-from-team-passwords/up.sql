@@ -0,0 +1,13 @@
+-- Each team gets an admin named after it, logging in with the team's password
+INSERT INTO admins (id, team_id, username, password_hash)
+SELECT
+    gen_random_uuid(),
+    teams.id,
+    CASE
+        WHEN EXISTS (SELECT 1 FROM admins WHERE admins.username = teams.name)
+        THEN teams.name || '-' || teams.id
+        ELSE teams.name
+    END,
+    teams.admin_password
+FROM teams
+WHERE NOT EXISTS (SELECT 1 FROM admins WHERE admins.team_id = teams.id)
\ No newline at end of file
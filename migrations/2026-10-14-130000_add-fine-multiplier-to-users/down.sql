@@ -0,0 +1 @@
+ALTER TABLE users DROP COLUMN fine_multiplier
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN fine_multiplier REAL NOT NULL DEFAULT 1 CHECK (fine_multiplier > 0)
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE users DROP COLUMN anonymized
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE sanctions ALTER COLUMN price TYPE REAL USING price / 100.0
\ No newline at end of file
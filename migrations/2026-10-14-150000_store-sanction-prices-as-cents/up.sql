@@ -0,0 +1 @@
+ALTER TABLE sanctions ALTER COLUMN price TYPE BIGINT USING ROUND(price * 100)
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE sanctions DROP COLUMN season;
+ALTER TABLE teams DROP COLUMN current_season
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE teams ADD COLUMN current_season VARCHAR;
+ALTER TABLE sanctions ADD COLUMN season VARCHAR
\ No newline at end of file
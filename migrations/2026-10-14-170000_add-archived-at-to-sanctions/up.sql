@@ -0,0 +1 @@
+ALTER TABLE sanctions ADD COLUMN archived_at DATE
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE deleted_sanctions_audit
\ No newline at end of file
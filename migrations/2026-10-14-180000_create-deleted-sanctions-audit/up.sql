@@ -0,0 +1,10 @@
+CREATE TABLE deleted_sanctions_audit (
+    id UUID PRIMARY KEY,
+    sanction_id UUID NOT NULL,
+    team_id UUID NOT NULL,
+    sanction JSONB NOT NULL,
+    actor VARCHAR,
+    deleted_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    CONSTRAINT team_id FOREIGN KEY (team_id) REFERENCES teams (id) ON DELETE CASCADE
+)
\ No newline at end of file
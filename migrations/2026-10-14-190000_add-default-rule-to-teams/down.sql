@@ -0,0 +1 @@
+ALTER TABLE teams DROP COLUMN default_rule_id
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE teams ADD COLUMN default_rule_id UUID
\ No newline at end of file
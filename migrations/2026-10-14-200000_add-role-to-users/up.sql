@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN role VARCHAR
\ No newline at end of file
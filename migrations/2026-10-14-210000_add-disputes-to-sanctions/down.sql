@@ -0,0 +1 @@
+ALTER TABLE sanctions DROP COLUMN disputed, DROP COLUMN dispute_note
\ No newline at end of file
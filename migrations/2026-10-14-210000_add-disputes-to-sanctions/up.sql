@@ -0,0 +1 @@
+ALTER TABLE sanctions ADD COLUMN disputed BOOLEAN NOT NULL DEFAULT FALSE, ADD COLUMN dispute_note VARCHAR
\ No newline at end of file
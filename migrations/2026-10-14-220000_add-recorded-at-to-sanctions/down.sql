@@ -0,0 +1 @@
+ALTER TABLE sanctions DROP COLUMN recorded_at
\ No newline at end of file
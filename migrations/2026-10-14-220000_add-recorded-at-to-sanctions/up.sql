@@ -0,0 +1 @@
+ALTER TABLE sanctions ADD COLUMN recorded_at TIMESTAMPTZ NOT NULL DEFAULT now()
\ No newline at end of file
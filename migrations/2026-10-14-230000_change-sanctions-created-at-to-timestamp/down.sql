@@ -0,0 +1,3 @@
+ALTER TABLE sanctions
+    ALTER COLUMN created_at TYPE DATE USING (created_at AT TIME ZONE 'UTC')::DATE,
+    ALTER COLUMN created_at SET DEFAULT CURRENT_DATE
\ No newline at end of file
@@ -0,0 +1,3 @@
+ALTER TABLE sanctions
+    ALTER COLUMN created_at TYPE TIMESTAMPTZ USING created_at::TIMESTAMP AT TIME ZONE 'UTC',
+    ALTER COLUMN created_at SET DEFAULT now()
\ No newline at end of file
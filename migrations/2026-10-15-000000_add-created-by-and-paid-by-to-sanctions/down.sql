@@ -0,0 +1 @@
+ALTER TABLE sanctions DROP COLUMN created_by, DROP COLUMN paid_by
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE sanctions ADD COLUMN created_by UUID REFERENCES admins(id) ON DELETE SET NULL, ADD COLUMN paid_by UUID REFERENCES admins(id) ON DELETE SET NULL
\ No newline at end of file
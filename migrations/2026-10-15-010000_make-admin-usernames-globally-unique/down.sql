@@ -0,0 +1 @@
+ALTER TABLE admins DROP CONSTRAINT username, ADD CONSTRAINT username UNIQUE (team_id, username)
\ No newline at end of file
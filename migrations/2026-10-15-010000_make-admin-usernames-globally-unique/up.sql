@@ -0,0 +1 @@
+ALTER TABLE admins DROP CONSTRAINT username, ADD CONSTRAINT username UNIQUE (username)
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE admins DROP COLUMN user_id;
+ALTER TABLE teams DROP COLUMN allow_self_fine
\ No newline at end of file
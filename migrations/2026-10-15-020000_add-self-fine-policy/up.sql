@@ -0,0 +1,2 @@
+ALTER TABLE teams ADD COLUMN allow_self_fine BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE admins ADD COLUMN user_id UUID REFERENCES users(id) ON DELETE SET NULL
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE sanctions DROP COLUMN waive_reason
\ No newline at end of file
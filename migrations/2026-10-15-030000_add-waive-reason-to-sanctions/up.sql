@@ -0,0 +1 @@
+ALTER TABLE sanctions ADD COLUMN waive_reason VARCHAR
\ No newline at end of file
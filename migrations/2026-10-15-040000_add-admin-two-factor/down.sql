@@ -0,0 +1,2 @@
+DROP TABLE admin_login_codes;
+ALTER TABLE admins DROP COLUMN email, DROP COLUMN two_factor_enabled
\ No newline at end of file
@@ -0,0 +1,11 @@
+ALTER TABLE admins ADD COLUMN email VARCHAR, ADD COLUMN two_factor_enabled BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE admin_login_codes (
+    id UUID PRIMARY KEY,
+    admin_id UUID NOT NULL,
+    code_hash VARCHAR NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+
+    CONSTRAINT admin_id FOREIGN KEY (admin_id) REFERENCES admins (id) ON DELETE CASCADE
+)
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE teams DROP COLUMN max_balance, DROP COLUMN clamp_to_max_balance
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE teams ADD COLUMN max_balance BIGINT, ADD COLUMN clamp_to_max_balance BOOLEAN NOT NULL DEFAULT FALSE
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN external_id VARCHAR, ADD CONSTRAINT external_id UNIQUE (team_id, external_id)
\ No newline at end of file
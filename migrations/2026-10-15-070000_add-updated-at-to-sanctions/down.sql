@@ -0,0 +1,2 @@
+DROP TRIGGER IF EXISTS set_updated_at ON sanctions;
+ALTER TABLE sanctions DROP COLUMN updated_at
\ No newline at end of file
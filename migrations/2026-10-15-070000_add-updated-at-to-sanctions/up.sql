@@ -0,0 +1,2 @@
+ALTER TABLE sanctions ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+SELECT diesel_manage_updated_at('sanctions')
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE teams DROP COLUMN max_rules
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE teams ADD COLUMN max_rules INTEGER NOT NULL DEFAULT 100
\ No newline at end of file
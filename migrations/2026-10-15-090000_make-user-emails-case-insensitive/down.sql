@@ -0,0 +1,2 @@
+DROP INDEX email;
+ALTER TABLE users ADD CONSTRAINT email UNIQUE (email)
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE users DROP CONSTRAINT email;
+CREATE UNIQUE INDEX email ON users (LOWER(email))
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE sanction_events
\ No newline at end of file
@@ -0,0 +1,13 @@
+CREATE TABLE sanction_events (
+    id UUID PRIMARY KEY,
+    sanction_id UUID NOT NULL,
+    team_id UUID NOT NULL,
+    kind VARCHAR NOT NULL,
+    actor UUID,
+    occurred_at TIMESTAMPTZ NOT NULL,
+
+    CONSTRAINT sanction_id FOREIGN KEY (sanction_id) REFERENCES sanctions (id) ON DELETE CASCADE,
+    CONSTRAINT actor FOREIGN KEY (actor) REFERENCES admins (id) ON DELETE SET NULL
+);
+
+CREATE INDEX sanction_events_sanction_id ON sanction_events (sanction_id)
\ No newline at end of file
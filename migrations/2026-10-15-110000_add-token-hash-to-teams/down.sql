@@ -0,0 +1 @@
+ALTER TABLE teams DROP COLUMN token_hash
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE teams ADD COLUMN token_hash VARCHAR
\ No newline at end of file
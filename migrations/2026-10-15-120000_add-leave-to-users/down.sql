@@ -0,0 +1 @@
+ALTER TABLE users DROP COLUMN on_leave_until
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN on_leave_until DATE
\ No newline at end of file
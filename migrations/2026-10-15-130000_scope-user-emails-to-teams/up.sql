@@ -0,0 +1,2 @@
+DROP INDEX email;
+CREATE UNIQUE INDEX email ON users (team_id, LOWER(email))
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE teams DROP COLUMN grace_days
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE teams ADD COLUMN grace_days INTEGER NOT NULL DEFAULT 0
\ No newline at end of file
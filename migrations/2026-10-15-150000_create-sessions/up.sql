@@ -0,0 +1,18 @@
+CREATE TABLE sessions (
+    id UUID PRIMARY KEY,
+    team_id UUID NOT NULL,
+    date DATE NOT NULL,
+    kind VARCHAR NOT NULL,
+
+    CONSTRAINT team_id FOREIGN KEY (team_id) REFERENCES teams (id) ON DELETE CASCADE,
+    CONSTRAINT date UNIQUE (team_id, date)
+);
+
+CREATE TABLE session_attendances (
+    session_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+
+    PRIMARY KEY (session_id, user_id),
+    CONSTRAINT session_id FOREIGN KEY (session_id) REFERENCES sessions (id) ON DELETE CASCADE,
+    CONSTRAINT user_id FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+)
\ No newline at end of file
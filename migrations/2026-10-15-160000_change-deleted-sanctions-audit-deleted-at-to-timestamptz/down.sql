@@ -0,0 +1,3 @@
+ALTER TABLE deleted_sanctions_audit
+    ALTER COLUMN deleted_at TYPE TIMESTAMP USING deleted_at::TIMESTAMP,
+    ALTER COLUMN deleted_at SET DEFAULT CURRENT_TIMESTAMP
\ No newline at end of file
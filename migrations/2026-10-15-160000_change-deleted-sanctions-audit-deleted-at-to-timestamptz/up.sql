@@ -0,0 +1,3 @@
+ALTER TABLE deleted_sanctions_audit
+    ALTER COLUMN deleted_at TYPE TIMESTAMPTZ USING deleted_at::TIMESTAMPTZ,
+    ALTER COLUMN deleted_at SET DEFAULT now()
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE teams ADD COLUMN admin_password VARCHAR NOT NULL DEFAULT '';
+ALTER TABLE teams ALTER COLUMN admin_password DROP DEFAULT
\ No newline at end of file
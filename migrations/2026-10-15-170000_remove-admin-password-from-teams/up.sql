@@ -0,0 +1 @@
+ALTER TABLE teams DROP COLUMN admin_password
\ No newline at end of file
@@ -0,0 +1,50 @@
+use uuid::Uuid;
+
+use super::models::Admin;
+use crate::database::postgres::DbError;
+
+pub trait AdminsDb {
+    /// The password must have been validated by the caller.
+    fn add_admin(&self, team_id: Uuid, username: &str, password: &str) -> Result<Admin, DbError>;
+
+    fn verify_admin(&self, team_id: Uuid, username: &str, password: &str)
+        -> Result<Admin, DbError>;
+
+    fn remove_admin(&self, team_id: Uuid, username: &str) -> Result<Admin, DbError>;
+
+    fn set_admin_user(
+        &self,
+        team_id: Uuid,
+        username: &str,
+        user_id: Option<Uuid>,
+    ) -> Result<Admin, DbError>;
+
+    fn find_teams_without_admin(&self) -> Result<Vec<Uuid>, DbError>;
+
+    fn set_two_factor(
+        &self,
+        team_id: Uuid,
+        username: &str,
+        enabled: bool,
+        email: Option<&str>,
+    ) -> Result<Admin, DbError>;
+
+    /// Refused when the two-factor login isn't enabled.
+    fn request_login_code(
+        &self,
+        team_id: Uuid,
+        username: &str,
+        password: &str,
+    ) -> Result<(Admin, String), DbError>;
+
+    /// Consumes the code, so that it can't be given twice.
+    fn verify_login_code(
+        &self,
+        team_id: Uuid,
+        username: &str,
+        code: &str,
+    ) -> Result<Admin, DbError>;
+
+    /// The usernames are unique across teams.
+    fn find_team_by_admin_username(&self, username: &str) -> Result<Uuid, DbError>;
+}
@@ -0,0 +1,4 @@
+pub mod interface;
+pub mod models;
+pub mod postgres;
+pub mod utils;
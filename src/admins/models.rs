@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use diesel::{Insertable, Queryable};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::database::schema::{admin_login_codes, admins};
+
+#[derive(Debug, Clone, Queryable, Insertable, PartialEq, Serialize, Default)]
+#[table_name = "admins"]
+pub struct Admin {
+    pub id: Uuid,
+    pub team_id: Uuid,
+    pub username: String,
+    #[serde(skip_serializing)]
+    pub password_hash: String,
+    pub user_id: Option<Uuid>,
+    pub email: Option<String>,
+    pub two_factor_enabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewAdmin {
+    pub username: String,
+    pub password: String,
+}
+
+/// Only the hash of the code is stored.
+#[derive(Debug, Clone, Queryable, Insertable, PartialEq)]
+#[table_name = "admin_login_codes"]
+pub struct AdminLoginCode {
+    pub id: Uuid,
+    pub admin_id: Uuid,
+    pub code_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,612 @@
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use std::ops::Deref;
+use uuid::Uuid;
+
+use super::{
+    interface::AdminsDb,
+    models::{Admin, AdminLoginCode},
+    utils::{
+        login_code::{generate_code, CODE_VALIDITY_MINUTES},
+        password::{hash_password, verify_password},
+    },
+};
+use crate::database::{
+    postgres::{DbConnection, DbError},
+    schema::{admin_login_codes, admins, teams, users},
+};
+use crate::users::models::is_valid_email;
+
+pub(crate) fn insert_admin(
+    conn: &DbConnection,
+    team_id: Uuid,
+    username: &str,
+    password: &str,
+) -> Result<Admin, DbError> {
+    let admin: Admin = diesel::insert_into(admins::table)
+        .values(&Admin {
+            id: Uuid::new_v4(),
+            team_id,
+            username: String::from(username),
+            password_hash: hash_password(password)?,
+            user_id: None,
+            email: None,
+            two_factor_enabled: false,
+        })
+        .get_result(conn.deref())?;
+
+    Ok(admin)
+}
+
+impl AdminsDb for DbConnection {
+    fn add_admin(&self, team_id: Uuid, username: &str, password: &str) -> Result<Admin, DbError> {
+        insert_admin(self, team_id, username, password)
+    }
+
+    fn verify_admin(
+        &self,
+        team_id: Uuid,
+        username: &str,
+        password: &str,
+    ) -> Result<Admin, DbError> {
+        let admin: Admin = admins::table
+            .filter(
+                admins::team_id
+                    .eq(team_id)
+                    .and(admins::username.eq(username)),
+            )
+            .get_result(self.deref())?;
+
+        if verify_password(password, &admin.password_hash) {
+            Ok(admin)
+        } else {
+            Err(DbError::NotFound)
+        }
+    }
+
+    fn remove_admin(&self, team_id: Uuid, username: &str) -> Result<Admin, DbError> {
+        self.deref().transaction::<_, DbError, _>(|| {
+            teams::table
+                .find(team_id)
+                .select(teams::id)
+                .for_update()
+                .get_result::<Uuid>(self.deref())?;
+
+            let admins_count: i64 = admins::table
+                .filter(admins::team_id.eq(team_id))
+                .count()
+                .get_result(self.deref())?;
+
+            let admin: Admin = admins::table
+                .filter(
+                    admins::team_id
+                        .eq(team_id)
+                        .and(admins::username.eq(username)),
+                )
+                .get_result(self.deref())?;
+
+            if admins_count <= 1 {
+                return Err(DbError::Forbidden(String::from(
+                    "A team must keep at least one admin",
+                )));
+            }
+
+            diesel::delete(admins::table.find(admin.id)).execute(self.deref())?;
+
+            Ok(admin)
+        })
+    }
+
+    fn set_admin_user(
+        &self,
+        team_id: Uuid,
+        username: &str,
+        user_id: Option<Uuid>,
+    ) -> Result<Admin, DbError> {
+        if let Some(user_id) = user_id {
+            users::table
+                .filter(users::team_id.eq(team_id).and(users::id.eq(user_id)))
+                .select(users::id)
+                .get_result::<Uuid>(self.deref())
+                .optional()?
+                .ok_or_else(|| {
+                    DbError::ForeignKeyViolation(String::from(
+                        "The key user_id doesn't refer to anything",
+                    ))
+                })?;
+        }
+
+        let admin: Admin = diesel::update(
+            admins::table.filter(
+                admins::team_id
+                    .eq(team_id)
+                    .and(admins::username.eq(username)),
+            ),
+        )
+        .set(admins::user_id.eq(user_id))
+        .get_result(self.deref())?;
+
+        Ok(admin)
+    }
+
+    fn set_two_factor(
+        &self,
+        team_id: Uuid,
+        username: &str,
+        enabled: bool,
+        email: Option<&str>,
+    ) -> Result<Admin, DbError> {
+        match email {
+            Some(email) if !is_valid_email(email) => {
+                return Err(DbError::NotValid(String::from(
+                    "The field email must be a valid email address",
+                )));
+            }
+            None if enabled => {
+                return Err(DbError::NotValid(String::from(
+                    "The field email must be given to enable the two-factor login",
+                )));
+            }
+            _ => {}
+        }
+
+        let admin: Admin = diesel::update(
+            admins::table.filter(
+                admins::team_id
+                    .eq(team_id)
+                    .and(admins::username.eq(username)),
+            ),
+        )
+        .set((
+            admins::email.eq(email),
+            admins::two_factor_enabled.eq(enabled),
+        ))
+        .get_result(self.deref())?;
+
+        Ok(admin)
+    }
+
+    fn request_login_code(
+        &self,
+        team_id: Uuid,
+        username: &str,
+        password: &str,
+    ) -> Result<(Admin, String), DbError> {
+        let admin = self.verify_admin(team_id, username, password)?;
+
+        if !admin.two_factor_enabled {
+            return Err(DbError::Forbidden(String::from(
+                "The two-factor login is not enabled for this admin",
+            )));
+        }
+
+        let code = generate_code();
+
+        diesel::insert_into(admin_login_codes::table)
+            .values(&AdminLoginCode {
+                id: Uuid::new_v4(),
+                admin_id: admin.id,
+                code_hash: hash_password(&code)?,
+                expires_at: Utc::now() + Duration::minutes(CODE_VALIDITY_MINUTES),
+                used_at: None,
+            })
+            .execute(self.deref())?;
+
+        Ok((admin, code))
+    }
+
+    fn verify_login_code(
+        &self,
+        team_id: Uuid,
+        username: &str,
+        code: &str,
+    ) -> Result<Admin, DbError> {
+        self.deref().transaction::<_, DbError, _>(|| {
+            let admin: Admin = admins::table
+                .filter(
+                    admins::team_id
+                        .eq(team_id)
+                        .and(admins::username.eq(username)),
+                )
+                .get_result(self.deref())?;
+
+            let now = Utc::now();
+            let login_codes: Vec<AdminLoginCode> = admin_login_codes::table
+                .filter(
+                    admin_login_codes::admin_id
+                        .eq(admin.id)
+                        .and(admin_login_codes::used_at.is_null())
+                        .and(admin_login_codes::expires_at.gt(now)),
+                )
+                .for_update()
+                .get_results(self.deref())?;
+
+            let login_code = login_codes
+                .into_iter()
+                .find(|login_code| verify_password(code, &login_code.code_hash))
+                .ok_or(DbError::NotFound)?;
+
+            diesel::update(admin_login_codes::table.find(login_code.id))
+                .set(admin_login_codes::used_at.eq(now))
+                .execute(self.deref())?;
+
+            Ok(admin)
+        })
+    }
+
+    fn find_teams_without_admin(&self) -> Result<Vec<Uuid>, DbError> {
+        let team_ids: Vec<Uuid> = teams::table
+            .left_join(admins::table)
+            .filter(admins::id.is_null())
+            .select(teams::id)
+            .order_by(teams::id)
+            .get_results(self.deref())?;
+
+        Ok(team_ids)
+    }
+
+    fn find_team_by_admin_username(&self, username: &str) -> Result<Uuid, DbError> {
+        let team_id: Uuid = admins::table
+            .filter(admins::username.eq(username))
+            .select(admins::team_id)
+            .get_result(self.deref())?;
+
+        Ok(team_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::result::Error;
+
+    use super::*;
+    use crate::teams::{interface::TeamsDb, models::Team};
+    use crate::test_utils::postgres::{init_connection, unique_admin};
+    use crate::users::{interface::UsersDb, models::User};
+
+    #[test]
+    fn test_add_admin() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+
+            let admin = conn.add_admin(team_id, "john", "password").unwrap();
+
+            assert_eq!(admin.team_id, team_id);
+            assert_eq!(admin.username, String::from("john"));
+            assert_ne!(admin.password_hash, String::from("password"));
+
+            let error = conn.add_admin(team_id, "john", "password").unwrap_err();
+
+            assert_eq!(
+                error,
+                DbError::UniqueViolation(String::from(
+                    "The field username is already used by another user"
+                ))
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_verify_admin() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+
+            let john = conn.add_admin(team_id, "john", "password").unwrap();
+            let jane = conn.add_admin(team_id, "jane", "other password").unwrap();
+
+            assert_eq!(
+                conn.verify_admin(team_id, "john", "password").unwrap(),
+                john
+            );
+            assert_eq!(
+                conn.verify_admin(team_id, "jane", "other password")
+                    .unwrap(),
+                jane
+            );
+
+            let error = conn
+                .verify_admin(team_id, "john", "other password")
+                .unwrap_err();
+
+            assert_eq!(error, DbError::NotFound);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_remove_admin() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let first_admin = unique_admin();
+            let team_id = conn.create_team(&Team::default(), &first_admin).unwrap().id;
+
+            let john = conn.add_admin(team_id, "john", "password").unwrap();
+            let jane = conn.add_admin(team_id, "jane", "password").unwrap();
+
+            assert_eq!(conn.remove_admin(team_id, "jane").unwrap(), jane);
+            assert_eq!(conn.remove_admin(team_id, "john").unwrap(), john);
+
+            let error = conn
+                .remove_admin(team_id, &first_admin.username)
+                .unwrap_err();
+
+            assert_eq!(
+                error,
+                DbError::Forbidden(String::from("A team must keep at least one admin"))
+            );
+            assert_eq!(
+                conn.verify_admin(team_id, "jane", "password").unwrap_err(),
+                DbError::NotFound
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_find_teams_without_admin() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+            // The teams created before the admins existed have none.
+            let lonely_team_id = diesel::insert_into(teams::table)
+                .values(&Team {
+                    id: Uuid::new_v4(),
+                    name: String::from("Lonely"),
+                    ..Default::default()
+                })
+                .returning(teams::id)
+                .get_result::<Uuid>(conn.deref())
+                .unwrap();
+
+            let team_ids = conn.find_teams_without_admin().unwrap();
+
+            assert!(team_ids.contains(&lonely_team_id));
+            assert!(!team_ids.contains(&team_id));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_find_team_by_admin_username() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+            let other_team_id = conn
+                .create_team(
+                    &Team {
+                        id: Uuid::new_v4(),
+                        name: String::from("Other"),
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+
+            conn.add_admin(team_id, "john", "password").unwrap();
+            conn.add_admin(other_team_id, "jane", "password").unwrap();
+
+            assert_eq!(conn.find_team_by_admin_username("john").unwrap(), team_id);
+            assert_eq!(
+                conn.find_team_by_admin_username("jane").unwrap(),
+                other_team_id
+            );
+            assert_eq!(
+                conn.find_team_by_admin_username("jack").unwrap_err(),
+                DbError::NotFound
+            );
+            assert_eq!(
+                conn.add_admin(other_team_id, "john", "password")
+                    .unwrap_err(),
+                DbError::UniqueViolation(String::from(
+                    "The field username is already used by another user"
+                ))
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_set_admin_user() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            conn.add_admin(team_id, "john", "password").unwrap();
+
+            let admin = conn.set_admin_user(team_id, "john", Some(user_id)).unwrap();
+
+            assert_eq!(admin.user_id, Some(user_id));
+            assert_eq!(
+                conn.set_admin_user(team_id, "john", None).unwrap().user_id,
+                None
+            );
+            match conn
+                .set_admin_user(team_id, "john", Some(Uuid::new_v4()))
+                .unwrap_err()
+            {
+                DbError::ForeignKeyViolation(_) => {}
+                err => panic!("Unexpected error {:?}", err),
+            }
+            assert_eq!(
+                conn.set_admin_user(team_id, "jack", None).unwrap_err(),
+                DbError::NotFound
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_set_two_factor() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+            conn.add_admin(team_id, "john", "password").unwrap();
+
+            assert_eq!(
+                conn.set_two_factor(team_id, "john", true, None)
+                    .unwrap_err(),
+                DbError::NotValid(String::from(
+                    "The field email must be given to enable the two-factor login"
+                ))
+            );
+            assert_eq!(
+                conn.set_two_factor(team_id, "john", true, Some("john"))
+                    .unwrap_err(),
+                DbError::NotValid(String::from(
+                    "The field email must be a valid email address"
+                ))
+            );
+
+            let john = conn
+                .set_two_factor(team_id, "john", true, Some("john@example.com"))
+                .unwrap();
+
+            assert!(john.two_factor_enabled);
+            assert_eq!(john.email, Some(String::from("john@example.com")));
+
+            // The email is kept for when the two-factor login is enabled again.
+            let john = conn
+                .set_two_factor(team_id, "john", false, Some("john@example.com"))
+                .unwrap();
+
+            assert!(!john.two_factor_enabled);
+            assert_eq!(john.email, Some(String::from("john@example.com")));
+            assert_eq!(
+                conn.request_login_code(team_id, "john", "password")
+                    .unwrap_err(),
+                DbError::Forbidden(String::from(
+                    "The two-factor login is not enabled for this admin"
+                ))
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_verify_login_code() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+            conn.add_admin(team_id, "john", "password").unwrap();
+
+            assert_eq!(
+                conn.request_login_code(team_id, "john", "password")
+                    .unwrap_err(),
+                DbError::Forbidden(String::from(
+                    "The two-factor login is not enabled for this admin"
+                ))
+            );
+
+            let john = conn
+                .set_two_factor(team_id, "john", true, Some("john@example.com"))
+                .unwrap();
+            assert!(john.two_factor_enabled);
+
+            assert_eq!(
+                conn.request_login_code(team_id, "john", "other password")
+                    .unwrap_err(),
+                DbError::NotFound
+            );
+
+            let (admin, code) = conn
+                .request_login_code(team_id, "john", "password")
+                .unwrap();
+
+            assert_eq!(admin, john);
+            assert_eq!(
+                conn.verify_login_code(team_id, "john", "not a code")
+                    .unwrap_err(),
+                DbError::NotFound
+            );
+            assert_eq!(
+                conn.verify_login_code(team_id, "john", &code).unwrap(),
+                john
+            );
+
+            // A code can only be given once.
+            assert_eq!(
+                conn.verify_login_code(team_id, "john", &code).unwrap_err(),
+                DbError::NotFound
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_verify_expired_login_code() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+            conn.add_admin(team_id, "john", "password").unwrap();
+            conn.set_two_factor(team_id, "john", true, Some("john@example.com"))
+                .unwrap();
+
+            let (_, code) = conn
+                .request_login_code(team_id, "john", "password")
+                .unwrap();
+
+            diesel::update(admin_login_codes::table)
+                .set(admin_login_codes::expires_at.eq(Utc::now() - Duration::minutes(1)))
+                .execute(conn.deref())
+                .unwrap();
+
+            assert_eq!(
+                conn.verify_login_code(team_id, "john", &code).unwrap_err(),
+                DbError::NotFound
+            );
+
+            Ok(())
+        });
+    }
+}
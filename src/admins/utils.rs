@@ -0,0 +1,160 @@
+pub mod password {
+    use bcrypt::{hash, verify, BcryptError, DEFAULT_COST};
+
+    use crate::api::models::ValidationError;
+
+    // Hashing with the default cost takes seconds in debug builds.
+    const HASH_COST: u32 = if cfg!(test) { 4 } else { DEFAULT_COST };
+
+    pub const DEFAULT_MIN_LENGTH: usize = 8;
+
+    pub fn validate_password(
+        field: &str,
+        password: &str,
+        min_length: usize,
+    ) -> Result<(), ValidationError> {
+        if password.chars().count() < min_length {
+            return Err(ValidationError {
+                field: String::from(field),
+                reason: format!("must be at least {} characters long", min_length),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn hash_password(password: &str) -> Result<String, BcryptError> {
+        hash(password, HASH_COST)
+    }
+
+    pub fn verify_password(password: &str, password_hash: &str) -> bool {
+        verify(password, password_hash).unwrap_or(false)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_verify_password() {
+            let password_hash = hash_password("password").unwrap();
+
+            assert!(verify_password("password", &password_hash));
+            assert!(!verify_password("other password", &password_hash));
+            assert!(!verify_password("password", "not a hash"));
+        }
+
+        #[test]
+        fn test_validate_password() {
+            assert!(validate_password("password", "password", DEFAULT_MIN_LENGTH).is_ok());
+            assert_eq!(
+                validate_password("password", "short", DEFAULT_MIN_LENGTH).unwrap_err(),
+                ValidationError {
+                    field: String::from("password"),
+                    reason: format!("must be at least {} characters long", DEFAULT_MIN_LENGTH),
+                }
+            );
+            assert!(validate_password("password", "password", 12).is_err());
+        }
+    }
+}
+
+pub mod login_code {
+    use std::io;
+    use uuid::Uuid;
+
+    const CODE_LENGTH: usize = 6;
+
+    pub const CODE_VALIDITY_MINUTES: i64 = 10;
+
+    pub fn generate_code() -> String {
+        let bytes = Uuid::new_v4();
+        let bytes = bytes.as_bytes();
+        let number = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+        format!(
+            "{:0width$}",
+            number % 10_u32.pow(CODE_LENGTH as u32),
+            width = CODE_LENGTH
+        )
+    }
+
+    fn message(email: &str, code: &str) -> String {
+        format!(
+            "To: {}\nSubject: Your login code\n\nYour caisse noire login code is {}. It \
+             expires in {} minutes.\n",
+            email, code, CODE_VALIDITY_MINUTES
+        )
+    }
+
+    #[cfg(not(test))]
+    pub fn send_code(email: &str, code: &str) -> io::Result<()> {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        let mut sendmail = Command::new("sendmail")
+            .arg("-t")
+            .stdin(Stdio::piped())
+            .spawn()?;
+        if let Some(stdin) = sendmail.stdin.as_mut() {
+            stdin.write_all(message(email, code).as_bytes())?;
+        }
+
+        if sendmail.wait()?.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other("sendmail failed"))
+        }
+    }
+
+    #[cfg(test)]
+    thread_local! {
+        pub static SENT_MESSAGES: std::cell::RefCell<Vec<String>> = Default::default();
+    }
+
+    #[cfg(test)]
+    pub fn send_code(email: &str, code: &str) -> io::Result<()> {
+        SENT_MESSAGES.with(|messages| messages.borrow_mut().push(message(email, code)));
+
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_generate_code() {
+            let code = generate_code();
+
+            assert_eq!(code.len(), CODE_LENGTH);
+            assert!(code.chars().all(|c| c.is_ascii_digit()));
+        }
+    }
+}
+
+pub mod token {
+    use uuid::Uuid;
+
+    pub fn generate_token() -> String {
+        format!(
+            "{}{}",
+            Uuid::new_v4().to_simple(),
+            Uuid::new_v4().to_simple()
+        )
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_generate_token() {
+            let token = generate_token();
+
+            assert_eq!(token.len(), 64);
+            assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+            assert_ne!(generate_token(), token);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use rouille::{Request, Response};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::routes::route_pattern;
+
+const UNKNOWN_ROUTE: &str = "unknown";
+
+#[derive(Debug, Default, Clone, Copy)]
+struct RouteTimings {
+    count: u64,
+    total: Duration,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteStats {
+    pub count: u64,
+    pub average_ms: f64,
+}
+
+#[derive(Clone, Default)]
+pub struct Metrics {
+    timings: Arc<Mutex<BTreeMap<String, RouteTimings>>>,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Default::default()
+    }
+
+    fn record(&self, route: String, duration: Duration) {
+        let mut timings = self.timings.lock().unwrap_or_else(|err| err.into_inner());
+        let route_timings = timings.entry(route).or_default();
+
+        route_timings.count += 1;
+        route_timings.total += duration;
+    }
+
+    pub fn stats(&self) -> BTreeMap<String, RouteStats> {
+        let timings = self.timings.lock().unwrap_or_else(|err| err.into_inner());
+
+        timings
+            .iter()
+            .map(|(route, route_timings)| {
+                (
+                    route.clone(),
+                    RouteStats {
+                        count: route_timings.count,
+                        average_ms: route_timings.total.as_secs_f64() * 1000.0
+                            / route_timings.count as f64,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    pub fn handle_request<F>(&self, request: &Request, handler: F) -> Response
+    where
+        F: FnOnce() -> Response,
+    {
+        if request.method() == "GET" && request.url() == "/metrics" {
+            return Response::json(&self.stats());
+        }
+
+        let start = Instant::now();
+        let response = handler();
+
+        self.record(
+            format!(
+                "{} {}",
+                request.method(),
+                route_pattern(&request.url()).unwrap_or(UNKNOWN_ROUTE)
+            ),
+            start.elapsed(),
+        );
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+    use std::io::Read;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::api::{models::test_utils::RequestBuilder, routes::handle_request};
+    use crate::test_utils::routes::DbMock;
+
+    #[test]
+    fn test_metrics() {
+        let metrics = Metrics::new();
+
+        for _ in 0..2 {
+            let request = RequestBuilder::get(format!("/teams/{}", Uuid::new_v4()));
+            let response =
+                metrics.handle_request(&request, || handle_request(&request, &DbMock::default()));
+
+            assert_eq!(response.status_code, 200);
+        }
+
+        let response = metrics
+            .handle_request(&RequestBuilder::get(String::from("/metrics")), || {
+                panic!("The metrics must not be forwarded to the handler")
+            });
+        let (mut reader, _) = response.data.into_reader_and_size();
+        let mut body = String::new();
+        reader.read_to_string(&mut body).unwrap();
+        let stats: Value = serde_json::from_str(&body).unwrap();
+
+        assert_eq!(stats["GET /teams/{}"]["count"], 2);
+        assert!(stats["GET /teams/{}"]["averageMs"].as_f64().unwrap() >= 0.0);
+        assert_eq!(metrics.stats().len(), 1);
+    }
+}
@@ -1,2 +1,7 @@
+#[cfg(feature = "metrics")]
+pub mod metrics;
 pub mod models;
 pub mod routes;
+pub mod server;
+pub mod utils;
+pub mod version;
@@ -1,5 +1,7 @@
 use rouille::input::json::JsonError;
 use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
 
 use crate::database::postgres::DbError;
 use crate::sanctions::{
@@ -11,6 +13,8 @@ use crate::sanctions::{
 pub struct ErrorResponse {
     pub kind: ErrorKind,
     pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<HashMap<String, Vec<String>>>,
 }
 
 impl ErrorResponse {
@@ -18,6 +22,39 @@ impl ErrorResponse {
         ErrorResponse {
             kind: ErrorKind::NotFound,
             description: String::from("Not found"),
+            fields: None,
+        }
+    }
+
+    pub fn resource_not_found(resource: Resource) -> ErrorResponse {
+        ErrorResponse {
+            kind: ErrorKind::NotFound,
+            description: format!("{} not found", resource),
+            fields: None,
+        }
+    }
+
+    pub fn unauthorized() -> ErrorResponse {
+        ErrorResponse {
+            kind: ErrorKind::Unauthorized,
+            description: String::from("The credentials are not valid"),
+            fields: None,
+        }
+    }
+
+    pub fn login_code_required() -> ErrorResponse {
+        ErrorResponse {
+            kind: ErrorKind::Unauthorized,
+            description: String::from("A login code is required"),
+            fields: None,
+        }
+    }
+
+    pub fn bad_request(description: String) -> ErrorResponse {
+        ErrorResponse {
+            kind: ErrorKind::BadRequest,
+            description,
+            fields: None,
         }
     }
 
@@ -25,10 +62,41 @@ impl ErrorResponse {
         ErrorResponse {
             kind: ErrorKind::BadParameter,
             description,
+            fields: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resource {
+    Team,
+    User,
+    Sanction,
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Resource::Team => write!(f, "Team"),
+            Resource::User => write!(f, "User"),
+            Resource::Sanction => write!(f, "Sanction"),
         }
     }
 }
 
+pub trait NotFoundAs<T> {
+    fn not_found_as(self, resource: Resource) -> Result<T, ErrorResponse>;
+}
+
+impl<T> NotFoundAs<T> for Result<T, DbError> {
+    fn not_found_as(self, resource: Resource) -> Result<T, ErrorResponse> {
+        self.map_err(|error| match error {
+            DbError::NotFound => ErrorResponse::resource_not_found(resource),
+            error => error.into(),
+        })
+    }
+}
+
 #[derive(Debug, Serialize, PartialEq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum ErrorKind {
@@ -36,10 +104,15 @@ pub enum ErrorKind {
     Unknown,
     NotFound,
     Json,
+    InvalidValue,
     BadReference,
     DuplicatedField,
     BadParameter,
     NotValid,
+    Forbidden,
+    BadRequest,
+    Unauthorized,
+    MethodNotAllowed,
 }
 
 impl ErrorKind {
@@ -49,10 +122,15 @@ impl ErrorKind {
             ErrorKind::Unknown => 500,
             ErrorKind::NotFound => 404,
             ErrorKind::Json => 400,
+            ErrorKind::InvalidValue => 422,
             ErrorKind::BadReference => 400,
             ErrorKind::DuplicatedField => 400,
             ErrorKind::BadParameter => 400,
-            ErrorKind::NotValid => 400,
+            ErrorKind::NotValid => 422,
+            ErrorKind::Forbidden => 403,
+            ErrorKind::BadRequest => 400,
+            ErrorKind::Unauthorized => 401,
+            ErrorKind::MethodNotAllowed => 405,
         }
     }
 }
@@ -69,35 +147,116 @@ impl From<DbError> for ErrorResponse {
             DbError::NotFound => ErrorResponse {
                 kind: ErrorKind::NotFound,
                 description: String::from("Not found"),
+                fields: None,
             },
             DbError::Unknown => ErrorResponse {
                 kind: ErrorKind::Unknown,
                 description: String::from("An internal error occured"),
+                fields: None,
             },
             DbError::ServiceUnavailable => ErrorResponse {
                 kind: ErrorKind::ServiceUnavailable,
                 description: String::from("The service is currently unavailable"),
+                fields: None,
             },
             DbError::ForeignKeyViolation(description) => ErrorResponse {
                 kind: ErrorKind::BadReference,
                 description,
+                fields: None,
             },
             DbError::UniqueViolation(description) => ErrorResponse {
                 kind: ErrorKind::DuplicatedField,
                 description,
+                fields: None,
+            },
+            DbError::Forbidden(description) => ErrorResponse {
+                kind: ErrorKind::Forbidden,
+                description,
+                fields: None,
+            },
+            DbError::NotValid(description) => ErrorResponse {
+                kind: ErrorKind::NotValid,
+                description,
+                fields: None,
             },
         }
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub struct ValidationError {
+    pub field: String,
+    pub reason: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The field {} {}", self.field, self.reason)
+    }
+}
+
+#[derive(Debug, PartialEq, Default)]
+pub struct ValidationErrors(pub Vec<ValidationError>);
+
+impl ValidationErrors {
+    pub fn push(&mut self, error: ValidationError) {
+        self.0.push(error);
+    }
+
+    pub fn into_result(self) -> Result<(), ValidationErrors> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+
+    pub fn by_field(&self) -> HashMap<String, Vec<String>> {
+        let mut fields: HashMap<String, Vec<String>> = HashMap::new();
+        for error in &self.0 {
+            fields
+                .entry(error.field.clone())
+                .or_default()
+                .push(error.reason.clone());
+        }
+
+        fields
+    }
+}
+
+impl From<ValidationError> for ValidationErrors {
+    fn from(error: ValidationError) -> Self {
+        ValidationErrors(vec![error])
+    }
+}
+
+impl From<ValidationErrors> for ErrorResponse {
+    fn from(errors: ValidationErrors) -> Self {
+        ErrorResponse {
+            kind: ErrorKind::NotValid,
+            description: errors
+                .0
+                .iter()
+                .map(ValidationError::to_string)
+                .collect::<Vec<String>>()
+                .join(", "),
+            fields: Some(errors.by_field()),
+        }
+    }
+}
+
+impl From<ValidationError> for ErrorResponse {
+    fn from(error: ValidationError) -> Self {
+        ValidationErrors::from(error).into()
+    }
+}
+
 impl From<SanctionInfoError> for ErrorResponse {
     fn from(error: SanctionInfoError) -> Self {
         ErrorResponse {
             kind: ErrorKind::NotValid,
-            description: format!(
-                "The rule {} has the kind {} which can't be associated with {}",
-                error.associated_rule_name, error.associated_rule_kind, error.extra_info
-            ),
+            description: error.to_string(),
+            fields: None,
         }
     }
 }
@@ -129,11 +288,42 @@ impl From<ParameterError> for ErrorResponse {
     }
 }
 
+const UNKNOWN_VARIANT_ERROR: &str = "unknown variant";
+
 impl From<JsonError> for ErrorResponse {
     fn from(error: JsonError) -> ErrorResponse {
+        let description = match error {
+            JsonError::ParseError(error)
+                if error.is_data() && error.to_string().starts_with(UNKNOWN_VARIANT_ERROR) =>
+            {
+                return ErrorResponse {
+                    kind: ErrorKind::InvalidValue,
+                    description: format!("The request body has an invalid value: {}", error),
+                    fields: None,
+                };
+            }
+            JsonError::WrongContentType => {
+                String::from("The request body must be sent as application/json")
+            }
+            JsonError::ParseError(error) if error.is_data() => format!(
+                "The request body doesn't have the expected format (line {}, column {})",
+                error.line(),
+                error.column()
+            ),
+            JsonError::ParseError(error) => format!(
+                "The request body is not valid JSON (line {}, column {})",
+                error.line(),
+                error.column()
+            ),
+            JsonError::BodyAlreadyExtracted | JsonError::IoError(_) => {
+                String::from("The request body could not be read")
+            }
+        };
+
         ErrorResponse {
             kind: ErrorKind::Json,
-            description: error.to_string(),
+            description,
+            fields: None,
         }
     }
 }
@@ -164,6 +354,28 @@ pub mod test_utils {
             )
         }
 
+        pub fn put(url: String, data: &Value) -> Request {
+            let serialized_data = serde_json::to_vec(data).expect("Failed to serialize data");
+
+            Request::fake_http(
+                "PUT",
+                url,
+                vec![RequestBuilder::json_header()],
+                serialized_data,
+            )
+        }
+
+        pub fn patch(url: String, data: &Value) -> Request {
+            let serialized_data = serde_json::to_vec(data).expect("Failed to serialize data");
+
+            Request::fake_http(
+                "PATCH",
+                url,
+                vec![RequestBuilder::json_header()],
+                serialized_data,
+            )
+        }
+
         pub fn delete(url: String) -> Request {
             Request::fake_http("DELETE", url, vec![RequestBuilder::json_header()], vec![])
         }
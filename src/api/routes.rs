@@ -1,11 +1,105 @@
-use super::models::ErrorResponse;
+use super::models::{ErrorKind, ErrorResponse};
+use super::utils::request_id;
+use super::version::handle_request as version_request_handling;
+use crate::admins::interface::AdminsDb;
 use crate::sanctions::{
     interface::SanctionsDb, routes::handle_request as sanctions_request_handling,
 };
 use crate::teams::{interface::TeamsDb, routes::handle_request as teams_request_handling};
-use crate::users::{interface::UsersDb, routes::handle_request as users_request_handling};
-use rouille::{find_route, Request, Response};
+use crate::users::{
+    interface::UsersDb,
+    routes::{handle_request as users_request_handling, ResultWrapper as UsersResultWrapper},
+};
+use deflate::deflate_bytes_gzip;
+use log::info;
+use rouille::{
+    content_encoding::accepted_content_encodings, find_route, Request, Response, ResponseBody,
+};
 use serde::Serialize;
+use std::io::Read;
+
+const COMPRESSION_THRESHOLD: usize = 1024;
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct RouteSpec {
+    pub pattern: &'static str,
+    pub methods: &'static [&'static str],
+}
+
+const fn route(pattern: &'static str, methods: &'static [&'static str]) -> RouteSpec {
+    RouteSpec { pattern, methods }
+}
+
+/// The requests to the paths not listed here are not dispatched.
+pub const ROUTES: &[RouteSpec] = &[
+    route("/routes", &["GET"]),
+    route("/version", &["GET"]),
+    route("/login", &["POST"]),
+    route("/teams", &["POST"]),
+    route("/teams/{}", &["GET", "POST"]),
+    route("/teams/{}/login", &["POST"]),
+    route("/teams/{}/login/code", &["POST"]),
+    route("/teams/{}/login/verify", &["POST"]),
+    route("/teams/{}/token", &["POST"]),
+    route("/teams/{}/rules", &["POST", "PUT"]),
+    route("/teams/{}/users", &["GET", "POST"]),
+    route("/teams/{}/users/{}", &["GET", "POST", "PATCH"]),
+    route("/teams/{}/sanctions", &["GET", "POST"]),
+    route("/teams/{}/sanctions/{}", &["DELETE"]),
+    route("/teams/{}/leaderboard", &["GET"]),
+];
+
+#[cfg(feature = "metrics")]
+const METRICS_ROUTES: &[RouteSpec] = &[route("/metrics", &["GET"])];
+#[cfg(not(feature = "metrics"))]
+const METRICS_ROUTES: &[RouteSpec] = &[];
+
+pub fn routes() -> impl Iterator<Item = &'static RouteSpec> {
+    ROUTES.iter().chain(METRICS_ROUTES)
+}
+
+fn find_route(url: &str) -> Option<&'static RouteSpec> {
+    let segments: Vec<&str> = url.split('/').collect();
+
+    routes().find(|route| {
+        let pattern_segments: Vec<&str> = route.pattern.split('/').collect();
+
+        pattern_segments.len() == segments.len()
+            && pattern_segments
+                .iter()
+                .zip(segments.iter())
+                .all(|(pattern_segment, segment)| {
+                    *pattern_segment == "{}" || pattern_segment == segment
+                })
+    })
+}
+
+fn allowed_methods(url: &str) -> Option<&'static [&'static str]> {
+    find_route(url).map(|route| route.methods)
+}
+
+pub fn route_pattern(url: &str) -> Option<&'static str> {
+    find_route(url).map(|route| route.pattern)
+}
+
+fn handle_routes_request(request: &Request) -> Result<Vec<&'static RouteSpec>, ErrorResponse> {
+    if request.method() == "GET" && request.url() == "/routes" {
+        Ok(routes().collect())
+    } else {
+        Err(ErrorResponse::not_found())
+    }
+}
+
+fn method_not_allowed(method: &str, methods: &[&str]) -> Response {
+    let error_response = ErrorResponse {
+        kind: ErrorKind::MethodNotAllowed,
+        description: format!("The method {} is not allowed on this route", method),
+        fields: None,
+    };
+    let response: Response = error_response.into();
+
+    response.with_additional_header("Allow", methods.join(", "))
+}
 
 fn extract_response<T>(result: Result<T, ErrorResponse>) -> Response
 where
@@ -17,16 +111,450 @@ where
     }
 }
 
-pub fn handle_request<T>(request: &Request, db: &T) -> Response
+fn extract_users_response(
+    request: &Request,
+    result: Result<UsersResultWrapper, ErrorResponse>,
+) -> Response {
+    let link_header = result
+        .as_ref()
+        .ok()
+        .and_then(|wrapper| wrapper.link_header(&request.url()));
+
+    match link_header {
+        Some(link_header) => extract_response(result).with_additional_header("Link", link_header),
+        None => extract_response(result),
+    }
+}
+
+pub fn handle_request<T>(request: &Request, db: &T, password_min_length: usize) -> Response
 where
-    T: TeamsDb + UsersDb + SanctionsDb,
+    T: AdminsDb + TeamsDb + UsersDb + SanctionsDb,
 {
-    match request.method() {
-        "OPTIONS" => Response::empty_204(),
-        _ => find_route!(
-            extract_response(teams_request_handling(request, db)),
-            extract_response(users_request_handling(request, db)),
+    match (request.method(), allowed_methods(&request.url())) {
+        ("OPTIONS", _) => Response::empty_204(),
+        (_, None) => ErrorResponse::not_found().into(),
+        (method, Some(methods)) if !methods.contains(&method) => {
+            method_not_allowed(method, methods)
+        }
+        (_, Some(_)) => find_route!(
+            extract_response(handle_routes_request(request)),
+            extract_response(version_request_handling(request)),
+            extract_response(teams_request_handling(request, db, password_min_length)),
+            extract_users_response(request, users_request_handling(request, db)),
             extract_response(sanctions_request_handling(request, db))
         ),
     }
 }
+
+pub fn trace_request<F>(request: &Request, handler: F) -> Response
+where
+    F: FnOnce(&str) -> Response,
+{
+    let request_id = request_id(request);
+    let response = handler(&request_id);
+
+    info!(
+        "[{}] {} {} -> {}",
+        request_id,
+        request.method(),
+        request.raw_url(),
+        response.status_code
+    );
+
+    response.with_unique_header("X-Request-Id", request_id)
+}
+
+pub fn compress_response(request: &Request, response: Response) -> Response {
+    let accepts_gzip = accepted_content_encodings(request)
+        .any(|encoding| encoding.split(';').next() == Some("gzip"));
+    let is_encoded = response
+        .headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("Content-Encoding"));
+
+    if !accepts_gzip || is_encoded {
+        return response;
+    }
+
+    let (mut reader, size) = response.data.into_reader_and_size();
+    let mut body = Vec::with_capacity(size.unwrap_or(0));
+    if reader.read_to_end(&mut body).is_err() {
+        let error_response = ErrorResponse {
+            kind: ErrorKind::Unknown,
+            description: "The response body could not be read".to_string(),
+            fields: None,
+        };
+        return error_response.into();
+    }
+
+    if body.len() < COMPRESSION_THRESHOLD {
+        return Response {
+            data: ResponseBody::from_data(body),
+            ..response
+        };
+    }
+
+    Response {
+        data: ResponseBody::from_data(deflate_bytes_gzip(&body)),
+        ..response
+    }
+    .with_unique_header("Content-Encoding", "gzip")
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::read::GzDecoder;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::admins::utils::password::DEFAULT_MIN_LENGTH;
+    use crate::api::models::test_utils::RequestBuilder;
+    use crate::test_utils::routes::DbMock;
+
+    fn header<'a>(response: &'a Response, name: &str) -> Option<&'a str> {
+        response
+            .headers
+            .iter()
+            .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_ref())
+    }
+
+    #[test]
+    fn test_method_not_allowed() {
+        let response = handle_request(
+            &RequestBuilder::post(
+                format!("/teams/{}/sanctions/{}", Uuid::new_v4(), Uuid::new_v4()),
+                &json!({}),
+            ),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
+        );
+
+        assert_eq!(response.status_code, 405);
+        assert_eq!(header(&response, "Allow"), Some("DELETE"));
+
+        let response = handle_request(
+            &RequestBuilder::delete(format!("/teams/{}/users", Uuid::new_v4())),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
+        );
+
+        assert_eq!(response.status_code, 405);
+        assert_eq!(header(&response, "Allow"), Some("GET, POST"));
+    }
+
+    #[test]
+    fn test_trace_request_generates_id() {
+        let request = RequestBuilder::get(String::from("/unknown"));
+        let mut handled_id = String::new();
+        let response = trace_request(&request, |request_id| {
+            handled_id = request_id.to_string();
+            handle_request(&request, &DbMock::default(), DEFAULT_MIN_LENGTH)
+        });
+
+        assert!(Uuid::parse_str(&handled_id).is_ok());
+        assert_eq!(header(&response, "X-Request-Id"), Some(handled_id.as_str()));
+    }
+
+    #[test]
+    fn test_trace_request_preserves_inbound_id() {
+        let request = Request::fake_http(
+            "GET",
+            "/unknown",
+            vec![("X-Request-Id".to_owned(), "abc-123".to_owned())],
+            vec![],
+        );
+        let response = trace_request(&request, |request_id| {
+            assert_eq!(request_id, "abc-123");
+            handle_request(&request, &DbMock::default(), DEFAULT_MIN_LENGTH)
+        });
+
+        assert_eq!(header(&response, "X-Request-Id"), Some("abc-123"));
+    }
+
+    #[test]
+    fn test_trace_request_replaces_malformed_id() {
+        let request = Request::fake_http(
+            "GET",
+            "/unknown",
+            vec![("X-Request-Id".to_owned(), "bad id\u{7f}".to_owned())],
+            vec![],
+        );
+        let response = trace_request(&request, |_| Response::empty_204());
+
+        assert_ne!(header(&response, "X-Request-Id"), Some("bad id\u{7f}"));
+        assert!(Uuid::parse_str(header(&response, "X-Request-Id").unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_routes() {
+        let response = handle_request(
+            &RequestBuilder::get(String::from("/routes")),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
+        );
+
+        assert_eq!(response.status_code, 200);
+
+        let mut body = String::new();
+        response
+            .data
+            .into_reader_and_size()
+            .0
+            .read_to_string(&mut body)
+            .unwrap();
+        let routes: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap();
+
+        assert!(routes.contains(&json!({
+            "pattern": "/teams/{}/users",
+            "methods": ["GET", "POST"],
+        })));
+        assert!(routes.contains(&json!({
+            "pattern": "/teams/{}/users/{}",
+            "methods": ["GET", "POST", "PATCH"],
+        })));
+        assert!(routes.contains(&json!({
+            "pattern": "/teams/{}/sanctions",
+            "methods": ["GET", "POST"],
+        })));
+        assert!(routes.contains(&json!({
+            "pattern": "/teams/{}/sanctions/{}",
+            "methods": ["DELETE"],
+        })));
+    }
+
+    #[test]
+    fn test_version() {
+        let response = handle_request(
+            &RequestBuilder::get(String::from("/version")),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
+        );
+
+        assert_eq!(response.status_code, 200);
+
+        let mut body = String::new();
+        response
+            .data
+            .into_reader_and_size()
+            .0
+            .read_to_string(&mut body)
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+        assert_eq!(body["version"], json!(env!("CARGO_PKG_VERSION")));
+    }
+
+    #[test]
+    fn test_unknown_route() {
+        let response = handle_request(
+            &RequestBuilder::get(String::from("/unknown")),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
+        );
+
+        assert_eq!(response.status_code, 404);
+    }
+
+    #[test]
+    fn test_paginated_users_link_header() {
+        let team_id = Uuid::new_v4();
+
+        let response = handle_request(
+            &RequestBuilder::get(format!("/teams/{}/users?limit=1&offset=1", team_id)),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
+        );
+
+        assert_eq!(response.status_code, 200);
+        assert_eq!(
+            header(&response, "Link"),
+            Some(
+                format!(
+                    "</teams/{0}/users?limit=1&offset=0>; rel=\"prev\", \
+                     </teams/{0}/users?limit=1&offset=0>; rel=\"last\"",
+                    team_id
+                )
+                .as_ref()
+            )
+        );
+
+        let response = handle_request(
+            &RequestBuilder::get(format!("/teams/{}/users", team_id)),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
+        );
+
+        assert!(header(&response, "Link").is_none());
+    }
+
+    #[test]
+    fn test_compress_response() {
+        let body = "caisse noire ".repeat(200);
+        let request = Request::fake_http(
+            "GET",
+            "/",
+            vec![("Accept-Encoding".to_string(), "br, gzip;q=0.8".to_string())],
+            vec![],
+        );
+
+        let response = compress_response(&request, Response::text(body.clone()));
+
+        assert_eq!(header(&response, "Content-Encoding"), Some("gzip"));
+
+        let (reader, _) = response.data.into_reader_and_size();
+        let mut decompressed = String::new();
+        GzDecoder::new(reader)
+            .read_to_string(&mut decompressed)
+            .unwrap();
+
+        assert_eq!(decompressed, body);
+    }
+
+    #[test]
+    fn test_compress_response_small_body() {
+        let request = Request::fake_http(
+            "GET",
+            "/",
+            vec![("Accept-Encoding".to_string(), "gzip".to_string())],
+            vec![],
+        );
+
+        let response = compress_response(&request, Response::text("caisse noire"));
+
+        assert_eq!(header(&response, "Content-Encoding"), None);
+
+        let (mut reader, _) = response.data.into_reader_and_size();
+        let mut body = String::new();
+        reader.read_to_string(&mut body).unwrap();
+
+        assert_eq!(body, "caisse noire");
+    }
+
+    #[test]
+    fn test_compress_response_gzip_not_accepted() {
+        let request = Request::fake_http("GET", "/", vec![], vec![]);
+
+        let response = compress_response(&request, Response::text("caisse noire ".repeat(200)));
+
+        assert_eq!(header(&response, "Content-Encoding"), None);
+    }
+
+    fn serve(request: &Request) -> Response {
+        #[cfg(feature = "metrics")]
+        return crate::api::metrics::Metrics::new().handle_request(request, || {
+            handle_request(request, &DbMock::default(), DEFAULT_MIN_LENGTH)
+        });
+        #[cfg(not(feature = "metrics"))]
+        handle_request(request, &DbMock::default(), DEFAULT_MIN_LENGTH)
+    }
+
+    fn is_unknown_route(response: Response) -> bool {
+        let mut body = String::new();
+        response
+            .data
+            .into_reader_and_size()
+            .0
+            .read_to_string(&mut body)
+            .unwrap();
+
+        body.contains("\"description\":\"Not found\"")
+    }
+
+    #[test]
+    fn test_undeclared_route() {
+        let response = serve(&RequestBuilder::get(format!(
+            "/teams/{}/unknown",
+            Uuid::new_v4()
+        )));
+
+        assert_eq!(response.status_code, 404);
+        assert!(is_unknown_route(response));
+
+        let response = serve(&RequestBuilder::post(String::from("/unknown"), &json!({})));
+
+        assert_eq!(response.status_code, 404);
+    }
+
+    #[test]
+    fn test_every_route_is_handled() {
+        for spec in routes() {
+            let url = spec.pattern.replace("{}", &Uuid::new_v4().to_string());
+
+            for method in spec.methods {
+                let request = Request::fake_http(*method, url.clone(), vec![], b"{}".to_vec());
+                let response = serve(&request);
+
+                assert!(
+                    !is_unknown_route(response),
+                    "{} {} is declared but not handled",
+                    method,
+                    spec.pattern
+                );
+            }
+        }
+    }
+
+    fn handled_routes(source: &str) -> Vec<(String, String)> {
+        let mut handled = vec![];
+
+        for line in source.lines().map(str::trim) {
+            if line.starts_with('(') && line.contains(") (/") && line.ends_with("=> {") {
+                let mut parts = line.split(['(', ')']);
+                let method = parts.nth(1).unwrap();
+                let path = parts.nth(1).unwrap();
+                let pattern = path
+                    .split('/')
+                    .map(|segment| {
+                        if segment.starts_with('{') {
+                            "{}"
+                        } else {
+                            segment
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("/");
+
+                handled.push((method.to_string(), pattern));
+            } else if let Some(condition) = line
+                .strip_prefix("if request.method() == \"")
+                .filter(|condition| condition.contains("request.url() == \""))
+            {
+                let method = condition.split('"').next().unwrap();
+                let pattern = condition.split('"').nth(2).unwrap();
+
+                handled.push((method.to_string(), pattern.to_string()));
+            }
+        }
+
+        handled
+    }
+
+    #[test]
+    fn test_every_handled_route_is_declared() {
+        let mut sources = vec![
+            include_str!("routes.rs"),
+            include_str!("version.rs"),
+            include_str!("../teams/routes.rs"),
+            include_str!("../users/routes.rs"),
+            include_str!("../sanctions/routes.rs"),
+        ];
+        if cfg!(feature = "metrics") {
+            sources.push(include_str!("metrics.rs"));
+        }
+
+        let handled: Vec<_> = sources.into_iter().flat_map(handled_routes).collect();
+
+        assert!(handled.len() > ROUTES.len());
+        for (method, pattern) in handled {
+            assert!(
+                routes()
+                    .any(|spec| spec.pattern == pattern && spec.methods.contains(&method.as_str())),
+                "{} {} is handled but not declared",
+                method,
+                pattern
+            );
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use rouille::{Request, Response, Server};
+use signal_hook::consts::{SIGINT, SIGTERM};
+use std::io;
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc,
+};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    requested: Arc<AtomicBool>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+pub struct InFlightGuard {
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Shutdown {
+        Default::default()
+    }
+
+    pub fn register_signals(&self) -> io::Result<()> {
+        signal_hook::flag::register(SIGTERM, self.requested.clone())?;
+        signal_hook::flag::register(SIGINT, self.requested.clone())?;
+
+        Ok(())
+    }
+
+    pub fn trigger(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    pub fn track(&self) -> InFlightGuard {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+
+        InFlightGuard {
+            in_flight: self.in_flight.clone(),
+        }
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    pub fn wait_for_in_flight(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+
+        while self.in_flight() > 0 {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        true
+    }
+}
+
+pub fn run_until_shutdown<F>(server: Server<F>, shutdown: &Shutdown)
+where
+    F: Send + Sync + 'static + Fn(&Request) -> Response,
+{
+    while !shutdown.is_requested() {
+        server.poll();
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    use super::*;
+
+    #[test]
+    fn test_run_until_shutdown() {
+        let shutdown = Shutdown::new();
+        let resource = Arc::new(());
+
+        let handler_shutdown = shutdown.clone();
+        let handler_resource = resource.clone();
+        let server = Server::new("127.0.0.1:0", move |_request| {
+            let _guard = handler_shutdown.track();
+            let _resource = &handler_resource;
+            thread::sleep(Duration::from_millis(200));
+            Response::empty_204()
+        })
+        .unwrap();
+        let address = server.server_addr();
+
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+
+        let trigger = shutdown.clone();
+        thread::spawn(move || {
+            while trigger.in_flight() == 0 {
+                thread::sleep(POLL_INTERVAL);
+            }
+            trigger.trigger();
+        });
+
+        run_until_shutdown(server, &shutdown);
+
+        assert!(shutdown.wait_for_in_flight(Duration::from_secs(5)));
+        assert!(client.join().unwrap().starts_with("HTTP/1.1 204"));
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Arc::strong_count(&resource) > 1 && Instant::now() < deadline {
+            thread::sleep(POLL_INTERVAL);
+        }
+        assert_eq!(Arc::strong_count(&resource), 1);
+    }
+}
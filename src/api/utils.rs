@@ -0,0 +1,139 @@
+use rouille::{input::json::json_input, Request};
+use serde::de::DeserializeOwned;
+use uuid::Uuid;
+
+use super::models::ErrorResponse;
+use crate::database::pagination::{Pagination, MAX_LIMIT};
+
+pub fn parse_uuid(segment: &str) -> Result<Uuid, ErrorResponse> {
+    Uuid::parse_str(segment)
+        .map_err(|_| ErrorResponse::bad_request(format!("{} is not a valid id", segment)))
+}
+
+pub fn parse_json_body<T>(request: &Request) -> Result<T, ErrorResponse>
+where
+    T: DeserializeOwned,
+{
+    Ok(json_input(request)?)
+}
+
+fn parse_integer_param(request: &Request, name: &str) -> Result<Option<i64>, ErrorResponse> {
+    match request.get_param(name) {
+        Some(value) => value.parse::<i64>().map(Some).map_err(|_| {
+            ErrorResponse::bad_parameter(format!("The {} parameter must be a number.", name))
+        }),
+        None => Ok(None),
+    }
+}
+
+pub fn parse_pagination(request: &Request) -> Result<Option<Pagination>, ErrorResponse> {
+    match (
+        parse_integer_param(request, "limit")?,
+        parse_integer_param(request, "offset")?,
+    ) {
+        (None, None) => Ok(None),
+        (limit, offset) => Ok(Some(Pagination::new(limit, offset, MAX_LIMIT))),
+    }
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 128
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+pub fn request_id(request: &Request) -> String {
+    match request.header("X-Request-Id") {
+        Some(id) if is_valid_request_id(id) => id.to_string(),
+        _ => Uuid::new_v4().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+    use crate::api::models::{test_utils::RequestBuilder, ErrorKind};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Body {
+        name: String,
+    }
+
+    #[test]
+    fn test_parse_uuid() {
+        let id = Uuid::new_v4();
+
+        assert_eq!(parse_uuid(&id.to_string()).unwrap(), id);
+    }
+
+    #[test]
+    fn test_parse_malformed_uuid() {
+        let error = parse_uuid("not-an-id").unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::BadRequest);
+        assert_eq!(error.kind.status_code(), 400);
+        assert_eq!(
+            error.description,
+            String::from("not-an-id is not a valid id")
+        );
+    }
+
+    #[test]
+    fn test_parse_pagination() {
+        let request = RequestBuilder::get(String::from("/root_url?limit=1000000"));
+
+        assert_eq!(
+            parse_pagination(&request).unwrap(),
+            Some(Pagination {
+                limit: MAX_LIMIT,
+                offset: 0
+            })
+        );
+
+        let request = RequestBuilder::get(String::from("/root_url"));
+
+        assert_eq!(parse_pagination(&request).unwrap(), None);
+
+        let request = RequestBuilder::get(String::from("/root_url?offset=a"));
+
+        assert_eq!(
+            parse_pagination(&request).unwrap_err().kind,
+            ErrorKind::BadParameter
+        );
+    }
+
+    #[test]
+    fn test_parse_json_body() {
+        let request = RequestBuilder::post(String::from("/"), &json!({ "name": "John" }));
+
+        assert_eq!(
+            parse_json_body::<Body>(&request).unwrap(),
+            Body {
+                name: String::from("John")
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_malformed_json_body() {
+        let request = Request::fake_http(
+            "POST",
+            "/",
+            vec![("Content-Type".to_owned(), "application/json".to_owned())],
+            b"{\"name\": ".to_vec(),
+        );
+
+        let error = parse_json_body::<Body>(&request).unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::Json);
+        assert_eq!(
+            error.description,
+            "The request body is not valid JSON (line 1, column 9)"
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use chrono::{DateTime, TimeZone, Utc};
+use rouille::Request;
+use serde::Serialize;
+
+use super::models::ErrorResponse;
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionResponse {
+    pub version: &'static str,
+    pub git_sha: Option<&'static str>,
+    pub built_at: DateTime<Utc>,
+}
+
+pub fn version_info() -> VersionResponse {
+    let build_timestamp = env!("BUILD_TIMESTAMP").parse::<i64>().unwrap_or_default();
+
+    VersionResponse {
+        version: env!("CARGO_PKG_VERSION"),
+        git_sha: option_env!("GIT_SHA"),
+        built_at: Utc.timestamp(build_timestamp, 0),
+    }
+}
+
+pub fn handle_request(request: &Request) -> Result<VersionResponse, ErrorResponse> {
+    if request.method() == "GET" && request.url() == "/version" {
+        Ok(version_info())
+    } else {
+        Err(ErrorResponse::not_found())
+    }
+}
@@ -0,0 +1,154 @@
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::BigInt;
+use serde::{ser::Error, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::value::RawValue;
+use std::fmt;
+use std::io::Write;
+use std::iter::Sum;
+use std::ops::{Add, Mul};
+
+/// A monetary amount counted in cents so that sums stay exact.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, AsExpression, FromSqlRow,
+)]
+#[sql_type = "BigInt"]
+pub struct Amount(i64);
+
+impl Amount {
+    pub fn from_cents(cents: i64) -> Amount {
+        Amount(cents)
+    }
+
+    pub fn from_decimal(value: f64) -> Amount {
+        Amount((value * 100.0).round() as i64)
+    }
+
+    pub fn cents(self) -> i64 {
+        self.0
+    }
+
+    pub fn to_decimal(self) -> f64 {
+        self.0 as f64 / 100.0
+    }
+
+    pub fn scale(self, factor: f64) -> Amount {
+        Amount((self.0 as f64 * factor).round() as i64)
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let cents = self.0.abs();
+
+        write!(f, "{}{}.{:02}", sign, cents / 100, cents % 100)
+    }
+}
+
+impl Add for Amount {
+    type Output = Amount;
+
+    fn add(self, other: Amount) -> Amount {
+        Amount(self.0 + other.0)
+    }
+}
+
+impl Mul<i64> for Amount {
+    type Output = Amount;
+
+    fn mul(self, factor: i64) -> Amount {
+        Amount(self.0 * factor)
+    }
+}
+
+impl Sum for Amount {
+    fn sum<I: Iterator<Item = Amount>>(iter: I) -> Amount {
+        Amount(iter.map(|amount| amount.0).sum())
+    }
+}
+
+impl<'a> Sum<&'a Amount> for Amount {
+    fn sum<I: Iterator<Item = &'a Amount>>(iter: I) -> Amount {
+        iter.copied().sum()
+    }
+}
+
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RawValue::from_string(self.to_string())
+            .map_err(S::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Amount, D::Error> {
+        f64::deserialize(deserializer).map(Amount::from_decimal)
+    }
+}
+
+impl ToSql<BigInt, Pg> for Amount {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        ToSql::<BigInt, Pg>::to_sql(&self.0, out)
+    }
+}
+
+impl FromSql<BigInt, Pg> for Amount {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Amount> {
+        <i64 as FromSql<BigInt, Pg>>::from_sql(bytes).map(Amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_sum_is_exact() {
+        let float_total: f32 = (0..1000).map(|_| 0.1_f32).sum();
+        let total: Amount = (0..1000).map(|_| Amount::from_decimal(0.1)).sum();
+
+        assert_ne!(float_total, 100.0);
+        assert_eq!(total, Amount::from_cents(10_000));
+        assert_eq!(total.to_decimal(), 100.0);
+    }
+
+    #[test]
+    fn test_amount_json() {
+        assert_eq!(json!(Amount::from_cents(250)), json!(2.5));
+        assert_eq!(
+            serde_json::to_string(&Amount::from_cents(-5)).unwrap(),
+            "-0.05"
+        );
+        assert_eq!(
+            serde_json::from_value::<Amount>(json!(0.3)).unwrap(),
+            Amount::from_decimal(0.1) + Amount::from_decimal(0.2)
+        );
+    }
+
+    #[test]
+    fn test_computed_amount_json_has_no_float_noise() {
+        let float_balance: f32 = (0..25).map(|_| 0.1_f32).sum();
+        let balance: Amount = (0..25).map(|_| Amount::from_decimal(0.1)).sum();
+
+        assert_ne!(serde_json::to_string(&float_balance).unwrap(), "2.50");
+        assert_eq!(serde_json::to_string(&balance).unwrap(), "2.50");
+        assert_eq!(
+            serde_json::to_string(&balance.scale(1.0 / 3.0)).unwrap(),
+            "0.83"
+        );
+    }
+
+    #[test]
+    fn test_scale_rounds_to_the_cent() {
+        assert_eq!(Amount::from_cents(250).scale(1.5), Amount::from_cents(375));
+        assert_eq!(
+            Amount::from_cents(10).scale(1.0 / 3.0),
+            Amount::from_cents(3)
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use diesel::prelude::*;
+use serde::Serialize;
+use std::io::Write;
+use std::ops::Deref;
+use uuid::Uuid;
+
+use super::{
+    postgres::{DbConnection, DbError},
+    schema::{sanctions, teams, users},
+};
+use crate::sanctions::models::Sanction;
+use crate::teams::models::Team;
+use crate::users::models::User;
+
+pub trait BackupDb {
+    /// For platform operators only. The admins and the team tokens aren't exported.
+    fn stream_backup(&self, writer: &mut impl Write) -> Result<(), DbError>;
+}
+
+const BACKUP_BATCH_SIZE: i64 = 500;
+
+fn write_backup_rows<T, W, F>(
+    writer: &mut W,
+    kind: &str,
+    fetch_after: F,
+    id: fn(&T) -> Uuid,
+) -> Result<(), DbError>
+where
+    T: Serialize,
+    W: Write,
+    F: Fn(Option<Uuid>) -> Result<Vec<T>, DbError>,
+{
+    let mut last_id = None;
+
+    loop {
+        let rows = fetch_after(last_id)?;
+
+        for row in &rows {
+            let mut value = serde_json::to_value(row).map_err(|_| DbError::Unknown)?;
+            value["_type"] = serde_json::Value::from(kind);
+
+            serde_json::to_writer(&mut *writer, &value).map_err(|_| DbError::Unknown)?;
+            writer.write_all(b"\n").map_err(|_| DbError::Unknown)?;
+        }
+
+        match rows.last() {
+            Some(row) if rows.len() as i64 == BACKUP_BATCH_SIZE => last_id = Some(id(row)),
+            _ => return Ok(()),
+        }
+    }
+}
+
+/// Must run in a single snapshot for the three tables to agree.
+fn write_backup<W: Write>(conn: &DbConnection, writer: &mut W) -> Result<(), DbError> {
+    write_backup_rows(
+        writer,
+        "team",
+        |after| {
+            let mut query = teams::table
+                .order_by(teams::id)
+                .limit(BACKUP_BATCH_SIZE)
+                .into_boxed();
+            if let Some(after) = after {
+                query = query.filter(teams::id.gt(after));
+            }
+
+            Ok(query.get_results::<Team>(conn.deref())?)
+        },
+        |team| team.id,
+    )?;
+    write_backup_rows(
+        writer,
+        "user",
+        |after| {
+            let mut query = users::table
+                .order_by(users::id)
+                .limit(BACKUP_BATCH_SIZE)
+                .into_boxed();
+            if let Some(after) = after {
+                query = query.filter(users::id.gt(after));
+            }
+
+            Ok(query.get_results::<User>(conn.deref())?)
+        },
+        |user| user.id,
+    )?;
+    write_backup_rows(
+        writer,
+        "sanction",
+        |after| {
+            let mut query = sanctions::table
+                .order_by(sanctions::id)
+                .limit(BACKUP_BATCH_SIZE)
+                .into_boxed();
+            if let Some(after) = after {
+                query = query.filter(sanctions::id.gt(after));
+            }
+
+            Ok(query.get_results::<Sanction>(conn.deref())?)
+        },
+        |sanction| sanction.id,
+    )?;
+
+    writer.flush().map_err(|_| DbError::Unknown)
+}
+
+impl BackupDb for DbConnection {
+    fn stream_backup(&self, writer: &mut impl Write) -> Result<(), DbError> {
+        self.deref()
+            .build_transaction()
+            .repeatable_read()
+            .read_only()
+            .run(|| write_backup(self, writer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::result::Error;
+    use serde_json::json;
+
+    use super::*;
+    use crate::sanctions::{interface::SanctionsDb, models::CreateSanction};
+    use crate::teams::{
+        interface::TeamsDb,
+        models::{Rule, Team},
+    };
+    use crate::test_utils::postgres::{init_connection, unique_admin};
+    use crate::users::interface::UsersDb;
+
+    #[test]
+    fn test_stream_backup() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let backup = || {
+                let mut buffer: Vec<u8> = vec![];
+                write_backup(&conn, &mut buffer).unwrap();
+
+                String::from_utf8(buffer)
+                    .unwrap()
+                    .lines()
+                    .map(|line| serde_json::from_str(line).unwrap())
+                    .collect::<Vec<serde_json::Value>>()
+            };
+            let previous_lines = backup().len();
+
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: vec![Rule::default()],
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user_ids: Vec<Uuid> = (0..2)
+                .map(|_| {
+                    conn.create_user(&User {
+                        id: Uuid::new_v4(),
+                        team_id,
+                        ..Default::default()
+                    })
+                    .unwrap()
+                    .id
+                })
+                .collect();
+            conn.create_sanctions(&[CreateSanction {
+                id: Uuid::new_v4(),
+                user_id: user_ids[0],
+                team_id,
+                ..Default::default()
+            }])
+            .unwrap();
+
+            let lines = backup();
+            assert_eq!(lines.len(), previous_lines + 4);
+
+            let types: Vec<&str> = lines
+                .iter()
+                .filter(|line| line["id"] == json!(team_id) || line["team_id"] == json!(team_id))
+                .map(|line| line["_type"].as_str().unwrap())
+                .collect();
+            assert_eq!(types, vec!["team", "user", "user", "sanction"]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_stream_backup_in_transaction() {
+        let conn = init_connection();
+
+        let mut buffer: Vec<u8> = vec![];
+        conn.stream_backup(&mut buffer).unwrap();
+
+        assert!(String::from_utf8(buffer)
+            .unwrap()
+            .lines()
+            .all(|line| serde_json::from_str::<serde_json::Value>(line).is_ok()));
+        // The snapshot is over, so the connection can start a transaction again.
+        assert!(conn.deref().test_transaction::<_, Error, _>(|| Ok(true)));
+    }
+}
@@ -1,2 +1,6 @@
+pub mod amount;
+pub mod backup;
+pub mod pagination;
 pub mod postgres;
+pub mod routing;
 pub mod schema;
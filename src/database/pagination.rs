@@ -0,0 +1,149 @@
+use serde::Serialize;
+
+pub const DEFAULT_LIMIT: i64 = 50;
+pub const MAX_LIMIT: i64 = 200;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Pagination {
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Pagination {
+    pub fn new(limit: Option<i64>, offset: Option<i64>, max_limit: i64) -> Pagination {
+        let limit = match limit {
+            Some(limit) if limit > 0 => limit.min(max_limit),
+            _ => DEFAULT_LIMIT.min(max_limit),
+        };
+
+        Pagination {
+            limit,
+            offset: offset.unwrap_or(0).max(0),
+        }
+    }
+}
+
+impl Default for Pagination {
+    fn default() -> Pagination {
+        Pagination::new(None, None, MAX_LIMIT)
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl<T> Paginated<T> {
+    pub fn new(items: Vec<T>, total: i64, pagination: &Pagination) -> Paginated<T> {
+        Paginated {
+            items,
+            total,
+            limit: pagination.limit,
+            offset: pagination.offset,
+        }
+    }
+
+    pub fn link_header(&self, path: &str) -> Option<String> {
+        let link = |offset: i64, rel: &str| {
+            format!(
+                "<{}?limit={}&offset={}>; rel=\"{}\"",
+                path, self.limit, offset, rel
+            )
+        };
+        let mut links: Vec<String> = vec![];
+
+        if self.offset + self.limit < self.total {
+            links.push(link(self.offset + self.limit, "next"));
+        }
+        if self.offset > 0 {
+            links.push(link((self.offset - self.limit).max(0), "prev"));
+        }
+        if !links.is_empty() {
+            let last_offset = (self.total - 1).max(0) / self.limit * self.limit;
+            links.push(link(last_offset, "last"));
+        }
+
+        if links.is_empty() {
+            None
+        } else {
+            Some(links.join(", "))
+        }
+    }
+
+    pub fn map<U, F>(self, f: F) -> Paginated<U>
+    where
+        F: FnMut(T) -> U,
+    {
+        Paginated {
+            items: self.items.into_iter().map(f).collect(),
+            total: self.total,
+            limit: self.limit,
+            offset: self.offset,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pagination_clamps_limit() {
+        let pagination = Pagination::new(Some(1_000_000), Some(20), MAX_LIMIT);
+
+        assert_eq!(
+            pagination,
+            Pagination {
+                limit: MAX_LIMIT,
+                offset: 20
+            }
+        );
+        assert_eq!(Pagination::new(Some(100), None, 10).limit, 10);
+    }
+
+    #[test]
+    fn test_pagination_normalizes_values() {
+        let pagination = Pagination::new(Some(-5), Some(-1), MAX_LIMIT);
+
+        assert_eq!(
+            pagination,
+            Pagination {
+                limit: DEFAULT_LIMIT,
+                offset: 0
+            }
+        );
+        assert_eq!(
+            Pagination::new(Some(0), None, MAX_LIMIT).limit,
+            DEFAULT_LIMIT
+        );
+    }
+
+    #[test]
+    fn test_link_header() {
+        let page = Paginated::new(
+            vec![()],
+            95,
+            &Pagination {
+                limit: 20,
+                offset: 40,
+            },
+        );
+
+        assert_eq!(
+            page.link_header("/teams/1/users"),
+            Some(String::from(
+                "</teams/1/users?limit=20&offset=60>; rel=\"next\", \
+                 </teams/1/users?limit=20&offset=20>; rel=\"prev\", \
+                 </teams/1/users?limit=20&offset=80>; rel=\"last\""
+            ))
+        );
+
+        let single_page = Paginated::new(vec![()], 1, &Pagination::default());
+
+        assert_eq!(single_page.link_header("/teams/1/users"), None);
+    }
+}
@@ -2,12 +2,17 @@ use diesel::pg::PgConnection;
 use r2d2_diesel::ConnectionManager;
 use std::ops::Deref;
 
+use crate::api::models::ValidationError;
+use crate::sanctions::models::SanctionInfoError;
+
 #[derive(Debug, PartialEq)]
 pub enum DbError {
     ServiceUnavailable,
     NotFound,
     ForeignKeyViolation(String),
     UniqueViolation(String),
+    Forbidden(String),
+    NotValid(String),
     Unknown,
 }
 
@@ -17,6 +22,24 @@ impl From<r2d2::Error> for DbError {
     }
 }
 
+impl From<bcrypt::BcryptError> for DbError {
+    fn from(_: bcrypt::BcryptError) -> DbError {
+        DbError::Unknown
+    }
+}
+
+impl From<SanctionInfoError> for DbError {
+    fn from(error: SanctionInfoError) -> DbError {
+        DbError::NotValid(error.to_string())
+    }
+}
+
+impl From<ValidationError> for DbError {
+    fn from(error: ValidationError) -> DbError {
+        DbError::NotValid(error.to_string())
+    }
+}
+
 impl From<diesel::result::Error> for DbError {
     fn from(error: diesel::result::Error) -> DbError {
         match error {
@@ -46,6 +69,55 @@ impl From<diesel::result::Error> for DbError {
     }
 }
 
+pub type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+
+pub fn init_db_pool(database_url: &str, pool_size: u32) -> DbPool {
+    let manager = ConnectionManager::<PgConnection>::new(database_url);
+    r2d2::Pool::builder()
+        .max_size(pool_size)
+        .build_unchecked(manager)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DbAccess {
+    Read,
+    Write,
+}
+
+pub struct DbPools {
+    primary: DbPool,
+    replica: Option<DbPool>,
+}
+
+impl DbPools {
+    pub fn pool(&self, access: DbAccess) -> &DbPool {
+        match (access, &self.replica) {
+            (DbAccess::Read, Some(replica)) => replica,
+            _ => &self.primary,
+        }
+    }
+
+    pub fn get_connection(&self, access: DbAccess) -> Result<DbConnection, DbError> {
+        get_db_connection(self.pool(access))
+    }
+}
+
+pub fn init_db_connections(
+    primary_url: &str,
+    replica_url: Option<&str>,
+    pool_size: u32,
+) -> DbPools {
+    DbPools {
+        primary: init_db_pool(primary_url, pool_size),
+        replica: replica_url.map(|url| init_db_pool(url, pool_size)),
+    }
+}
+
+pub fn get_db_connection(pool: &DbPool) -> Result<DbConnection, DbError> {
+    let connection = pool.get()?;
+    Ok(DbConnection(connection))
+}
+
 pub fn init_db_connection(database_url: &str) -> Result<DbConnection, DbError> {
     let manager = ConnectionManager::<PgConnection>::new(database_url);
     let pool = r2d2::Pool::new(manager)?;
@@ -62,3 +134,18 @@ impl Deref for DbConnection {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIMARY_URL: &str = "postgres://primary/caisse_noire";
+
+    #[test]
+    fn test_reads_fall_back_to_the_primary() {
+        let pools = init_db_connections(PRIMARY_URL, None, 1);
+
+        assert!(std::ptr::eq(pools.pool(DbAccess::Read), &pools.primary));
+        assert!(std::ptr::eq(pools.pool(DbAccess::Write), &pools.primary));
+    }
+}
@@ -0,0 +1,717 @@
+use chrono::{naive::NaiveDate, DateTime, Utc};
+use log::error;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use uuid::Uuid;
+
+use super::{
+    amount::Amount,
+    backup::BackupDb,
+    pagination::{Paginated, Pagination},
+    postgres::{DbAccess, DbConnection, DbError, DbPools},
+};
+use crate::admins::{
+    interface::AdminsDb,
+    models::{Admin, NewAdmin},
+};
+use crate::sanctions::{
+    interface::{SanctionsDb, SanctionsLedgerDb},
+    models::*,
+};
+use crate::sessions::{interface::SessionsDb, models::Session};
+use crate::teams::{interface::TeamsDb, models::*};
+use crate::users::{interface::UsersDb, models::*};
+
+pub trait ConnectionSource {
+    fn get_connection(&self, access: DbAccess) -> Result<DbConnection, DbError>;
+}
+
+impl ConnectionSource for DbPools {
+    fn get_connection(&self, access: DbAccess) -> Result<DbConnection, DbError> {
+        DbPools::get_connection(self, access)
+    }
+}
+
+/// Once the request wrote, its reads go to the primary too so that they see the writes.
+pub struct RoutedDb<'a, P: ConnectionSource = DbPools> {
+    pools: &'a P,
+    request_id: &'a str,
+    replica: RefCell<Option<DbConnection>>,
+    primary: RefCell<Option<DbConnection>>,
+}
+
+impl<'a, P: ConnectionSource> RoutedDb<'a, P> {
+    pub fn new(pools: &'a P, request_id: &'a str) -> RoutedDb<'a, P> {
+        RoutedDb {
+            pools,
+            request_id,
+            replica: RefCell::new(None),
+            primary: RefCell::new(None),
+        }
+    }
+
+    fn with_connection<T, F>(&self, access: DbAccess, operation: F) -> Result<T, DbError>
+    where
+        F: FnOnce(&DbConnection) -> Result<T, DbError>,
+    {
+        let access = match access {
+            DbAccess::Read if self.primary.borrow().is_some() => DbAccess::Write,
+            access => access,
+        };
+        let mut connection = match access {
+            DbAccess::Read => self.replica.borrow_mut(),
+            DbAccess::Write => self.primary.borrow_mut(),
+        };
+
+        if connection.is_none() {
+            let new_connection = self.pools.get_connection(access).map_err(|err| {
+                error!(
+                    "[{}] Could not get a database connection: {:?}",
+                    self.request_id, err
+                );
+                err
+            })?;
+            *connection = Some(new_connection);
+        }
+
+        match connection.as_ref() {
+            Some(connection) => operation(connection),
+            None => Err(DbError::ServiceUnavailable),
+        }
+    }
+}
+
+impl<'a, P: ConnectionSource> AdminsDb for RoutedDb<'a, P> {
+    fn add_admin(&self, team_id: Uuid, username: &str, password: &str) -> Result<Admin, DbError> {
+        self.with_connection(DbAccess::Write, |db| {
+            db.add_admin(team_id, username, password)
+        })
+    }
+
+    fn verify_admin(
+        &self,
+        team_id: Uuid,
+        username: &str,
+        password: &str,
+    ) -> Result<Admin, DbError> {
+        self.with_connection(DbAccess::Read, |db| {
+            db.verify_admin(team_id, username, password)
+        })
+    }
+
+    fn remove_admin(&self, team_id: Uuid, username: &str) -> Result<Admin, DbError> {
+        self.with_connection(DbAccess::Write, |db| db.remove_admin(team_id, username))
+    }
+
+    fn set_admin_user(
+        &self,
+        team_id: Uuid,
+        username: &str,
+        user_id: Option<Uuid>,
+    ) -> Result<Admin, DbError> {
+        self.with_connection(DbAccess::Write, |db| {
+            db.set_admin_user(team_id, username, user_id)
+        })
+    }
+
+    fn find_teams_without_admin(&self) -> Result<Vec<Uuid>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.find_teams_without_admin())
+    }
+
+    fn set_two_factor(
+        &self,
+        team_id: Uuid,
+        username: &str,
+        enabled: bool,
+        email: Option<&str>,
+    ) -> Result<Admin, DbError> {
+        self.with_connection(DbAccess::Write, |db| {
+            db.set_two_factor(team_id, username, enabled, email)
+        })
+    }
+
+    fn request_login_code(
+        &self,
+        team_id: Uuid,
+        username: &str,
+        password: &str,
+    ) -> Result<(Admin, String), DbError> {
+        self.with_connection(DbAccess::Write, |db| {
+            db.request_login_code(team_id, username, password)
+        })
+    }
+
+    fn verify_login_code(
+        &self,
+        team_id: Uuid,
+        username: &str,
+        code: &str,
+    ) -> Result<Admin, DbError> {
+        self.with_connection(DbAccess::Write, |db| {
+            db.verify_login_code(team_id, username, code)
+        })
+    }
+
+    fn find_team_by_admin_username(&self, username: &str) -> Result<Uuid, DbError> {
+        self.with_connection(DbAccess::Read, |db| {
+            db.find_team_by_admin_username(username)
+        })
+    }
+}
+
+impl<'a, P: ConnectionSource> BackupDb for RoutedDb<'a, P> {
+    fn stream_backup(&self, writer: &mut impl Write) -> Result<(), DbError> {
+        self.with_connection(DbAccess::Read, |db| db.stream_backup(writer))
+    }
+}
+
+impl<'a, P: ConnectionSource> SanctionsDb for RoutedDb<'a, P> {
+    fn get_sanctions(
+        &self,
+        team_id: Uuid,
+        date_interval: Option<(NaiveDate, NaiveDate)>,
+        filter: &SanctionFilter,
+    ) -> Result<Vec<Sanction>, DbError> {
+        self.with_connection(DbAccess::Read, |db| {
+            db.get_sanctions(team_id, date_interval, filter)
+        })
+    }
+
+    fn create_sanctions(&self, sanctions: &[CreateSanction]) -> Result<Vec<Sanction>, DbError> {
+        self.with_connection(DbAccess::Write, |db| db.create_sanctions(sanctions))
+    }
+
+    fn create_sanctions_overriding_leave(
+        &self,
+        sanctions: &[CreateSanction],
+    ) -> Result<Vec<Sanction>, DbError> {
+        self.with_connection(DbAccess::Write, |db| {
+            db.create_sanctions_overriding_leave(sanctions)
+        })
+    }
+
+    fn delete_sanction(
+        &self,
+        team_id: Uuid,
+        sanction_id: Uuid,
+        actor: Option<&str>,
+    ) -> Result<Sanction, DbError> {
+        self.with_connection(DbAccess::Write, |db| {
+            db.delete_sanction(team_id, sanction_id, actor)
+        })
+    }
+
+    fn get_fine_leaderboard(
+        &self,
+        team_id: Uuid,
+        pagination: Option<&Pagination>,
+    ) -> Result<Paginated<(User, Amount)>, DbError> {
+        self.with_connection(DbAccess::Read, |db| {
+            db.get_fine_leaderboard(team_id, pagination)
+        })
+    }
+
+    fn query_sanctions(
+        &self,
+        team_id: Uuid,
+        query: SanctionQuery,
+    ) -> Result<Paginated<Sanction>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.query_sanctions(team_id, query))
+    }
+}
+
+impl<'a, P: ConnectionSource> SanctionsLedgerDb for RoutedDb<'a, P> {
+    fn get_unpaid_older_than(
+        &self,
+        team_id: Uuid,
+        days: i64,
+    ) -> Result<Vec<(User, Vec<Sanction>)>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_unpaid_older_than(team_id, days))
+    }
+
+    fn get_rule_revenue(&self, team_id: Uuid) -> Result<Vec<(Rule, i64, Amount)>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_rule_revenue(team_id))
+    }
+
+    fn get_balance(&self, team_id: Uuid, user_id: Uuid) -> Result<Amount, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_balance(team_id, user_id))
+    }
+
+    fn mark_user_sanctions_paid(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+        date: NaiveDate,
+        paid_by: Option<Uuid>,
+    ) -> Result<u64, DbError> {
+        self.with_connection(DbAccess::Write, |db| {
+            db.mark_user_sanctions_paid(team_id, user_id, date, paid_by)
+        })
+    }
+
+    fn get_user_timeline(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<TimelineEvent>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_user_timeline(team_id, user_id))
+    }
+
+    fn get_sanctions_by_category(
+        &self,
+        team_id: Uuid,
+        category: RuleCategory,
+    ) -> Result<Vec<Sanction>, DbError> {
+        self.with_connection(DbAccess::Read, |db| {
+            db.get_sanctions_by_category(team_id, category)
+        })
+    }
+
+    fn preview_balance_with(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+        rule_id: Uuid,
+        extra_info: &ExtraInfo,
+    ) -> Result<Amount, DbError> {
+        self.with_connection(DbAccess::Read, |db| {
+            db.preview_balance_with(team_id, user_id, rule_id, extra_info)
+        })
+    }
+
+    fn get_unbroken_rules(&self, team_id: Uuid, user_id: Uuid) -> Result<Vec<Rule>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_unbroken_rules(team_id, user_id))
+    }
+
+    fn get_collection_rate(&self, team_id: Uuid) -> Result<f32, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_collection_rate(team_id))
+    }
+
+    fn archive_season(&self, team_id: Uuid, season: &str) -> Result<SeasonArchive, DbError> {
+        self.with_connection(DbAccess::Write, |db| db.archive_season(team_id, season))
+    }
+
+    fn get_daily_sanction_counts(
+        &self,
+        team_id: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<(NaiveDate, i64)>, DbError> {
+        self.with_connection(DbAccess::Read, |db| {
+            db.get_daily_sanction_counts(team_id, from, to)
+        })
+    }
+
+    fn get_rule_trend(
+        &self,
+        team_id: Uuid,
+        rule_id: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+        bucket: Bucket,
+    ) -> Result<Vec<(NaiveDate, i64)>, DbError> {
+        self.with_connection(DbAccess::Read, |db| {
+            db.get_rule_trend(team_id, rule_id, from, to, bucket)
+        })
+    }
+
+    fn get_biggest_fine(&self, team_id: Uuid) -> Result<Option<(User, Sanction, Amount)>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_biggest_fine(team_id))
+    }
+
+    fn get_global_unpaid_total(&self) -> Result<Amount, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_global_unpaid_total())
+    }
+
+    fn get_clean_streak(&self, team_id: Uuid, user_id: Uuid) -> Result<i64, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_clean_streak(team_id, user_id))
+    }
+
+    fn get_balances(
+        &self,
+        team_id: Uuid,
+        user_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Amount>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_balances(team_id, user_ids))
+    }
+
+    fn find_future_dated_sanctions(&self, team_id: Uuid) -> Result<Vec<Sanction>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.find_future_dated_sanctions(team_id))
+    }
+
+    fn undo_last_sanction(&self, team_id: Uuid, user_id: Uuid) -> Result<Sanction, DbError> {
+        self.with_connection(DbAccess::Write, |db| {
+            db.undo_last_sanction(team_id, user_id)
+        })
+    }
+
+    fn get_fine_distribution(&self, team_id: Uuid) -> Result<FineStats, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_fine_distribution(team_id))
+    }
+
+    fn dispute_sanction(
+        &self,
+        team_id: Uuid,
+        sanction_id: Uuid,
+        note: Option<&str>,
+    ) -> Result<Sanction, DbError> {
+        self.with_connection(DbAccess::Write, |db| {
+            db.dispute_sanction(team_id, sanction_id, note)
+        })
+    }
+
+    fn resolve_dispute(
+        &self,
+        team_id: Uuid,
+        sanction_id: Uuid,
+        upheld: bool,
+    ) -> Result<Sanction, DbError> {
+        self.with_connection(DbAccess::Write, |db| {
+            db.resolve_dispute(team_id, sanction_id, upheld)
+        })
+    }
+
+    fn get_amount_by_category(
+        &self,
+        team_id: Uuid,
+    ) -> Result<Vec<(Option<RuleCategory>, Amount)>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_amount_by_category(team_id))
+    }
+
+    fn get_recent_sanctions(&self, team_id: Uuid, hours: i64) -> Result<Vec<Sanction>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_recent_sanctions(team_id, hours))
+    }
+
+    fn get_unused_rules(&self, team_id: Uuid, since: NaiveDate) -> Result<Vec<Rule>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_unused_rules(team_id, since))
+    }
+
+    fn simulate_rule_price_change(
+        &self,
+        team_id: Uuid,
+        rule_id: Uuid,
+        new_price: Amount,
+    ) -> Result<(Amount, Amount), DbError> {
+        self.with_connection(DbAccess::Read, |db| {
+            db.simulate_rule_price_change(team_id, rule_id, new_price)
+        })
+    }
+
+    fn get_sanction_details(&self, team_id: Uuid) -> Result<Vec<SanctionDetail>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_sanction_details(team_id))
+    }
+
+    fn get_average_balance(&self, team_id: Uuid) -> Result<Amount, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_average_balance(team_id))
+    }
+
+    fn waive_sanctions_where(
+        &self,
+        team_id: Uuid,
+        date_interval: Option<(NaiveDate, NaiveDate)>,
+        filter: &SanctionFilter,
+        reason: &str,
+    ) -> Result<u64, DbError> {
+        self.with_connection(DbAccess::Write, |db| {
+            db.waive_sanctions_where(team_id, date_interval, filter, reason)
+        })
+    }
+
+    fn get_rules_with_usage(&self, team_id: Uuid) -> Result<Vec<(Rule, i64)>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_rules_with_usage(team_id))
+    }
+
+    fn get_sanctions_modified_since(
+        &self,
+        team_id: Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<Sanction>, DbError> {
+        self.with_connection(DbAccess::Read, |db| {
+            db.get_sanctions_modified_since(team_id, since)
+        })
+    }
+
+    fn get_sanction_audit(
+        &self,
+        team_id: Uuid,
+        sanction_id: Uuid,
+    ) -> Result<Vec<AuditEvent>, DbError> {
+        self.with_connection(DbAccess::Read, |db| {
+            db.get_sanction_audit(team_id, sanction_id)
+        })
+    }
+
+    fn get_user_vs_average(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(Amount, Amount, f32), DbError> {
+        self.with_connection(DbAccess::Read, |db| {
+            db.get_user_vs_average(team_id, user_id)
+        })
+    }
+
+    fn get_fine_gini(&self, team_id: Uuid) -> Result<f32, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_fine_gini(team_id))
+    }
+
+    fn rule_deletion_impact(&self, team_id: Uuid, rule_id: Uuid) -> Result<RuleImpact, DbError> {
+        self.with_connection(DbAccess::Read, |db| {
+            db.rule_deletion_impact(team_id, rule_id)
+        })
+    }
+
+    fn get_top_rule_per_user(
+        &self,
+        team_id: Uuid,
+    ) -> Result<Vec<(User, Option<Rule>, i64)>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_top_rule_per_user(team_id))
+    }
+}
+
+impl<'a, P: ConnectionSource> SessionsDb for RoutedDb<'a, P> {
+    fn create_session(&self, session: &Session) -> Result<Session, DbError> {
+        self.with_connection(DbAccess::Write, |db| db.create_session(session))
+    }
+
+    fn record_attendance(
+        &self,
+        team_id: Uuid,
+        session_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(), DbError> {
+        self.with_connection(DbAccess::Write, |db| {
+            db.record_attendance(team_id, session_id, user_id)
+        })
+    }
+
+    fn get_missing_attendance(
+        &self,
+        team_id: Uuid,
+        session_date: NaiveDate,
+    ) -> Result<Vec<User>, DbError> {
+        self.with_connection(DbAccess::Read, |db| {
+            db.get_missing_attendance(team_id, session_date)
+        })
+    }
+}
+
+impl<'a, P: ConnectionSource> TeamsDb for RoutedDb<'a, P> {
+    fn login(&self, name: &str) -> Result<Uuid, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.login(name))
+    }
+
+    fn get_team(&self, id: Uuid) -> Result<Team, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_team(id))
+    }
+
+    fn create_team(&self, team: &Team, admin: &NewAdmin) -> Result<Team, DbError> {
+        self.with_connection(DbAccess::Write, |db| db.create_team(team, admin))
+    }
+
+    fn update_team(&self, id: Uuid, team: &UpdateTeam) -> Result<Team, DbError> {
+        self.with_connection(DbAccess::Write, |db| db.update_team(id, team))
+    }
+
+    fn add_rule(&self, team_id: Uuid, rule: &Rule) -> Result<Rule, DbError> {
+        self.with_connection(DbAccess::Write, |db| db.add_rule(team_id, rule))
+    }
+
+    fn copy_rules(&self, source_team: Uuid, target_team: Uuid) -> Result<Vec<Rule>, DbError> {
+        self.with_connection(DbAccess::Write, |db| {
+            db.copy_rules(source_team, target_team)
+        })
+    }
+
+    fn import_rules(&self, team_id: Uuid, rules: Vec<RuleInput>) -> Result<Vec<Rule>, DbError> {
+        self.with_connection(DbAccess::Write, |db| db.import_rules(team_id, rules))
+    }
+
+    fn set_current_season(&self, team_id: Uuid, season: Option<&str>) -> Result<Team, DbError> {
+        self.with_connection(DbAccess::Write, |db| db.set_current_season(team_id, season))
+    }
+
+    fn get_team_with_users(&self, team_id: Uuid) -> Result<(Team, Vec<User>), DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_team_with_users(team_id))
+    }
+
+    fn get_rules_by_tag(&self, team_id: Uuid, tag: &str) -> Result<Vec<Rule>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_rules_by_tag(team_id, tag))
+    }
+
+    fn replace_rules(&self, team_id: Uuid, rules: &[Rule]) -> Result<Vec<Rule>, DbError> {
+        self.with_connection(DbAccess::Write, |db| db.replace_rules(team_id, rules))
+    }
+
+    fn set_max_balance(
+        &self,
+        team_id: Uuid,
+        max_balance: Option<Amount>,
+        clamp: bool,
+    ) -> Result<Team, DbError> {
+        self.with_connection(DbAccess::Write, |db| {
+            db.set_max_balance(team_id, max_balance, clamp)
+        })
+    }
+
+    fn set_max_rules(&self, team_id: Uuid, max_rules: u32) -> Result<Team, DbError> {
+        self.with_connection(DbAccess::Write, |db| db.set_max_rules(team_id, max_rules))
+    }
+
+    fn set_grace_days(&self, team_id: Uuid, grace_days: u32) -> Result<Team, DbError> {
+        self.with_connection(DbAccess::Write, |db| db.set_grace_days(team_id, grace_days))
+    }
+
+    fn rotate_team_token(&self, team_id: Uuid) -> Result<String, DbError> {
+        self.with_connection(DbAccess::Write, |db| db.rotate_team_token(team_id))
+    }
+
+    fn verify_team_token(&self, team_id: Uuid, token: &str) -> Result<Uuid, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.verify_team_token(team_id, token))
+    }
+
+    fn get_teams_for_email(&self, email: &str) -> Result<Vec<Team>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_teams_for_email(email))
+    }
+}
+
+impl<'a, P: ConnectionSource> UsersDb for RoutedDb<'a, P> {
+    fn get_users(&self, team_id: Uuid) -> Result<Vec<User>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_users(team_id))
+    }
+
+    fn get_users_page(
+        &self,
+        team_id: Uuid,
+        pagination: &Pagination,
+    ) -> Result<Paginated<User>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_users_page(team_id, pagination))
+    }
+
+    fn get_user(&self, team_id: Uuid, user_id: Uuid) -> Result<User, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_user(team_id, user_id))
+    }
+
+    fn create_user(&self, user: &User) -> Result<User, DbError> {
+        self.with_connection(DbAccess::Write, |db| db.create_user(user))
+    }
+
+    fn update_user(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+        user: &UpdateUser,
+    ) -> Result<User, DbError> {
+        self.with_connection(DbAccess::Write, |db| db.update_user(team_id, user_id, user))
+    }
+
+    fn patch_user(&self, team_id: Uuid, user_id: Uuid, patch: &UserPatch) -> Result<User, DbError> {
+        self.with_connection(DbAccess::Write, |db| db.patch_user(team_id, user_id, patch))
+    }
+
+    fn find_duplicate_users(&self, team_id: Uuid) -> Result<Vec<Vec<User>>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.find_duplicate_users(team_id))
+    }
+
+    fn export_user_data(&self, team_id: Uuid, user_id: Uuid) -> Result<UserDataExport, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.export_user_data(team_id, user_id))
+    }
+
+    fn anonymize_user(&self, team_id: Uuid, user_id: Uuid) -> Result<User, DbError> {
+        self.with_connection(DbAccess::Write, |db| db.anonymize_user(team_id, user_id))
+    }
+
+    fn get_users_without_email(&self, team_id: Uuid) -> Result<Vec<User>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_users_without_email(team_id))
+    }
+
+    fn get_users_by_role(&self, team_id: Uuid, role: &str) -> Result<Vec<User>, DbError> {
+        self.with_connection(DbAccess::Read, |db| db.get_users_by_role(team_id, role))
+    }
+
+    fn upsert_user(&self, user: &User) -> Result<User, DbError> {
+        self.with_connection(DbAccess::Write, |db| db.upsert_user(user))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::prelude::*;
+    use dotenv::dotenv;
+    use std::cell::Cell;
+    use std::env::var;
+    use std::ops::Deref;
+
+    use super::*;
+    use crate::database::{postgres::init_db_connections, schema::teams};
+    use crate::test_utils::postgres::{init_connection, unique_admin};
+
+    struct PoolsSpy {
+        pools: DbPools,
+        reads: Cell<u32>,
+        writes: Cell<u32>,
+    }
+
+    impl PoolsSpy {
+        fn new() -> PoolsSpy {
+            dotenv().ok();
+
+            let database_url = var("DATABASE_URL").expect("DATABASE_URL must be set for tests");
+
+            PoolsSpy {
+                pools: init_db_connections(&database_url, None, 2),
+                reads: Cell::new(0),
+                writes: Cell::new(0),
+            }
+        }
+
+        fn counts(&self) -> (u32, u32) {
+            (self.reads.get(), self.writes.get())
+        }
+    }
+
+    impl ConnectionSource for PoolsSpy {
+        fn get_connection(&self, access: DbAccess) -> Result<DbConnection, DbError> {
+            let count = match access {
+                DbAccess::Read => &self.reads,
+                DbAccess::Write => &self.writes,
+            };
+            count.set(count.get() + 1);
+
+            self.pools.get_connection(DbAccess::Write)
+        }
+    }
+
+    #[test]
+    fn test_reads_use_the_replica() {
+        let pools = PoolsSpy::new();
+        let db = RoutedDb::new(&pools, "test");
+
+        assert_eq!(db.get_team(Uuid::new_v4()).unwrap_err(), DbError::NotFound);
+        assert_eq!(db.get_teams_for_email("").unwrap(), vec![]);
+        assert_eq!(pools.counts(), (1, 0));
+    }
+
+    #[test]
+    fn test_writes_use_the_primary() {
+        let pools = PoolsSpy::new();
+        let db = RoutedDb::new(&pools, "test");
+        let team = Team {
+            id: Uuid::new_v4(),
+            name: Uuid::new_v4().to_string(),
+            ..Default::default()
+        };
+
+        let write = db.create_team(&team, &unique_admin());
+        let read_after_write = db.get_team(team.id);
+
+        diesel::delete(teams::table.find(team.id))
+            .execute(init_connection().deref())
+            .unwrap();
+
+        assert_eq!(write.unwrap().id, team.id);
+        assert_eq!(read_after_write.unwrap().id, team.id);
+        assert_eq!(pools.counts(), (0, 1));
+    }
+}
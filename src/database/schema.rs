@@ -1,11 +1,82 @@
+table! {
+    admin_login_codes (id) {
+        id -> Uuid,
+        admin_id -> Uuid,
+        code_hash -> Varchar,
+        expires_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    admins (id) {
+        id -> Uuid,
+        team_id -> Uuid,
+        username -> Varchar,
+        password_hash -> Varchar,
+        user_id -> Nullable<Uuid>,
+        email -> Nullable<Varchar>,
+        two_factor_enabled -> Bool,
+    }
+}
+
+table! {
+    deleted_sanctions_audit (id) {
+        id -> Uuid,
+        sanction_id -> Uuid,
+        team_id -> Uuid,
+        sanction -> Jsonb,
+        actor -> Nullable<Varchar>,
+        deleted_at -> Timestamptz,
+    }
+}
+
+table! {
+    sanction_events (id) {
+        id -> Uuid,
+        sanction_id -> Uuid,
+        team_id -> Uuid,
+        kind -> Varchar,
+        actor -> Nullable<Uuid>,
+        occurred_at -> Timestamptz,
+    }
+}
+
+table! {
+    session_attendances (session_id, user_id) {
+        session_id -> Uuid,
+        user_id -> Uuid,
+    }
+}
+
+table! {
+    sessions (id) {
+        id -> Uuid,
+        team_id -> Uuid,
+        date -> Date,
+        kind -> Varchar,
+    }
+}
+
 table! {
     sanctions (id) {
         id -> Uuid,
         user_id -> Uuid,
         team_id -> Uuid,
         sanction_info -> Jsonb,
-        price -> Float4,
-        created_at -> Date,
+        price -> Int8,
+        created_at -> Timestamptz,
+        paid_at -> Nullable<Date>,
+        waived_at -> Nullable<Date>,
+        season -> Nullable<Varchar>,
+        archived_at -> Nullable<Date>,
+        disputed -> Bool,
+        dispute_note -> Nullable<Varchar>,
+        recorded_at -> Timestamptz,
+        created_by -> Nullable<Uuid>,
+        paid_by -> Nullable<Uuid>,
+        waive_reason -> Nullable<Varchar>,
+        updated_at -> Timestamptz,
     }
 }
 
@@ -13,8 +84,15 @@ table! {
     teams (id) {
         id -> Uuid,
         name -> Varchar,
-        admin_password -> Varchar,
         rules -> Array<Jsonb>,
+        current_season -> Nullable<Varchar>,
+        default_rule_id -> Nullable<Uuid>,
+        allow_self_fine -> Bool,
+        max_balance -> Nullable<Int8>,
+        clamp_to_max_balance -> Bool,
+        max_rules -> Int4,
+        token_hash -> Nullable<Varchar>,
+        grace_days -> Int4,
     }
 }
 
@@ -26,9 +104,33 @@ table! {
         lastname -> Varchar,
         nickname -> Nullable<Varchar>,
         email -> Nullable<Varchar>,
+        fine_multiplier -> Float4,
+        anonymized -> Bool,
+        role -> Nullable<Varchar>,
+        external_id -> Nullable<Varchar>,
+        on_leave_until -> Nullable<Date>,
     }
 }
 
+joinable!(admin_login_codes -> admins (admin_id));
+joinable!(admins -> teams (team_id));
+joinable!(deleted_sanctions_audit -> teams (team_id));
+joinable!(sanction_events -> admins (actor));
+joinable!(sanction_events -> sanctions (sanction_id));
 joinable!(sanctions -> users (user_id));
+joinable!(session_attendances -> sessions (session_id));
+joinable!(session_attendances -> users (user_id));
+joinable!(sessions -> teams (team_id));
+joinable!(users -> teams (team_id));
 
-allow_tables_to_appear_in_same_query!(sanctions, teams, users,);
+allow_tables_to_appear_in_same_query!(
+    admin_login_codes,
+    admins,
+    deleted_sanctions_audit,
+    sanction_events,
+    sanctions,
+    session_attendances,
+    sessions,
+    teams,
+    users,
+);
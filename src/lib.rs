@@ -1,6 +1,9 @@
+pub mod admins;
 pub mod api;
+pub mod config;
 pub mod database;
 pub mod sanctions;
+pub mod sessions;
 pub mod teams;
 pub mod test_utils;
 pub mod users;
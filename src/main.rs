@@ -1,44 +1,84 @@
-use rouille::{start_server, Response};
-use std::env::{var, VarError};
+use log::{info, warn};
+use rouille::{Response, Server};
+use std::time::Duration;
 
-use caisse_noire::api::{models::ErrorResponse, routes::handle_request};
-use caisse_noire::database::postgres::init_db_connection;
+#[cfg(feature = "metrics")]
+use caisse_noire::api::metrics::Metrics;
+use caisse_noire::api::{
+    routes::{compress_response, handle_request, trace_request},
+    server::{run_until_shutdown, Shutdown},
+};
+use caisse_noire::config::models::Config;
+use caisse_noire::database::{postgres::init_db_connections, routing::RoutedDb};
 
-fn extract_var(var_name: &str) -> Result<String, VarError> {
-    use dotenv::dotenv;
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
 
-    dotenv().ok();
-    var(var_name)
-}
-
-fn with_cors(response: Response) -> Response {
-    match extract_var("ENABLED_ORIGIN").ok() {
+fn with_cors(response: Response, enabled_origin: &Option<String>) -> Response {
+    match enabled_origin {
         Some(origin) => response
-            .with_additional_header("Access-Control-Allow-Origin", origin)
-            .with_additional_header("Access-Control-Allow-Headers", "content-type")
-            .with_additional_header("Access-Control-Allow-Methods", "GET, POST, DELETE"),
+            .with_additional_header("Access-Control-Allow-Origin", origin.clone())
+            .with_additional_header("Access-Control-Allow-Headers", "content-type, x-actor")
+            .with_additional_header(
+                "Access-Control-Allow-Methods",
+                "GET, POST, PUT, PATCH, DELETE",
+            ),
         None => response,
     }
 }
 
 fn main() {
-    let port = match extract_var("PORT") {
-        Ok(port) => port,
-        _ => panic!("PORT must be set"),
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(err) => panic!("{}", err),
     };
 
-    let database_url = match extract_var("DATABASE_URL") {
-        Ok(database_url) => database_url,
-        _ => panic!("DATABASE_URL must be set"),
-    };
+    env_logger::init();
+
+    let pools = init_db_connections(
+        &config.database_url,
+        config.replica_database_url.as_deref(),
+        config.pool_size,
+    );
+
+    let shutdown = Shutdown::new();
+    if let Err(err) = shutdown.register_signals() {
+        panic!("Could not register the shutdown signals: {}", err);
+    }
+
+    #[cfg(feature = "metrics")]
+    let metrics = Metrics::new();
 
-    start_server(format!("0.0.0.0:{}", port), move |request| {
-        with_cors(match init_db_connection(&database_url) {
-            Ok(db_connection) => handle_request(request, &db_connection),
-            Err(err) => {
-                let error_response: ErrorResponse = err.into();
-                error_response.into()
-            }
+    let handler_shutdown = shutdown.clone();
+    let listen_address = config.listen_address.clone();
+    let server = match Server::new(listen_address, move |request| {
+        let _in_flight = handler_shutdown.track();
+
+        trace_request(request, |request_id| {
+            let dispatch = || {
+                handle_request(
+                    request,
+                    &RoutedDb::new(&pools, request_id),
+                    config.password_min_length,
+                )
+            };
+            #[cfg(feature = "metrics")]
+            let response = metrics.handle_request(request, dispatch);
+            #[cfg(not(feature = "metrics"))]
+            let response = dispatch();
+
+            let response = with_cors(response, &config.enabled_origin);
+
+            compress_response(request, response)
         })
-    });
+    }) {
+        Ok(server) => server,
+        Err(err) => panic!("{}", err),
+    };
+
+    run_until_shutdown(server, &shutdown);
+
+    info!("Shutting down, waiting for the in flight requests");
+    if !shutdown.wait_for_in_flight(SHUTDOWN_TIMEOUT) {
+        warn!("{} requests were still running", shutdown.in_flight());
+    }
 }
@@ -0,0 +1,297 @@
+use chrono::{naive::NaiveDate, Utc};
+use std::collections::{hash_map::Entry, HashMap};
+use uuid::Uuid;
+
+use super::models::CreateSanction;
+use crate::database::{amount::Amount, postgres::DbError};
+use crate::teams::models::{Rule, Team};
+
+pub trait SanctionChecksDb {
+    fn get_users_teams(&self, user_ids: &[Uuid]) -> Result<HashMap<Uuid, Uuid>, DbError>;
+
+    fn get_leaves(&self, user_ids: &[Uuid]) -> Result<HashMap<Uuid, NaiveDate>, DbError>;
+
+    fn get_day_rules(&self, user_id: Uuid, date: NaiveDate) -> Result<Vec<(Uuid, bool)>, DbError>;
+
+    fn get_admins_users(&self, admin_ids: &[Uuid]) -> Result<HashMap<Uuid, Uuid>, DbError>;
+
+    fn get_unpaid_total_and_multiplier(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(Amount, f32), DbError>;
+}
+
+/// The teams must be locked; the unexisting ones are left to the foreign keys.
+pub fn check_sanctions<T>(
+    db: &T,
+    teams: &HashMap<Uuid, Team>,
+    sanctions: &[CreateSanction],
+    override_leave: bool,
+) -> Result<Vec<CreateSanction>, DbError>
+where
+    T: SanctionChecksDb,
+{
+    check_users_team(db, teams, sanctions)?;
+    if !override_leave {
+        check_leaves(db, sanctions)?;
+    }
+    check_max_per_day(db, teams, sanctions)?;
+    check_exclusions(db, teams, sanctions)?;
+    check_self_fines(db, teams, sanctions)?;
+
+    let sanctions: Vec<CreateSanction> = sanctions
+        .iter()
+        .map(|sanction| CreateSanction {
+            season: sanction.season.clone().or_else(|| {
+                teams
+                    .get(&sanction.team_id)
+                    .and_then(|team| team.current_season.clone())
+            }),
+            ..sanction.clone()
+        })
+        .collect();
+
+    apply_max_balances(db, teams, sanctions)
+}
+
+fn sanction_date(sanction: &CreateSanction) -> NaiveDate {
+    sanction
+        .created_at
+        .map(|created_at| created_at.naive_utc().date())
+        .unwrap_or_else(|| Utc::today().naive_utc())
+}
+
+fn find_rule<'a>(
+    teams: &'a HashMap<Uuid, Team>,
+    sanction: &CreateSanction,
+) -> Option<(&'a Team, &'a Rule)> {
+    let team = teams.get(&sanction.team_id)?;
+    let rule = team
+        .rules
+        .iter()
+        .find(|rule| rule.id == sanction.sanction_info.associated_rule)?;
+
+    Some((team, rule))
+}
+
+fn check_users_team<T: SanctionChecksDb>(
+    db: &T,
+    teams: &HashMap<Uuid, Team>,
+    sanctions: &[CreateSanction],
+) -> Result<(), DbError> {
+    let user_ids: Vec<Uuid> = sanctions.iter().map(|sanction| sanction.user_id).collect();
+    let user_teams = db.get_users_teams(&user_ids)?;
+
+    for sanction in sanctions {
+        match user_teams.get(&sanction.user_id) {
+            Some(team_id) if *team_id != sanction.team_id => {
+                return Err(DbError::NotValid(format!(
+                    "The user {} doesn't belong to the team {}",
+                    sanction.user_id, sanction.team_id
+                )))
+            }
+            _ => {}
+        }
+
+        let rule_id = sanction.sanction_info.associated_rule;
+        match teams.get(&sanction.team_id) {
+            Some(team) if !team.rules.iter().any(|rule| rule.id == rule_id) => {
+                return Err(DbError::NotValid(format!(
+                    "The rule {} doesn't belong to the team {}",
+                    rule_id, sanction.team_id
+                )))
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn check_leaves<T: SanctionChecksDb>(db: &T, sanctions: &[CreateSanction]) -> Result<(), DbError> {
+    let user_ids: Vec<Uuid> = sanctions.iter().map(|sanction| sanction.user_id).collect();
+    let leaves = db.get_leaves(&user_ids)?;
+
+    for sanction in sanctions {
+        match leaves.get(&sanction.user_id) {
+            Some(on_leave_until) if sanction_date(sanction) <= *on_leave_until => {
+                return Err(DbError::Forbidden(format!(
+                    "The user {} is on leave until {}",
+                    sanction.user_id, on_leave_until
+                )))
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn check_max_per_day<T: SanctionChecksDb>(
+    db: &T,
+    teams: &HashMap<Uuid, Team>,
+    sanctions: &[CreateSanction],
+) -> Result<(), DbError> {
+    let mut counts: HashMap<(Uuid, Uuid, NaiveDate), u32> = HashMap::new();
+
+    for sanction in sanctions {
+        let rule = match find_rule(teams, sanction) {
+            Some((_, rule)) => rule,
+            None => continue,
+        };
+        let max_per_day = match rule.max_per_day {
+            Some(max_per_day) => max_per_day,
+            None => continue,
+        };
+
+        let date = sanction_date(sanction);
+        let count = match counts.entry((sanction.user_id, rule.id, date)) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(
+                db.get_day_rules(sanction.user_id, date)?
+                    .iter()
+                    .filter(|(rule_id, waived)| *rule_id == rule.id && !waived)
+                    .count() as u32,
+            ),
+        };
+
+        if *count >= max_per_day {
+            return Err(DbError::Forbidden(format!(
+                "The rule {} can't be applied more than {} times a day",
+                rule.name, max_per_day
+            )));
+        }
+        *count += 1;
+    }
+
+    Ok(())
+}
+
+fn check_exclusions<T: SanctionChecksDb>(
+    db: &T,
+    teams: &HashMap<Uuid, Team>,
+    sanctions: &[CreateSanction],
+) -> Result<(), DbError> {
+    let mut rules_by_day: HashMap<(Uuid, NaiveDate), Vec<Uuid>> = HashMap::new();
+
+    for sanction in sanctions {
+        let (team, rule) = match find_rule(teams, sanction) {
+            Some(found) => found,
+            None => continue,
+        };
+        let excluded: Vec<&Rule> = team
+            .rules
+            .iter()
+            .filter(|other| rule.excludes.contains(&other.id) || other.excludes.contains(&rule.id))
+            .collect();
+
+        let date = sanction_date(sanction);
+        let day_rules = match rules_by_day.entry((sanction.user_id, date)) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(
+                db.get_day_rules(sanction.user_id, date)?
+                    .into_iter()
+                    .filter(|(_, waived)| !waived)
+                    .map(|(rule_id, _)| rule_id)
+                    .collect(),
+            ),
+        };
+
+        if let Some(other) = excluded.iter().find(|other| day_rules.contains(&other.id)) {
+            return Err(DbError::Forbidden(format!(
+                "The rule {} can't be applied the same day as the rule {}",
+                rule.name, other.name
+            )));
+        }
+        day_rules.push(rule.id);
+    }
+
+    Ok(())
+}
+
+fn check_self_fines<T: SanctionChecksDb>(
+    db: &T,
+    teams: &HashMap<Uuid, Team>,
+    sanctions: &[CreateSanction],
+) -> Result<(), DbError> {
+    let admin_ids: Vec<Uuid> = sanctions
+        .iter()
+        .filter_map(|sanction| sanction.created_by)
+        .collect();
+    if admin_ids.is_empty() {
+        return Ok(());
+    }
+    let admin_users = db.get_admins_users(&admin_ids)?;
+
+    for sanction in sanctions {
+        let allow_self_fine = teams
+            .get(&sanction.team_id)
+            .is_none_or(|team| team.allow_self_fine);
+        let creator_user = sanction
+            .created_by
+            .and_then(|admin_id| admin_users.get(&admin_id));
+
+        if !allow_self_fine && creator_user == Some(&sanction.user_id) {
+            return Err(DbError::Forbidden(format!(
+                "The user {} can't be sanctioned by their own admin account",
+                sanction.user_id
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// The sanctions created paid or waived aren't owed and are left as they are.
+fn apply_max_balances<T: SanctionChecksDb>(
+    db: &T,
+    teams: &HashMap<Uuid, Team>,
+    sanctions: Vec<CreateSanction>,
+) -> Result<Vec<CreateSanction>, DbError> {
+    let mut unpaid_totals: HashMap<Uuid, (Amount, f32)> = HashMap::new();
+
+    sanctions
+        .into_iter()
+        .map(|sanction| {
+            let team = match teams.get(&sanction.team_id) {
+                Some(team) => team,
+                None => return Ok(sanction),
+            };
+            let max_balance = match team.max_balance {
+                Some(max_balance) if sanction.paid_at.is_none() && sanction.waived_at.is_none() => {
+                    max_balance
+                }
+                _ => return Ok(sanction),
+            };
+
+            let (unpaid_total, fine_multiplier) = match unpaid_totals.entry(sanction.user_id) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(
+                    db.get_unpaid_total_and_multiplier(sanction.team_id, sanction.user_id)?,
+                ),
+            };
+            if *fine_multiplier <= 0.0 {
+                return Ok(sanction);
+            }
+
+            // The balance is the unpaid total scaled by the fine multiplier, so the
+            // cap is compared with the unpaid total once scaled the other way.
+            let max_total = max_balance.scale(1.0 / f64::from(*fine_multiplier));
+            let price = if *unpaid_total + sanction.price <= max_total {
+                sanction.price
+            } else if team.clamp_to_max_balance {
+                Amount::from_cents((max_total.cents() - unpaid_total.cents()).max(0))
+            } else {
+                return Err(DbError::Forbidden(format!(
+                    "The sanction would bring the balance of the user {} over the max balance of {}",
+                    sanction.user_id,
+                    max_balance.to_decimal()
+                )));
+            };
+            *unpaid_total = *unpaid_total + price;
+
+            Ok(CreateSanction { price, ..sanction })
+        })
+        .collect()
+}
@@ -1,17 +1,207 @@
-use chrono::naive::NaiveDate;
+use chrono::{naive::NaiveDate, DateTime, Utc};
+use std::collections::HashMap;
 use uuid::Uuid;
 
-use super::models::{CreateSanction, Sanction};
-use crate::database::postgres::DbError;
+use super::models::{
+    AuditEvent, Bucket, CreateSanction, ExtraInfo, FineStats, RuleImpact, Sanction, SanctionDetail,
+    SanctionFilter, SanctionQuery, SeasonArchive, TimelineEvent,
+};
+use crate::database::{
+    amount::Amount,
+    pagination::{Paginated, Pagination},
+    postgres::DbError,
+};
+use crate::teams::models::{Rule, RuleCategory};
+use crate::users::models::User;
 
 pub trait SanctionsDb {
     fn get_sanctions(
         &self,
         team_id: Uuid,
         date_interval: Option<(NaiveDate, NaiveDate)>,
+        filter: &SanctionFilter,
     ) -> Result<Vec<Sanction>, DbError>;
 
     fn create_sanctions(&self, sanctions: &[CreateSanction]) -> Result<Vec<Sanction>, DbError>;
 
-    fn delete_sanction(&self, team_id: Uuid, sanction_id: Uuid) -> Result<Sanction, DbError>;
+    fn create_sanctions_overriding_leave(
+        &self,
+        sanctions: &[CreateSanction],
+    ) -> Result<Vec<Sanction>, DbError>;
+
+    fn delete_sanction(
+        &self,
+        team_id: Uuid,
+        sanction_id: Uuid,
+        actor: Option<&str>,
+    ) -> Result<Sanction, DbError>;
+
+    fn get_fine_leaderboard(
+        &self,
+        team_id: Uuid,
+        pagination: Option<&Pagination>,
+    ) -> Result<Paginated<(User, Amount)>, DbError>;
+
+    fn query_sanctions(
+        &self,
+        team_id: Uuid,
+        query: SanctionQuery,
+    ) -> Result<Paginated<Sanction>, DbError>;
+}
+
+pub trait SanctionsLedgerDb {
+    fn get_unpaid_older_than(
+        &self,
+        team_id: Uuid,
+        days: i64,
+    ) -> Result<Vec<(User, Vec<Sanction>)>, DbError>;
+
+    fn get_rule_revenue(&self, team_id: Uuid) -> Result<Vec<(Rule, i64, Amount)>, DbError>;
+
+    /// Scaled by the fine multiplier. The sanctions in the grace period are left out.
+    fn get_balance(&self, team_id: Uuid, user_id: Uuid) -> Result<Amount, DbError>;
+
+    fn mark_user_sanctions_paid(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+        date: NaiveDate,
+        paid_by: Option<Uuid>,
+    ) -> Result<u64, DbError>;
+
+    fn get_user_timeline(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<TimelineEvent>, DbError>;
+
+    fn get_sanctions_by_category(
+        &self,
+        team_id: Uuid,
+        category: RuleCategory,
+    ) -> Result<Vec<Sanction>, DbError>;
+
+    fn preview_balance_with(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+        rule_id: Uuid,
+        extra_info: &ExtraInfo,
+    ) -> Result<Amount, DbError>;
+
+    fn get_unbroken_rules(&self, team_id: Uuid, user_id: Uuid) -> Result<Vec<Rule>, DbError>;
+
+    fn get_collection_rate(&self, team_id: Uuid) -> Result<f32, DbError>;
+
+    fn archive_season(&self, team_id: Uuid, season: &str) -> Result<SeasonArchive, DbError>;
+
+    fn get_daily_sanction_counts(
+        &self,
+        team_id: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<(NaiveDate, i64)>, DbError>;
+
+    fn get_rule_trend(
+        &self,
+        team_id: Uuid,
+        rule_id: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+        bucket: Bucket,
+    ) -> Result<Vec<(NaiveDate, i64)>, DbError>;
+
+    fn get_biggest_fine(&self, team_id: Uuid) -> Result<Option<(User, Sanction, Amount)>, DbError>;
+
+    /// Reaches across teams, so it must only be exposed to platform operators.
+    fn get_global_unpaid_total(&self) -> Result<Amount, DbError>;
+
+    /// The current week isn't counted as it is not over.
+    fn get_clean_streak(&self, team_id: Uuid, user_id: Uuid) -> Result<i64, DbError>;
+
+    fn get_balances(
+        &self,
+        team_id: Uuid,
+        user_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Amount>, DbError>;
+
+    fn find_future_dated_sanctions(&self, team_id: Uuid) -> Result<Vec<Sanction>, DbError>;
+
+    fn undo_last_sanction(&self, team_id: Uuid, user_id: Uuid) -> Result<Sanction, DbError>;
+
+    fn get_fine_distribution(&self, team_id: Uuid) -> Result<FineStats, DbError>;
+
+    fn dispute_sanction(
+        &self,
+        team_id: Uuid,
+        sanction_id: Uuid,
+        note: Option<&str>,
+    ) -> Result<Sanction, DbError>;
+
+    /// An overturned sanction is waived today.
+    fn resolve_dispute(
+        &self,
+        team_id: Uuid,
+        sanction_id: Uuid,
+        upheld: bool,
+    ) -> Result<Sanction, DbError>;
+
+    fn get_amount_by_category(
+        &self,
+        team_id: Uuid,
+    ) -> Result<Vec<(Option<RuleCategory>, Amount)>, DbError>;
+
+    fn get_recent_sanctions(&self, team_id: Uuid, hours: i64) -> Result<Vec<Sanction>, DbError>;
+
+    fn get_unused_rules(&self, team_id: Uuid, since: NaiveDate) -> Result<Vec<Rule>, DbError>;
+
+    fn simulate_rule_price_change(
+        &self,
+        team_id: Uuid,
+        rule_id: Uuid,
+        new_price: Amount,
+    ) -> Result<(Amount, Amount), DbError>;
+
+    fn get_sanction_details(&self, team_id: Uuid) -> Result<Vec<SanctionDetail>, DbError>;
+
+    /// The debt of the anonymized users stays in the sum but they aren't counted.
+    fn get_average_balance(&self, team_id: Uuid) -> Result<Amount, DbError>;
+
+    fn waive_sanctions_where(
+        &self,
+        team_id: Uuid,
+        date_interval: Option<(NaiveDate, NaiveDate)>,
+        filter: &SanctionFilter,
+        reason: &str,
+    ) -> Result<u64, DbError>;
+
+    fn get_rules_with_usage(&self, team_id: Uuid) -> Result<Vec<(Rule, i64)>, DbError>;
+
+    /// The deleted sanctions are in the deletion audit instead.
+    fn get_sanctions_modified_since(
+        &self,
+        team_id: Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<Sanction>, DbError>;
+
+    fn get_sanction_audit(
+        &self,
+        team_id: Uuid,
+        sanction_id: Uuid,
+    ) -> Result<Vec<AuditEvent>, DbError>;
+
+    fn get_user_vs_average(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(Amount, Amount, f32), DbError>;
+
+    fn get_fine_gini(&self, team_id: Uuid) -> Result<f32, DbError>;
+
+    fn rule_deletion_impact(&self, team_id: Uuid, rule_id: Uuid) -> Result<RuleImpact, DbError>;
+
+    fn get_top_rule_per_user(
+        &self,
+        team_id: Uuid,
+    ) -> Result<Vec<(User, Option<Rule>, i64)>, DbError>;
 }
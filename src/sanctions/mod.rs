@@ -1,3 +1,4 @@
+pub mod checks;
 pub mod interface;
 pub mod models;
 pub mod postgres;
@@ -1,44 +1,120 @@
-use chrono::naive::NaiveDate;
+use chrono::{naive::NaiveDate, DateTime, Datelike, Duration, Local, TimeZone, Utc};
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
 use diesel::{Insertable, Queryable};
 use diesel_as_jsonb::AsJsonb;
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::io::Write;
 use uuid::Uuid;
 
-use crate::database::schema::sanctions;
+use super::utils::formatter::format_amount;
+use crate::api::models::ValidationError;
+use crate::database::{
+    amount::Amount,
+    pagination::{Pagination, MAX_LIMIT},
+    schema::{deleted_sanctions_audit, sanction_events, sanctions},
+};
 use crate::teams::models::{Rule, RuleKind};
+use crate::users::models::{User, UserResponse};
 
 #[derive(Deserialize)]
 pub struct UpdateSanctionRequest {
     pub id: Option<Uuid>,
     pub user_id: Uuid,
-    pub sanction_info: SanctionInfo,
+    pub sanction_info: SanctionInfoRequest,
     pub created_at: Option<NaiveDate>,
+    pub paid_at: Option<NaiveDate>,
+    pub season: Option<String>,
+}
+
+impl UpdateSanctionRequest {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let today = Local::today().naive_local();
+
+        match self.created_at {
+            Some(created_at) if created_at > today => {
+                return Err(ValidationError {
+                    field: String::from("created_at"),
+                    reason: String::from("must not be in the future"),
+                })
+            }
+            _ => {}
+        }
+
+        match self.paid_at {
+            Some(paid_at) if paid_at < self.created_at.unwrap_or(today) => Err(ValidationError {
+                field: String::from("paid_at"),
+                reason: String::from("must not be before created_at"),
+            }),
+            _ => Ok(()),
+        }
+    }
 }
 
-impl From<(UpdateSanctionRequest, Uuid, f32)> for CreateSanction {
+#[derive(Deserialize)]
+pub struct SanctionInfoRequest {
+    pub associated_rule: Option<Uuid>,
+    pub extra_info: ExtraInfo,
+}
+
+impl From<(UpdateSanctionRequest, Uuid, SanctionInfo, Amount)> for CreateSanction {
     fn from(
-        (update_request, team_id, price): (UpdateSanctionRequest, Uuid, f32),
+        (update_request, team_id, sanction_info, price): (
+            UpdateSanctionRequest,
+            Uuid,
+            SanctionInfo,
+            Amount,
+        ),
     ) -> CreateSanction {
         CreateSanction {
             id: update_request.id.unwrap_or_else(Uuid::new_v4),
             user_id: update_request.user_id,
             team_id,
-            sanction_info: update_request.sanction_info,
+            sanction_info,
             price,
-            created_at: update_request.created_at,
+            created_at: update_request.created_at.map(start_of_day),
+            paid_at: update_request.paid_at,
+            waived_at: None,
+            season: update_request.season,
+            recorded_at: None,
+            created_by: None,
         }
     }
 }
 
+/// Midnight UTC, standing for a day when only the date of a sanction is known.
+pub fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    DateTime::from_utc(date.and_hms(0, 0, 0), Utc)
+}
+
 #[derive(Queryable, Debug, PartialEq, Serialize, Clone)]
 pub struct Sanction {
     pub id: Uuid,
     pub user_id: Uuid,
     pub team_id: Uuid,
     pub sanction_info: SanctionInfo,
-    pub price: f32,
-    pub created_at: NaiveDate,
+    pub price: Amount,
+    pub created_at: DateTime<Utc>,
+    pub paid_at: Option<NaiveDate>,
+    pub waived_at: Option<NaiveDate>,
+    pub season: Option<String>,
+    pub archived_at: Option<NaiveDate>,
+    pub disputed: bool,
+    pub dispute_note: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+    pub created_by: Option<Uuid>,
+    pub paid_by: Option<Uuid>,
+    pub waive_reason: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Sanction {
+    pub fn created_on(&self) -> NaiveDate {
+        self.created_at.naive_utc().date()
+    }
 }
 
 impl Default for Sanction {
@@ -49,20 +125,335 @@ impl Default for Sanction {
             team_id: Default::default(),
             sanction_info: Default::default(),
             price: Default::default(),
-            created_at: NaiveDate::from_ymd(2019, 10, 5),
+            created_at: start_of_day(NaiveDate::from_ymd(2019, 10, 5)),
+            paid_at: None,
+            waived_at: None,
+            season: None,
+            archived_at: None,
+            disputed: false,
+            dispute_note: None,
+            recorded_at: Utc.ymd(2019, 10, 5).and_hms(0, 0, 0),
+            created_by: None,
+            paid_by: None,
+            waive_reason: None,
+            updated_at: Utc.ymd(2019, 10, 5).and_hms(0, 0, 0),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SanctionResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub team_id: Uuid,
+    pub sanction_info: SanctionInfo,
+    pub price: Amount,
+    pub created_at: DateTime<Utc>,
+    pub paid_at: Option<NaiveDate>,
+    pub waived_at: Option<NaiveDate>,
+    pub season: Option<String>,
+    pub archived_at: Option<NaiveDate>,
+    pub disputed: bool,
+    pub dispute_note: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+    pub created_by: Option<Uuid>,
+    pub paid_by: Option<Uuid>,
+    pub waive_reason: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<Sanction> for SanctionResponse {
+    fn from(sanction: Sanction) -> SanctionResponse {
+        SanctionResponse {
+            id: sanction.id,
+            user_id: sanction.user_id,
+            team_id: sanction.team_id,
+            sanction_info: sanction.sanction_info,
+            price: sanction.price,
+            created_at: sanction.created_at,
+            paid_at: sanction.paid_at,
+            waived_at: sanction.waived_at,
+            season: sanction.season,
+            archived_at: sanction.archived_at,
+            disputed: sanction.disputed,
+            dispute_note: sanction.dispute_note,
+            recorded_at: sanction.recorded_at,
+            created_by: sanction.created_by,
+            paid_by: sanction.paid_by,
+            waive_reason: sanction.waive_reason,
+            updated_at: sanction.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FormattedSanction {
+    #[serde(flatten)]
+    pub sanction: SanctionResponse,
+    pub formatted_price: String,
+}
+
+impl From<(Sanction, &str, &str)> for FormattedSanction {
+    fn from((sanction, currency, locale): (Sanction, &str, &str)) -> FormattedSanction {
+        FormattedSanction {
+            formatted_price: format_amount(sanction.price, currency, locale),
+            sanction: sanction.into(),
         }
     }
 }
 
-#[derive(Insertable, Default)]
+#[derive(Insertable, Default, Clone)]
 #[table_name = "sanctions"]
 pub struct CreateSanction {
     pub id: Uuid,
     pub user_id: Uuid,
     pub team_id: Uuid,
     pub sanction_info: SanctionInfo,
-    pub price: f32,
-    pub created_at: Option<NaiveDate>,
+    pub price: Amount,
+    pub created_at: Option<DateTime<Utc>>,
+    pub paid_at: Option<NaiveDate>,
+    pub waived_at: Option<NaiveDate>,
+    pub season: Option<String>,
+    pub recorded_at: Option<DateTime<Utc>>,
+    pub created_by: Option<Uuid>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SanctionFilter {
+    pub include_paid: bool,
+    pub include_waived: bool,
+    pub season: Option<String>,
+    pub user_id: Option<Uuid>,
+    pub rule_id: Option<Uuid>,
+}
+
+impl Default for SanctionFilter {
+    fn default() -> SanctionFilter {
+        SanctionFilter {
+            include_paid: true,
+            include_waived: false,
+            season: None,
+            user_id: None,
+            rule_id: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum SanctionOrder {
+    #[default]
+    NewestFirst,
+    OldestFirst,
+    HighestPriceFirst,
+}
+
+/// The paid and waived flags keep only the settled sanctions when true, the others when false.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SanctionQuery {
+    pub user_id: Option<Uuid>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub paid: Option<bool>,
+    pub waived: Option<bool>,
+    pub order: SanctionOrder,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl SanctionQuery {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from > to {
+                return Err(ValidationError {
+                    field: String::from("from"),
+                    reason: format!("must not be after the to date {}", to),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn pagination(&self) -> Pagination {
+        Pagination::new(self.limit, self.offset, MAX_LIMIT)
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TimelineEventKind {
+    Fined,
+    Paid,
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct TimelineEvent {
+    pub kind: TimelineEventKind,
+    pub date: NaiveDate,
+    pub sanction_id: Uuid,
+    pub rule_name: Option<String>,
+    pub amount: Amount,
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SeasonUserTotal {
+    pub user_id: Uuid,
+    pub total: Amount,
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SeasonArchive {
+    pub season: String,
+    pub users: Vec<SeasonUserTotal>,
+    pub total: Amount,
+    pub collection_rate: f32,
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FineStats {
+    pub count: i64,
+    pub mean: Amount,
+    pub median: Amount,
+    pub min: Amount,
+    pub max: Amount,
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleImpact {
+    pub sanctions_count: i64,
+    pub total: Amount,
+    pub users_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaderboardEntry {
+    pub user: UserResponse,
+    pub total: Amount,
+}
+
+impl From<(User, Amount)> for LeaderboardEntry {
+    fn from((user, total): (User, Amount)) -> LeaderboardEntry {
+        LeaderboardEntry {
+            user: user.into(),
+            total,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bucket {
+    Week,
+    Month,
+}
+
+impl Bucket {
+    pub fn start_of(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Bucket::Week => date - Duration::days(i64::from(date.weekday().num_days_from_monday())),
+            Bucket::Month => date.with_day(1).unwrap(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SanctionDetail {
+    #[serde(flatten)]
+    pub sanction: SanctionResponse,
+    pub creator_name: Option<String>,
+    pub settler_name: Option<String>,
+}
+
+#[derive(Queryable, Insertable, Debug, PartialEq, Clone)]
+#[table_name = "deleted_sanctions_audit"]
+pub struct DeletedSanctionAudit {
+    pub id: Uuid,
+    pub sanction_id: Uuid,
+    pub team_id: Uuid,
+    pub sanction: serde_json::Value,
+    pub actor: Option<String>,
+    pub deleted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone, Copy, AsExpression, FromSqlRow)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sql_type = "Text"]
+pub enum AuditEventKind {
+    Created,
+    Paid,
+    Waived,
+    Disputed,
+    DisputeUpheld,
+    DisputeOverturned,
+}
+
+impl AuditEventKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            AuditEventKind::Created => "CREATED",
+            AuditEventKind::Paid => "PAID",
+            AuditEventKind::Waived => "WAIVED",
+            AuditEventKind::Disputed => "DISPUTED",
+            AuditEventKind::DisputeUpheld => "DISPUTE_UPHELD",
+            AuditEventKind::DisputeOverturned => "DISPUTE_OVERTURNED",
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for AuditEventKind {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        ToSql::<Text, Pg>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for AuditEventKind {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<AuditEventKind> {
+        let kind = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+
+        [
+            AuditEventKind::Created,
+            AuditEventKind::Paid,
+            AuditEventKind::Waived,
+            AuditEventKind::Disputed,
+            AuditEventKind::DisputeUpheld,
+            AuditEventKind::DisputeOverturned,
+        ]
+        .iter()
+        .copied()
+        .find(|known_kind| known_kind.as_str() == kind)
+        .ok_or_else(|| format!("Unknown audit event kind {}", kind).into())
+    }
+}
+
+#[derive(Queryable, Insertable, Serialize, Debug, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "sanction_events"]
+pub struct AuditEvent {
+    pub id: Uuid,
+    pub sanction_id: Uuid,
+    pub team_id: Uuid,
+    pub kind: AuditEventKind,
+    pub actor: Option<Uuid>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl AuditEvent {
+    pub fn new(sanction: &Sanction, kind: AuditEventKind, actor: Option<Uuid>) -> AuditEvent {
+        AuditEvent {
+            id: Uuid::new_v4(),
+            sanction_id: sanction.id,
+            team_id: sanction.team_id,
+            kind,
+            actor,
+            occurred_at: Utc::now(),
+        }
+    }
 }
 
 pub struct SanctionInfoError {
@@ -71,14 +462,26 @@ pub struct SanctionInfoError {
     pub extra_info: String,
 }
 
+impl fmt::Display for SanctionInfoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The rule {} has the kind {} which can't be associated with {}",
+            self.associated_rule_name, self.associated_rule_kind, self.extra_info
+        )
+    }
+}
+
 #[derive(Debug, AsJsonb, Serialize, Deserialize, PartialEq, Clone, Default)]
 pub struct SanctionInfo {
     pub associated_rule: Uuid,
+    /// Defaults to `ExtraInfo::None` so that the older rows are still read.
+    #[serde(default)]
     pub extra_info: ExtraInfo,
 }
 
 impl SanctionInfo {
-    pub fn get_price(&self, rule: Rule) -> Result<f32, SanctionInfoError> {
+    pub fn get_price(&self, rule: Rule) -> Result<Amount, SanctionInfoError> {
         match self.extra_info {
             ExtraInfo::None => match rule.kind {
                 RuleKind::Basic { price } => Ok(price),
@@ -87,11 +490,15 @@ impl SanctionInfo {
             ExtraInfo::Multiplication { factor } => match rule.kind {
                 RuleKind::Multiplication {
                     price_to_multiply, ..
-                } => Ok(price_to_multiply * (factor as f32)),
+                } => Ok(price_to_multiply * i64::from(factor)),
                 RuleKind::TimeMultiplication {
                     price_per_time_unit,
                     ..
-                } => Ok(price_per_time_unit * (factor as f32)),
+                } => Ok(price_per_time_unit * i64::from(factor)),
+                _ => Err(()),
+            },
+            ExtraInfo::Percentage { base } => match rule.kind {
+                RuleKind::Percentage { rate } => Ok(base.scale(f64::from(rate))),
                 _ => Err(()),
             },
         }
@@ -108,6 +515,7 @@ impl SanctionInfo {
 pub enum ExtraInfo {
     None,
     Multiplication { factor: u32 },
+    Percentage { base: Amount },
 }
 
 impl Default for ExtraInfo {
@@ -121,6 +529,52 @@ impl fmt::Display for ExtraInfo {
         match self {
             ExtraInfo::None => write!(f, "NONE"),
             ExtraInfo::Multiplication { .. } => write!(f, "MULTIPLICATION"),
+            ExtraInfo::Percentage { .. } => write!(f, "PERCENTAGE"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_sanction_info_without_extra_info() {
+        let associated_rule = Uuid::new_v4();
+
+        let sanction_info: SanctionInfo =
+            serde_json::from_value(json!({ "associated_rule": associated_rule })).unwrap();
+
+        assert_eq!(
+            sanction_info,
+            SanctionInfo {
+                associated_rule,
+                extra_info: ExtraInfo::None,
+            }
+        );
+        assert!(serde_json::from_value::<SanctionInfoRequest>(json!({
+            "associated_rule": associated_rule
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn test_sanction_query_pagination_is_clamped() {
+        let query = SanctionQuery {
+            limit: Some(MAX_LIMIT + 1),
+            offset: Some(-1),
+            ..Default::default()
+        };
+
+        assert!(query.validate().is_ok());
+        assert_eq!(
+            query.pagination(),
+            Pagination {
+                limit: MAX_LIMIT,
+                offset: 0,
+            }
+        );
+    }
+}
@@ -1,132 +1,4346 @@
-use chrono::naive::NaiveDate;
+use chrono::{naive::NaiveDate, DateTime, Datelike, Duration, Local, Utc};
+use diesel::pg::Pg;
 use diesel::prelude::*;
+use std::collections::{BTreeMap, HashMap};
 use std::ops::Deref;
 use uuid::Uuid;
 
 use super::{
-    interface::SanctionsDb,
-    models::{CreateSanction, Sanction},
+    checks::{check_sanctions, SanctionChecksDb},
+    interface::{SanctionsDb, SanctionsLedgerDb},
+    models::{
+        start_of_day, AuditEvent, AuditEventKind, Bucket, CreateSanction, DeletedSanctionAudit,
+        ExtraInfo, FineStats, RuleImpact, Sanction, SanctionDetail, SanctionFilter, SanctionInfo,
+        SanctionOrder, SanctionQuery, SeasonArchive, SeasonUserTotal, TimelineEvent,
+        TimelineEventKind,
+    },
 };
 use crate::database::{
+    amount::Amount,
+    pagination::{Paginated, Pagination},
     postgres::{DbConnection, DbError},
-    schema::sanctions,
+    schema::{admins, deleted_sanctions_audit, sanction_events, sanctions, teams, users},
 };
+use crate::teams::models::{Rule, RuleCategory, Team};
+use crate::users::models::User;
+
+/// Locks the teams so that concurrent creations are checked against each other.
+fn lock_teams(
+    conn: &DbConnection,
+    sanctions: &[CreateSanction],
+) -> Result<HashMap<Uuid, Team>, DbError> {
+    let mut teams: HashMap<Uuid, Team> = HashMap::new();
+
+    for sanction in sanctions {
+        if teams.contains_key(&sanction.team_id) {
+            continue;
+        }
+
+        let team: Option<Team> = teams::table
+            .find(sanction.team_id)
+            .for_update()
+            .get_result(conn.deref())
+            .optional()?;
+
+        if let Some(team) = team {
+            teams.insert(team.id, team);
+        }
+    }
+
+    Ok(teams)
+}
+
+impl SanctionChecksDb for DbConnection {
+    fn get_users_teams(&self, user_ids: &[Uuid]) -> Result<HashMap<Uuid, Uuid>, DbError> {
+        let user_teams = users::table
+            .filter(users::id.eq_any(user_ids))
+            .select((users::id, users::team_id))
+            .get_results::<(Uuid, Uuid)>(self.deref())?
+            .into_iter()
+            .collect();
+
+        Ok(user_teams)
+    }
+
+    fn get_leaves(&self, user_ids: &[Uuid]) -> Result<HashMap<Uuid, NaiveDate>, DbError> {
+        let leaves = users::table
+            .filter(
+                users::id
+                    .eq_any(user_ids)
+                    .and(users::on_leave_until.is_not_null()),
+            )
+            .select((users::id, users::on_leave_until))
+            .get_results::<(Uuid, Option<NaiveDate>)>(self.deref())?
+            .into_iter()
+            .filter_map(|(user_id, on_leave_until)| {
+                on_leave_until.map(|on_leave_until| (user_id, on_leave_until))
+            })
+            .collect();
+
+        Ok(leaves)
+    }
+
+    fn get_day_rules(&self, user_id: Uuid, date: NaiveDate) -> Result<Vec<(Uuid, bool)>, DbError> {
+        let infos: Vec<(SanctionInfo, Option<NaiveDate>)> = sanctions::table
+            .filter(
+                sanctions::user_id
+                    .eq(user_id)
+                    .and(sanctions::created_at.ge(start_of_day(date)))
+                    .and(sanctions::created_at.lt(start_of_day(date.succ()))),
+            )
+            .select((sanctions::sanction_info, sanctions::waived_at))
+            .get_results(self.deref())?;
+
+        Ok(infos
+            .into_iter()
+            .map(|(info, waived_at)| (info.associated_rule, waived_at.is_some()))
+            .collect())
+    }
+
+    fn get_admins_users(&self, admin_ids: &[Uuid]) -> Result<HashMap<Uuid, Uuid>, DbError> {
+        let admin_users = admins::table
+            .filter(
+                admins::id
+                    .eq_any(admin_ids)
+                    .and(admins::user_id.is_not_null()),
+            )
+            .select((admins::id, admins::user_id))
+            .get_results::<(Uuid, Option<Uuid>)>(self.deref())?
+            .into_iter()
+            .filter_map(|(admin_id, user_id)| user_id.map(|user_id| (admin_id, user_id)))
+            .collect();
+
+        Ok(admin_users)
+    }
+
+    fn get_unpaid_total_and_multiplier(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(Amount, f32), DbError> {
+        Ok((
+            get_unpaid_total(self, team_id, user_id, None)?,
+            get_fine_multiplier(self, team_id, user_id)?,
+        ))
+    }
+}
+
+fn record_events(conn: &DbConnection, events: &[AuditEvent]) -> Result<(), DbError> {
+    if !events.is_empty() {
+        diesel::insert_into(sanction_events::table)
+            .values(events)
+            .execute(conn.deref())?;
+    }
+
+    Ok(())
+}
+
+fn get_fine_multiplier(conn: &DbConnection, team_id: Uuid, user_id: Uuid) -> Result<f32, DbError> {
+    let fine_multiplier: f32 = users::table
+        .filter(users::team_id.eq(team_id).and(users::id.eq(user_id)))
+        .select(users::fine_multiplier)
+        .get_result(conn.deref())?;
+
+    Ok(fine_multiplier)
+}
+
+fn get_grace_cutoff(conn: &DbConnection, team_id: Uuid) -> Result<Option<DateTime<Utc>>, DbError> {
+    let grace_days: i32 = teams::table
+        .find(team_id)
+        .select(teams::grace_days)
+        .get_result(conn.deref())?;
+
+    Ok(if grace_days > 0 {
+        Some(Utc::now() - Duration::days(i64::from(grace_days)))
+    } else {
+        None
+    })
+}
+
+fn get_unpaid_total(
+    conn: &DbConnection,
+    team_id: Uuid,
+    user_id: Uuid,
+    counted_before: Option<DateTime<Utc>>,
+) -> Result<Amount, DbError> {
+    let mut query = sanctions::table
+        .filter(
+            sanctions::team_id
+                .eq(team_id)
+                .and(sanctions::user_id.eq(user_id))
+                .and(sanctions::paid_at.is_null())
+                .and(sanctions::waived_at.is_null())
+                .and(sanctions::archived_at.is_null()),
+        )
+        .select(sanctions::price)
+        .into_boxed();
+    if let Some(counted_before) = counted_before {
+        query = query.filter(sanctions::created_at.lt(counted_before));
+    }
+
+    let prices: Vec<Amount> = query.get_results(conn.deref())?;
+
+    Ok(prices.into_iter().sum())
+}
+
+fn collection_rate(sanctions: &[(Amount, Option<NaiveDate>)]) -> f32 {
+    let levied: Amount = sanctions.iter().map(|(price, _)| price).sum();
+    let collected: Amount = sanctions
+        .iter()
+        .filter(|(_, paid_at)| paid_at.is_some())
+        .map(|(price, _)| price)
+        .sum();
+
+    if levied.cents() == 0 {
+        return 0.0;
+    }
+
+    (collected.cents() as f64 / levied.cents() as f64) as f32
+}
+
+fn gini_coefficient(totals: &[Amount]) -> f32 {
+    let sum: i64 = totals.iter().map(|total| total.cents()).sum();
+    if totals.len() < 2 || sum == 0 {
+        return 0.0;
+    }
+
+    let differences: i64 = totals
+        .iter()
+        .flat_map(|a| totals.iter().map(move |b| (a.cents() - b.cents()).abs()))
+        .sum();
+
+    // The mean being sum / n, n² * 2 * mean simplifies to 2 * n * sum.
+    (differences as f64 / (2.0 * totals.len() as f64 * sum as f64)) as f32
+}
+
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(i64::from(date.weekday().num_days_from_monday()))
+}
+
+fn insert_sanctions(
+    conn: &DbConnection,
+    sanctions: &[CreateSanction],
+    override_leave: bool,
+) -> Result<Vec<Sanction>, DbError> {
+    conn.deref().transaction::<_, DbError, _>(|| {
+        let teams = lock_teams(conn, sanctions)?;
+        let sanctions = check_sanctions(conn, &teams, sanctions, override_leave)?;
+
+        let sanctions: Vec<Sanction> = diesel::insert_into(sanctions::table)
+            .values(&sanctions)
+            .get_results(conn.deref())?;
+
+        let events: Vec<AuditEvent> = sanctions
+            .iter()
+            .map(|sanction| AuditEvent::new(sanction, AuditEventKind::Created, sanction.created_by))
+            .collect();
+        record_events(conn, &events)?;
+
+        Ok(sanctions)
+    })
+}
+
+fn filter_sanctions(team_id: Uuid, query: &SanctionQuery) -> sanctions::BoxedQuery<'static, Pg> {
+    let mut sanctions_query = sanctions::table
+        .filter(sanctions::team_id.eq(team_id))
+        .into_boxed();
+
+    if let Some(user_id) = query.user_id {
+        sanctions_query = sanctions_query.filter(sanctions::user_id.eq(user_id));
+    }
+    if let Some(from) = query.from {
+        sanctions_query = sanctions_query.filter(sanctions::created_at.ge(start_of_day(from)));
+    }
+    if let Some(to) = query.to {
+        sanctions_query = sanctions_query.filter(sanctions::created_at.lt(start_of_day(to.succ())));
+    }
+    match query.paid {
+        Some(true) => sanctions_query = sanctions_query.filter(sanctions::paid_at.is_not_null()),
+        Some(false) => sanctions_query = sanctions_query.filter(sanctions::paid_at.is_null()),
+        None => {}
+    }
+    match query.waived {
+        Some(true) => sanctions_query = sanctions_query.filter(sanctions::waived_at.is_not_null()),
+        Some(false) => sanctions_query = sanctions_query.filter(sanctions::waived_at.is_null()),
+        None => {}
+    }
+
+    sanctions_query
+}
 
 impl SanctionsDb for DbConnection {
     fn get_sanctions(
         &self,
         team_id: Uuid,
         date_interval: Option<(NaiveDate, NaiveDate)>,
+        filter: &SanctionFilter,
+    ) -> Result<Vec<Sanction>, DbError> {
+        let mut query = sanctions::table
+            .filter(sanctions::team_id.eq(team_id))
+            .into_boxed();
+
+        if let Some((min, max)) = date_interval {
+            query = query.filter(
+                sanctions::created_at
+                    .ge(start_of_day(min))
+                    .and(sanctions::created_at.lt(start_of_day(max.succ()))),
+            );
+        }
+        if !filter.include_paid {
+            query = query.filter(sanctions::paid_at.is_null());
+        }
+        if !filter.include_waived {
+            query = query.filter(sanctions::waived_at.is_null());
+        }
+        if let Some(season) = &filter.season {
+            query = query.filter(sanctions::season.eq(season));
+        }
+        if let Some(user_id) = filter.user_id {
+            query = query.filter(sanctions::user_id.eq(user_id));
+        }
+
+        let sanctions: Vec<Sanction> = query.get_results(self.deref())?;
+
+        Ok(match filter.rule_id {
+            Some(rule_id) => sanctions
+                .into_iter()
+                .filter(|sanction| sanction.sanction_info.associated_rule == rule_id)
+                .collect(),
+            None => sanctions,
+        })
+    }
+
+    fn create_sanctions(&self, sanctions: &[CreateSanction]) -> Result<Vec<Sanction>, DbError> {
+        insert_sanctions(self, sanctions, false)
+    }
+
+    fn create_sanctions_overriding_leave(
+        &self,
+        sanctions: &[CreateSanction],
+    ) -> Result<Vec<Sanction>, DbError> {
+        insert_sanctions(self, sanctions, true)
+    }
+
+    fn delete_sanction(
+        &self,
+        team_id: Uuid,
+        sanction_id: Uuid,
+        actor: Option<&str>,
+    ) -> Result<Sanction, DbError> {
+        self.deref().transaction::<_, DbError, _>(|| {
+            let sanction: Sanction = diesel::delete(
+                sanctions::table.filter(
+                    sanctions::team_id
+                        .eq(team_id)
+                        .and(sanctions::id.eq(sanction_id)),
+                ),
+            )
+            .get_result(self.deref())?;
+
+            diesel::insert_into(deleted_sanctions_audit::table)
+                .values(&DeletedSanctionAudit {
+                    id: Uuid::new_v4(),
+                    sanction_id,
+                    team_id,
+                    sanction: serde_json::to_value(&sanction).map_err(|_| DbError::Unknown)?,
+                    actor: actor.map(String::from),
+                    deleted_at: Utc::now(),
+                })
+                .execute(self.deref())?;
+
+            Ok(sanction)
+        })
+    }
+
+    fn get_fine_leaderboard(
+        &self,
+        team_id: Uuid,
+        pagination: Option<&Pagination>,
+    ) -> Result<Paginated<(User, Amount)>, DbError> {
+        teams::table
+            .find(team_id)
+            .select(teams::id)
+            .get_result::<Uuid>(self.deref())?;
+
+        let users: Vec<User> = users::table
+            .filter(users::team_id.eq(team_id).and(users::anonymized.eq(false)))
+            .get_results(self.deref())?;
+        let prices: Vec<(Uuid, Amount)> = sanctions::table
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::waived_at.is_null()),
+            )
+            .select((sanctions::user_id, sanctions::price))
+            .get_results(self.deref())?;
+
+        let mut leaderboard: Vec<(User, Amount)> = users
+            .into_iter()
+            .map(|user| {
+                let total: Amount = prices
+                    .iter()
+                    .filter(|(user_id, _)| *user_id == user.id)
+                    .map(|(_, price)| price)
+                    .sum();
+
+                (user, total)
+            })
+            .collect();
+        leaderboard.sort_by(|(a, a_total), (b, b_total)| {
+            b_total
+                .cmp(a_total)
+                .then_with(|| a.lastname.cmp(&b.lastname))
+                .then_with(|| a.firstname.cmp(&b.firstname))
+                .then_with(|| a.id.cmp(&b.id))
+        });
+
+        let total = leaderboard.len() as i64;
+        let pagination = match pagination {
+            Some(pagination) => pagination.clone(),
+            None => Pagination {
+                limit: total,
+                offset: 0,
+            },
+        };
+        let items: Vec<(User, Amount)> = leaderboard
+            .into_iter()
+            .skip(pagination.offset as usize)
+            .take(pagination.limit as usize)
+            .collect();
+
+        Ok(Paginated::new(items, total, &pagination))
+    }
+
+    fn query_sanctions(
+        &self,
+        team_id: Uuid,
+        query: SanctionQuery,
+    ) -> Result<Paginated<Sanction>, DbError> {
+        query.validate()?;
+        let pagination = query.pagination();
+
+        let total: i64 = filter_sanctions(team_id, &query)
+            .count()
+            .get_result(self.deref())?;
+
+        let sanctions_query = filter_sanctions(team_id, &query);
+        let sanctions_query = match query.order {
+            SanctionOrder::NewestFirst => {
+                sanctions_query.order_by((sanctions::created_at.desc(), sanctions::id))
+            }
+            SanctionOrder::OldestFirst => {
+                sanctions_query.order_by((sanctions::created_at, sanctions::id))
+            }
+            SanctionOrder::HighestPriceFirst => {
+                sanctions_query.order_by((sanctions::price.desc(), sanctions::id))
+            }
+        };
+        let sanctions: Vec<Sanction> = sanctions_query
+            .limit(pagination.limit)
+            .offset(pagination.offset)
+            .get_results(self.deref())?;
+
+        Ok(Paginated::new(sanctions, total, &pagination))
+    }
+}
+
+impl SanctionsLedgerDb for DbConnection {
+    fn get_unpaid_older_than(
+        &self,
+        team_id: Uuid,
+        days: i64,
+    ) -> Result<Vec<(User, Vec<Sanction>)>, DbError> {
+        let limit_date = Local::today().naive_local() - Duration::days(days);
+
+        let rows: Vec<(User, Sanction)> = sanctions::table
+            .inner_join(users::table)
+            .select((users::all_columns, sanctions::all_columns))
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::paid_at.is_null())
+                    .and(sanctions::waived_at.is_null())
+                    .and(sanctions::created_at.lt(start_of_day(limit_date))),
+            )
+            .order_by((users::id, sanctions::created_at))
+            .get_results(self.deref())?;
+
+        let mut result: Vec<(User, Vec<Sanction>)> = vec![];
+
+        rows.into_iter()
+            .for_each(|(user, sanction)| match result.last_mut() {
+                Some((last_user, sanctions)) if last_user.id == user.id => sanctions.push(sanction),
+                _ => result.push((user, vec![sanction])),
+            });
+
+        Ok(result)
+    }
+
+    fn get_rule_revenue(&self, team_id: Uuid) -> Result<Vec<(Rule, i64, Amount)>, DbError> {
+        let team: Team = teams::table.find(team_id).get_result(self.deref())?;
+
+        let sanctions: Vec<Sanction> = sanctions::table
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::waived_at.is_null()),
+            )
+            .get_results(self.deref())?;
+
+        let mut result: Vec<(Rule, i64, Amount)> = team
+            .rules
+            .into_iter()
+            .map(|rule| {
+                let rule_sanctions = sanctions
+                    .iter()
+                    .filter(|sanction| sanction.sanction_info.associated_rule == rule.id);
+
+                let count = rule_sanctions.clone().count() as i64;
+                let total = rule_sanctions.map(|sanction| sanction.price).sum();
+
+                (rule, count, total)
+            })
+            .collect();
+
+        result.sort_by(|(_, _, total_1), (_, _, total_2)| total_2.cmp(total_1));
+
+        Ok(result)
+    }
+
+    fn get_balance(&self, team_id: Uuid, user_id: Uuid) -> Result<Amount, DbError> {
+        let fine_multiplier = get_fine_multiplier(self, team_id, user_id)?;
+        let counted_before = get_grace_cutoff(self, team_id)?;
+
+        Ok(get_unpaid_total(self, team_id, user_id, counted_before)?
+            .scale(f64::from(fine_multiplier)))
+    }
+
+    fn mark_user_sanctions_paid(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+        date: NaiveDate,
+        paid_by: Option<Uuid>,
+    ) -> Result<u64, DbError> {
+        self.deref().transaction::<_, DbError, _>(|| {
+            let sanctions: Vec<Sanction> = diesel::update(
+                sanctions::table.filter(
+                    sanctions::team_id
+                        .eq(team_id)
+                        .and(sanctions::user_id.eq(user_id))
+                        .and(sanctions::paid_at.is_null())
+                        .and(sanctions::waived_at.is_null()),
+                ),
+            )
+            .set((sanctions::paid_at.eq(date), sanctions::paid_by.eq(paid_by)))
+            .get_results(self.deref())?;
+
+            let events: Vec<AuditEvent> = sanctions
+                .iter()
+                .map(|sanction| AuditEvent::new(sanction, AuditEventKind::Paid, paid_by))
+                .collect();
+            record_events(self, &events)?;
+
+            Ok(sanctions.len() as u64)
+        })
+    }
+
+    fn get_user_timeline(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<TimelineEvent>, DbError> {
+        let team: Team = teams::table.find(team_id).get_result(self.deref())?;
+        users::table
+            .filter(users::team_id.eq(team_id).and(users::id.eq(user_id)))
+            .select(users::id)
+            .get_result::<Uuid>(self.deref())?;
+
+        let sanctions: Vec<Sanction> = sanctions::table
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::user_id.eq(user_id))
+                    .and(sanctions::waived_at.is_null()),
+            )
+            .get_results(self.deref())?;
+
+        let mut events: Vec<TimelineEvent> = sanctions
+            .into_iter()
+            .flat_map(|sanction| {
+                let rule_name = team
+                    .rules
+                    .iter()
+                    .find(|rule| rule.id == sanction.sanction_info.associated_rule)
+                    .map(|rule| rule.name.clone());
+                let event = |kind, date| TimelineEvent {
+                    kind,
+                    date,
+                    sanction_id: sanction.id,
+                    rule_name: rule_name.clone(),
+                    amount: sanction.price,
+                };
+
+                let mut sanction_events =
+                    vec![event(TimelineEventKind::Fined, sanction.created_on())];
+                if let Some(paid_at) = sanction.paid_at {
+                    sanction_events.push(event(TimelineEventKind::Paid, paid_at));
+                }
+
+                sanction_events
+            })
+            .collect();
+
+        events.sort_by(|event_1, event_2| {
+            (event_1.date, &event_1.kind).cmp(&(event_2.date, &event_2.kind))
+        });
+
+        Ok(events)
+    }
+
+    fn get_sanctions_by_category(
+        &self,
+        team_id: Uuid,
+        category: RuleCategory,
     ) -> Result<Vec<Sanction>, DbError> {
-        let sanctions = match date_interval {
-            Some((min, max)) => sanctions::table
+        let team: Team = teams::table.find(team_id).get_result(self.deref())?;
+
+        let sanctions: Vec<Sanction> = sanctions::table
+            .filter(sanctions::team_id.eq(team_id))
+            .get_results(self.deref())?;
+
+        Ok(sanctions
+            .into_iter()
+            .filter(|sanction| {
+                team.rules.iter().any(|rule| {
+                    rule.id == sanction.sanction_info.associated_rule && rule.category == category
+                })
+            })
+            .collect())
+    }
+
+    fn preview_balance_with(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+        rule_id: Uuid,
+        extra_info: &ExtraInfo,
+    ) -> Result<Amount, DbError> {
+        let team: Team = teams::table.find(team_id).get_result(self.deref())?;
+        let rule = team.get_rule(rule_id).ok_or(DbError::NotFound)?;
+
+        let price = SanctionInfo {
+            associated_rule: rule_id,
+            extra_info: extra_info.clone(),
+        }
+        .get_price(rule)?;
+
+        let fine_multiplier = get_fine_multiplier(self, team_id, user_id)?;
+        let counted_before = get_grace_cutoff(self, team_id)?;
+
+        Ok(
+            (get_unpaid_total(self, team_id, user_id, counted_before)? + price)
+                .scale(f64::from(fine_multiplier)),
+        )
+    }
+
+    fn get_unbroken_rules(&self, team_id: Uuid, user_id: Uuid) -> Result<Vec<Rule>, DbError> {
+        let team: Team = teams::table.find(team_id).get_result(self.deref())?;
+        users::table
+            .filter(users::team_id.eq(team_id).and(users::id.eq(user_id)))
+            .select(users::id)
+            .get_result::<Uuid>(self.deref())?;
+
+        let broken_rules: Vec<Uuid> = sanctions::table
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::user_id.eq(user_id))
+                    .and(sanctions::waived_at.is_null()),
+            )
+            .select(sanctions::sanction_info)
+            .get_results::<SanctionInfo>(self.deref())?
+            .into_iter()
+            .map(|sanction_info| sanction_info.associated_rule)
+            .collect();
+
+        Ok(team
+            .rules
+            .into_iter()
+            .filter(|rule| !broken_rules.contains(&rule.id))
+            .collect())
+    }
+
+    fn get_collection_rate(&self, team_id: Uuid) -> Result<f32, DbError> {
+        let sanctions: Vec<(Amount, Option<NaiveDate>)> = sanctions::table
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::waived_at.is_null()),
+            )
+            .select((sanctions::price, sanctions::paid_at))
+            .get_results(self.deref())?;
+
+        Ok(collection_rate(&sanctions))
+    }
+
+    fn archive_season(&self, team_id: Uuid, season: &str) -> Result<SeasonArchive, DbError> {
+        self.deref().transaction::<_, DbError, _>(|| {
+            teams::table
+                .find(team_id)
+                .select(teams::id)
+                .get_result::<Uuid>(self.deref())?;
+
+            let sanctions: Vec<(Uuid, Amount, Option<NaiveDate>)> = sanctions::table
                 .filter(
-                    sanctions::created_at
-                        .between(min, max)
-                        .and(sanctions::team_id.eq(team_id)),
+                    sanctions::team_id
+                        .eq(team_id)
+                        .and(sanctions::season.eq(season))
+                        .and(sanctions::waived_at.is_null()),
                 )
-                .get_results(self.deref())?,
-            None => sanctions::table
-                .filter(sanctions::team_id.eq(team_id))
-                .get_results(self.deref())?,
-        };
+                .select((sanctions::user_id, sanctions::price, sanctions::paid_at))
+                .get_results(self.deref())?;
 
-        Ok(sanctions)
+            let mut totals: HashMap<Uuid, Amount> = HashMap::new();
+            for (user_id, price, _) in &sanctions {
+                let total = totals
+                    .entry(*user_id)
+                    .or_insert_with(|| Amount::from_cents(0));
+                *total = *total + *price;
+            }
+
+            let mut users: Vec<SeasonUserTotal> = totals
+                .into_iter()
+                .map(|(user_id, total)| SeasonUserTotal { user_id, total })
+                .collect();
+            users.sort_by(|a, b| b.total.cmp(&a.total).then(a.user_id.cmp(&b.user_id)));
+
+            let priced: Vec<(Amount, Option<NaiveDate>)> = sanctions
+                .iter()
+                .map(|(_, price, paid_at)| (*price, *paid_at))
+                .collect();
+
+            diesel::update(
+                sanctions::table.filter(
+                    sanctions::team_id
+                        .eq(team_id)
+                        .and(sanctions::season.eq(season))
+                        .and(sanctions::archived_at.is_null()),
+                ),
+            )
+            .set(sanctions::archived_at.eq(Local::today().naive_local()))
+            .execute(self.deref())?;
+
+            Ok(SeasonArchive {
+                season: season.to_string(),
+                total: users.iter().map(|user| user.total).sum(),
+                users,
+                collection_rate: collection_rate(&priced),
+            })
+        })
     }
 
-    fn create_sanctions(&self, sanctions: &[CreateSanction]) -> Result<Vec<Sanction>, DbError> {
-        let sanctions: Vec<Sanction> = diesel::insert_into(sanctions::table)
-            .values(sanctions)
+    fn get_daily_sanction_counts(
+        &self,
+        team_id: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<(NaiveDate, i64)>, DbError> {
+        let created_ats: Vec<DateTime<Utc>> = sanctions::table
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::created_at.ge(start_of_day(from)))
+                    .and(sanctions::created_at.lt(start_of_day(to.succ())))
+                    .and(sanctions::waived_at.is_null()),
+            )
+            .select(sanctions::created_at)
+            .get_results(self.deref())?;
+
+        let mut counts: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+        for created_at in created_ats {
+            *counts.entry(created_at.naive_utc().date()).or_insert(0) += 1;
+        }
+
+        Ok(counts.into_iter().collect())
+    }
+
+    fn get_rule_trend(
+        &self,
+        team_id: Uuid,
+        rule_id: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+        bucket: Bucket,
+    ) -> Result<Vec<(NaiveDate, i64)>, DbError> {
+        let sanctions: Vec<(DateTime<Utc>, SanctionInfo)> = sanctions::table
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::created_at.ge(start_of_day(from)))
+                    .and(sanctions::created_at.lt(start_of_day(to.succ())))
+                    .and(sanctions::waived_at.is_null()),
+            )
+            .select((sanctions::created_at, sanctions::sanction_info))
+            .get_results(self.deref())?;
+
+        let mut counts: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+        for (created_at, sanction_info) in sanctions {
+            if sanction_info.associated_rule == rule_id {
+                *counts
+                    .entry(bucket.start_of(created_at.naive_utc().date()))
+                    .or_insert(0) += 1;
+            }
+        }
+
+        Ok(counts.into_iter().collect())
+    }
+
+    fn get_biggest_fine(&self, team_id: Uuid) -> Result<Option<(User, Sanction, Amount)>, DbError> {
+        let biggest: Option<(User, Sanction)> = sanctions::table
+            .inner_join(users::table)
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::waived_at.is_null()),
+            )
+            .order_by((
+                sanctions::price.desc(),
+                sanctions::created_at,
+                sanctions::id,
+            ))
+            .select((users::all_columns, sanctions::all_columns))
+            .first(self.deref())
+            .optional()?;
+
+        Ok(biggest.map(|(user, sanction)| {
+            let price = sanction.price;
+            (user, sanction, price)
+        }))
+    }
+
+    fn get_global_unpaid_total(&self) -> Result<Amount, DbError> {
+        let prices: Vec<Amount> = sanctions::table
+            .filter(
+                sanctions::paid_at
+                    .is_null()
+                    .and(sanctions::waived_at.is_null())
+                    .and(sanctions::archived_at.is_null()),
+            )
+            .select(sanctions::price)
+            .get_results(self.deref())?;
+
+        Ok(prices.into_iter().sum())
+    }
+
+    fn get_clean_streak(&self, team_id: Uuid, user_id: Uuid) -> Result<i64, DbError> {
+        users::table
+            .filter(users::team_id.eq(team_id).and(users::id.eq(user_id)))
+            .select(users::id)
+            .get_result::<Uuid>(self.deref())?;
+
+        let last_sanction: Option<DateTime<Utc>> = sanctions::table
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::user_id.eq(user_id))
+                    .and(sanctions::waived_at.is_null()),
+            )
+            .select(sanctions::created_at)
+            .order_by(sanctions::created_at.desc())
+            .first(self.deref())
+            .optional()?;
+
+        Ok(match last_sanction {
+            Some(created_at) => {
+                let weeks = (week_start(Local::today().naive_local())
+                    - week_start(created_at.naive_utc().date()))
+                .num_weeks();
+                (weeks - 1).max(0)
+            }
+            None => 0,
+        })
+    }
+
+    fn get_balances(
+        &self,
+        team_id: Uuid,
+        user_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Amount>, DbError> {
+        let fine_multipliers: Vec<(Uuid, f32)> = users::table
+            .filter(users::team_id.eq(team_id).and(users::id.eq_any(user_ids)))
+            .select((users::id, users::fine_multiplier))
             .get_results(self.deref())?;
 
+        let mut query = sanctions::table
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::user_id.eq_any(user_ids))
+                    .and(sanctions::paid_at.is_null())
+                    .and(sanctions::waived_at.is_null())
+                    .and(sanctions::archived_at.is_null()),
+            )
+            .select((sanctions::user_id, sanctions::price))
+            .into_boxed();
+        if let Some(counted_before) = get_grace_cutoff(self, team_id)? {
+            query = query.filter(sanctions::created_at.lt(counted_before));
+        }
+
+        let prices: Vec<(Uuid, Amount)> = query.get_results(self.deref())?;
+
+        Ok(fine_multipliers
+            .into_iter()
+            .map(|(user_id, fine_multiplier)| {
+                let unpaid_total: Amount = prices
+                    .iter()
+                    .filter(|(sanction_user_id, _)| *sanction_user_id == user_id)
+                    .map(|(_, price)| price)
+                    .sum();
+
+                (user_id, unpaid_total.scale(f64::from(fine_multiplier)))
+            })
+            .collect())
+    }
+
+    fn find_future_dated_sanctions(&self, team_id: Uuid) -> Result<Vec<Sanction>, DbError> {
+        let sanctions: Vec<Sanction> =
+            sanctions::table
+                .filter(sanctions::team_id.eq(team_id).and(
+                    sanctions::created_at.ge(start_of_day(Local::today().naive_local().succ())),
+                ))
+                .order_by((sanctions::created_at, sanctions::id))
+                .get_results(self.deref())?;
+
         Ok(sanctions)
     }
 
-    fn delete_sanction(&self, team_id: Uuid, sanction_id: Uuid) -> Result<Sanction, DbError> {
-        let sanction: Sanction = diesel::delete(
-            sanctions::table.filter(
+    fn undo_last_sanction(&self, team_id: Uuid, user_id: Uuid) -> Result<Sanction, DbError> {
+        self.deref().transaction::<_, DbError, _>(|| {
+            let sanction_id: Uuid = sanctions::table
+                .filter(
+                    sanctions::team_id
+                        .eq(team_id)
+                        .and(sanctions::user_id.eq(user_id)),
+                )
+                .order_by((sanctions::created_at.desc(), sanctions::id.desc()))
+                .select(sanctions::id)
+                .for_update()
+                .first(self.deref())?;
+
+            self.delete_sanction(team_id, sanction_id, None)
+        })
+    }
+
+    fn get_fine_distribution(&self, team_id: Uuid) -> Result<FineStats, DbError> {
+        let prices: Vec<Amount> = sanctions::table
+            .filter(
                 sanctions::team_id
                     .eq(team_id)
-                    .and(sanctions::id.eq(sanction_id)),
-            ),
-        )
-        .get_result(self.deref())?;
+                    .and(sanctions::waived_at.is_null()),
+            )
+            .select(sanctions::price)
+            .order_by(sanctions::price)
+            .get_results(self.deref())?;
+
+        let count = prices.len();
+        if count == 0 {
+            return Ok(FineStats::default());
+        }
+
+        let total: Amount = prices.iter().sum();
+        // Both indexes point to the middle price when the count is odd.
+        let median = (prices[(count - 1) / 2] + prices[count / 2]).scale(0.5);
+
+        Ok(FineStats {
+            count: count as i64,
+            mean: total.scale(1.0 / count as f64),
+            median,
+            min: prices[0],
+            max: prices[count - 1],
+        })
+    }
+
+    fn dispute_sanction(
+        &self,
+        team_id: Uuid,
+        sanction_id: Uuid,
+        note: Option<&str>,
+    ) -> Result<Sanction, DbError> {
+        self.deref().transaction::<_, DbError, _>(|| {
+            let sanction: Sanction = sanctions::table
+                .filter(
+                    sanctions::team_id
+                        .eq(team_id)
+                        .and(sanctions::id.eq(sanction_id)),
+                )
+                .for_update()
+                .get_result(self.deref())?;
+
+            if sanction.waived_at.is_some() {
+                return Err(DbError::Forbidden(format!(
+                    "The sanction {} is already waived",
+                    sanction_id
+                )));
+            }
+
+            let sanction = diesel::update(sanctions::table.find(sanction_id))
+                .set((
+                    sanctions::disputed.eq(true),
+                    sanctions::dispute_note.eq(note),
+                ))
+                .get_result(self.deref())?;
+            record_events(
+                self,
+                &[AuditEvent::new(&sanction, AuditEventKind::Disputed, None)],
+            )?;
+
+            Ok(sanction)
+        })
+    }
+
+    fn resolve_dispute(
+        &self,
+        team_id: Uuid,
+        sanction_id: Uuid,
+        upheld: bool,
+    ) -> Result<Sanction, DbError> {
+        self.deref().transaction::<_, DbError, _>(|| {
+            let sanction: Sanction = sanctions::table
+                .filter(
+                    sanctions::team_id
+                        .eq(team_id)
+                        .and(sanctions::id.eq(sanction_id)),
+                )
+                .for_update()
+                .get_result(self.deref())?;
+
+            if !sanction.disputed {
+                return Err(DbError::Forbidden(format!(
+                    "The sanction {} is not disputed",
+                    sanction_id
+                )));
+            }
+
+            let waived_at = if upheld {
+                sanction.waived_at
+            } else {
+                Some(Local::today().naive_local())
+            };
+
+            let sanction = diesel::update(sanctions::table.find(sanction_id))
+                .set((
+                    sanctions::disputed.eq(false),
+                    sanctions::waived_at.eq(waived_at),
+                ))
+                .get_result(self.deref())?;
+            let kind = if upheld {
+                AuditEventKind::DisputeUpheld
+            } else {
+                AuditEventKind::DisputeOverturned
+            };
+            record_events(self, &[AuditEvent::new(&sanction, kind, None)])?;
+
+            Ok(sanction)
+        })
+    }
+
+    fn get_amount_by_category(
+        &self,
+        team_id: Uuid,
+    ) -> Result<Vec<(Option<RuleCategory>, Amount)>, DbError> {
+        let team: Team = teams::table.find(team_id).get_result(self.deref())?;
+
+        let sanctions: Vec<Sanction> = sanctions::table
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::paid_at.is_null())
+                    .and(sanctions::waived_at.is_null())
+                    .and(sanctions::archived_at.is_null()),
+            )
+            .get_results(self.deref())?;
+
+        let categories = [
+            Some(RuleCategory::GameDay),
+            Some(RuleCategory::TrainingDay),
+            None,
+        ];
+
+        Ok(categories
+            .iter()
+            .filter_map(|category| {
+                let category_sanctions: Vec<&Sanction> = sanctions
+                    .iter()
+                    .filter(|sanction| {
+                        team.rules
+                            .iter()
+                            .find(|rule| rule.id == sanction.sanction_info.associated_rule)
+                            .map(|rule| rule.category.clone())
+                            == *category
+                    })
+                    .collect();
+
+                if category_sanctions.is_empty() {
+                    None
+                } else {
+                    let total = category_sanctions
+                        .iter()
+                        .map(|sanction| sanction.price)
+                        .sum();
+                    Some((category.clone(), total))
+                }
+            })
+            .collect())
+    }
+
+    fn get_recent_sanctions(&self, team_id: Uuid, hours: i64) -> Result<Vec<Sanction>, DbError> {
+        let since = Utc::now() - Duration::hours(hours);
+
+        let sanctions: Vec<Sanction> = sanctions::table
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::recorded_at.ge(since)),
+            )
+            .order_by((sanctions::recorded_at.desc(), sanctions::id.desc()))
+            .get_results(self.deref())?;
+
+        Ok(sanctions)
+    }
+
+    fn get_unused_rules(&self, team_id: Uuid, since: NaiveDate) -> Result<Vec<Rule>, DbError> {
+        let team: Team = teams::table.find(team_id).get_result(self.deref())?;
+
+        let used_rules: Vec<Uuid> = sanctions::table
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::created_at.ge(start_of_day(since)))
+                    .and(sanctions::waived_at.is_null()),
+            )
+            .select(sanctions::sanction_info)
+            .get_results::<SanctionInfo>(self.deref())?
+            .into_iter()
+            .map(|sanction_info| sanction_info.associated_rule)
+            .collect();
+
+        Ok(team
+            .rules
+            .into_iter()
+            .filter(|rule| !used_rules.contains(&rule.id))
+            .collect())
+    }
+
+    fn simulate_rule_price_change(
+        &self,
+        team_id: Uuid,
+        rule_id: Uuid,
+        new_price: Amount,
+    ) -> Result<(Amount, Amount), DbError> {
+        let team: Team = teams::table.find(team_id).get_result(self.deref())?;
+        let mut rule = team.get_rule(rule_id).ok_or(DbError::NotFound)?;
+
+        rule.kind = rule.kind.with_price(new_price).ok_or_else(|| {
+            DbError::NotValid(format!("The rule {} has no price to change", rule.name))
+        })?;
+        rule.kind.validate()?;
+
+        let sanctions: Vec<Sanction> = sanctions::table
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::paid_at.is_null())
+                    .and(sanctions::waived_at.is_null())
+                    .and(sanctions::archived_at.is_null()),
+            )
+            .get_results(self.deref())?;
+
+        let current_total: Amount = sanctions.iter().map(|sanction| sanction.price).sum();
+        let simulated_total: Amount = sanctions
+            .iter()
+            .map(|sanction| {
+                if sanction.sanction_info.associated_rule == rule_id {
+                    sanction.sanction_info.get_price(rule.clone())
+                } else {
+                    Ok(sanction.price)
+                }
+            })
+            .sum::<Result<Amount, _>>()?;
+
+        Ok((current_total, simulated_total))
+    }
+
+    fn get_sanction_details(&self, team_id: Uuid) -> Result<Vec<SanctionDetail>, DbError> {
+        let sanctions: Vec<Sanction> = sanctions::table
+            .filter(sanctions::team_id.eq(team_id))
+            .order_by((sanctions::created_at, sanctions::id))
+            .get_results(self.deref())?;
+
+        let admin_ids: Vec<Uuid> = sanctions
+            .iter()
+            .flat_map(|sanction| sanction.created_by.into_iter().chain(sanction.paid_by))
+            .collect();
+        let usernames: HashMap<Uuid, String> = admins::table
+            .filter(admins::id.eq_any(&admin_ids))
+            .select((admins::id, admins::username))
+            .get_results::<(Uuid, String)>(self.deref())?
+            .into_iter()
+            .collect();
+        let username = |admin_id: Option<Uuid>| admin_id.and_then(|id| usernames.get(&id).cloned());
+
+        Ok(sanctions
+            .into_iter()
+            .map(|sanction| SanctionDetail {
+                creator_name: username(sanction.created_by),
+                settler_name: username(sanction.paid_by),
+                sanction: sanction.into(),
+            })
+            .collect())
+    }
+
+    fn get_average_balance(&self, team_id: Uuid) -> Result<Amount, DbError> {
+        teams::table
+            .find(team_id)
+            .select(teams::id)
+            .get_result::<Uuid>(self.deref())?;
+
+        let users: Vec<(Uuid, bool)> = users::table
+            .filter(users::team_id.eq(team_id))
+            .select((users::id, users::anonymized))
+            .get_results(self.deref())?;
+        let members = users.iter().filter(|(_, anonymized)| !anonymized).count();
+        if members == 0 {
+            return Ok(Amount::default());
+        }
+
+        let user_ids: Vec<Uuid> = users.iter().map(|(user_id, _)| *user_id).collect();
+        let total: Amount = self.get_balances(team_id, &user_ids)?.values().sum();
+
+        Ok(total.scale(1.0 / members as f64))
+    }
+
+    fn waive_sanctions_where(
+        &self,
+        team_id: Uuid,
+        date_interval: Option<(NaiveDate, NaiveDate)>,
+        filter: &SanctionFilter,
+        reason: &str,
+    ) -> Result<u64, DbError> {
+        self.deref().transaction::<_, DbError, _>(|| {
+            let filter = SanctionFilter {
+                include_waived: false,
+                ..filter.clone()
+            };
+            let sanction_ids: Vec<Uuid> = self
+                .get_sanctions(team_id, date_interval, &filter)?
+                .into_iter()
+                .map(|sanction| sanction.id)
+                .collect();
+
+            let sanctions: Vec<Sanction> =
+                diesel::update(sanctions::table.filter(sanctions::id.eq_any(sanction_ids)))
+                    .set((
+                        sanctions::waived_at.eq(Local::today().naive_local()),
+                        sanctions::waive_reason.eq(reason),
+                    ))
+                    .get_results(self.deref())?;
+
+            let events: Vec<AuditEvent> = sanctions
+                .iter()
+                .map(|sanction| AuditEvent::new(sanction, AuditEventKind::Waived, None))
+                .collect();
+            record_events(self, &events)?;
+
+            Ok(sanctions.len() as u64)
+        })
+    }
+
+    fn get_rules_with_usage(&self, team_id: Uuid) -> Result<Vec<(Rule, i64)>, DbError> {
+        let team: Team = teams::table.find(team_id).get_result(self.deref())?;
+
+        let used_rules: Vec<Uuid> = sanctions::table
+            .filter(sanctions::team_id.eq(team_id))
+            .select(sanctions::sanction_info)
+            .get_results::<SanctionInfo>(self.deref())?
+            .into_iter()
+            .map(|sanction_info| sanction_info.associated_rule)
+            .collect();
+
+        Ok(team
+            .rules
+            .into_iter()
+            .map(|rule| {
+                let count = used_rules
+                    .iter()
+                    .filter(|rule_id| **rule_id == rule.id)
+                    .count() as i64;
+
+                (rule, count)
+            })
+            .collect())
+    }
+
+    fn get_sanctions_modified_since(
+        &self,
+        team_id: Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<Sanction>, DbError> {
+        let sanctions: Vec<Sanction> = sanctions::table
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::updated_at.gt(since)),
+            )
+            .order_by((sanctions::updated_at, sanctions::id))
+            .get_results(self.deref())?;
+
+        Ok(sanctions)
+    }
+
+    fn get_sanction_audit(
+        &self,
+        team_id: Uuid,
+        sanction_id: Uuid,
+    ) -> Result<Vec<AuditEvent>, DbError> {
+        sanctions::table
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::id.eq(sanction_id)),
+            )
+            .select(sanctions::id)
+            .get_result::<Uuid>(self.deref())?;
+
+        let events: Vec<AuditEvent> = sanction_events::table
+            .filter(sanction_events::sanction_id.eq(sanction_id))
+            .order_by(sanction_events::occurred_at)
+            .get_results(self.deref())?;
+
+        Ok(events)
+    }
+
+    fn get_user_vs_average(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(Amount, Amount, f32), DbError> {
+        let balance = self.get_balance(team_id, user_id)?;
+        let average = self.get_average_balance(team_id)?;
+
+        let ratio = if average.cents() == 0 {
+            0.0
+        } else {
+            (balance.cents() as f64 / average.cents() as f64) as f32
+        };
+
+        Ok((balance, average, ratio))
+    }
+
+    fn get_fine_gini(&self, team_id: Uuid) -> Result<f32, DbError> {
+        teams::table
+            .find(team_id)
+            .select(teams::id)
+            .get_result::<Uuid>(self.deref())?;
+
+        let user_ids: Vec<Uuid> = users::table
+            .filter(users::team_id.eq(team_id))
+            .select(users::id)
+            .get_results(self.deref())?;
+        let prices: Vec<(Uuid, Amount)> = sanctions::table
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::waived_at.is_null()),
+            )
+            .select((sanctions::user_id, sanctions::price))
+            .get_results(self.deref())?;
+
+        let totals: Vec<Amount> = user_ids
+            .iter()
+            .map(|user_id| {
+                prices
+                    .iter()
+                    .filter(|(sanction_user_id, _)| sanction_user_id == user_id)
+                    .map(|(_, price)| price)
+                    .sum()
+            })
+            .collect();
+
+        Ok(gini_coefficient(&totals))
+    }
+
+    fn rule_deletion_impact(&self, team_id: Uuid, rule_id: Uuid) -> Result<RuleImpact, DbError> {
+        let team: Team = teams::table.find(team_id).get_result(self.deref())?;
+        team.get_rule(rule_id).ok_or(DbError::NotFound)?;
+
+        let sanctions: Vec<(Uuid, SanctionInfo, Amount)> = sanctions::table
+            .filter(sanctions::team_id.eq(team_id))
+            .select((
+                sanctions::user_id,
+                sanctions::sanction_info,
+                sanctions::price,
+            ))
+            .get_results(self.deref())?;
+
+        let mut user_ids: Vec<Uuid> = vec![];
+        let mut impact = RuleImpact::default();
+        for (user_id, sanction_info, price) in sanctions {
+            if sanction_info.associated_rule != rule_id {
+                continue;
+            }
+
+            impact.sanctions_count += 1;
+            impact.total = impact.total + price;
+            if !user_ids.contains(&user_id) {
+                user_ids.push(user_id);
+            }
+        }
+        impact.users_count = user_ids.len() as i64;
+
+        Ok(impact)
+    }
+
+    fn get_top_rule_per_user(
+        &self,
+        team_id: Uuid,
+    ) -> Result<Vec<(User, Option<Rule>, i64)>, DbError> {
+        let team: Team = teams::table.find(team_id).get_result(self.deref())?;
+
+        let users: Vec<User> = users::table
+            .filter(users::team_id.eq(team_id))
+            .order_by((users::lastname, users::firstname, users::id))
+            .get_results(self.deref())?;
+
+        let sanctions: Vec<(Uuid, SanctionInfo)> = sanctions::table
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::waived_at.is_null()),
+            )
+            .select((sanctions::user_id, sanctions::sanction_info))
+            .get_results(self.deref())?;
+
+        let mut rules = team.rules;
+        rules.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(users
+            .into_iter()
+            .map(|user| {
+                let top_rule = rules
+                    .iter()
+                    .map(|rule| {
+                        let count = sanctions
+                            .iter()
+                            .filter(|(user_id, sanction_info)| {
+                                *user_id == user.id && sanction_info.associated_rule == rule.id
+                            })
+                            .count() as i64;
+
+                        (rule, count)
+                    })
+                    .filter(|(_, count)| *count > 0)
+                    .fold(None, |top: Option<(&Rule, i64)>, (rule, count)| match top {
+                        Some((_, top_count)) if top_count >= count => top,
+                        _ => Some((rule, count)),
+                    });
+
+                match top_rule {
+                    Some((rule, count)) => (user, Some(rule.clone()), count),
+                    None => (user, None, 0),
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::result::Error;
+
+    use super::*;
+    use crate::admins::interface::AdminsDb;
+    use crate::sanctions::models::{ExtraInfo, SanctionInfo};
+    use crate::teams::{interface::TeamsDb, models::RuleKind};
+    use crate::test_utils::postgres::{init_connection, unique_admin};
+    use crate::users::{interface::UsersDb, models::User};
+
+    fn team_with_rule() -> Team {
+        Team {
+            rules: vec![Rule::default()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_get_sanctions() {
+        let conn = init_connection();
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let sanction = conn
+                .create_sanctions(&[CreateSanction {
+                    user_id,
+                    team_id,
+                    ..Default::default()
+                }])
+                .unwrap();
+
+            let team_id_2 = conn
+                .create_team(
+                    &Team {
+                        id: Uuid::new_v4(),
+                        name: String::from("CHBC"),
+                        ..team_with_rule()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user_id_2 = conn
+                .create_user(&User {
+                    id: Uuid::new_v4(),
+                    team_id: team_id_2,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let sanction_2 = conn
+                .create_sanctions(&[CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id: user_id_2,
+                    team_id: team_id_2,
+                    ..Default::default()
+                }])
+                .unwrap();
+
+            let sanctions: Vec<Sanction> = conn
+                .get_sanctions(team_id, None, &SanctionFilter::default())
+                .unwrap();
+            let sanctions_2: Vec<Sanction> = conn
+                .get_sanctions(team_id_2, None, &SanctionFilter::default())
+                .unwrap();
+
+            assert_eq!(sanction, sanctions);
+            assert_eq!(sanction_2, sanctions_2);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_sanctions_with_date_interval() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            let sanction = conn
+                .create_sanctions(&[CreateSanction {
+                    user_id,
+                    team_id,
+                    created_at: Some(start_of_day(NaiveDate::from_ymd(2019, 10, 13))),
+                    ..Default::default()
+                }])
+                .unwrap();
+
+            conn.create_sanctions(&[
+                CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    team_id,
+                    created_at: Some(start_of_day(NaiveDate::from_ymd(2019, 10, 5))),
+                    ..Default::default()
+                },
+                CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    team_id,
+                    created_at: Some(start_of_day(NaiveDate::from_ymd(2019, 10, 25))),
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+            let returned_sanctions: Vec<Sanction> = conn
+                .get_sanctions(
+                    team_id,
+                    Some((
+                        NaiveDate::from_ymd(2019, 10, 6),
+                        NaiveDate::from_ymd(2019, 10, 20),
+                    )),
+                    &SanctionFilter::default(),
+                )
+                .unwrap();
+
+            assert_eq!(sanction, returned_sanctions);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_create_sanctions() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let id = Uuid::new_v4();
+
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            let created_at = NaiveDate::from_ymd(2019, 10, 15);
+
+            let sanctions = conn
+                .create_sanctions(&[
+                    CreateSanction {
+                        id,
+                        user_id,
+                        team_id,
+                        ..Default::default()
+                    },
+                    CreateSanction {
+                        user_id,
+                        team_id,
+                        created_at: Some(start_of_day(created_at)),
+                        ..Default::default()
+                    },
+                ])
+                .unwrap();
+
+            assert_eq!(sanctions[0].id, id);
+            assert_eq!(sanctions[0].user_id, user_id);
+            assert_eq!(sanctions[0].team_id, team_id);
+            assert_eq!(sanctions[0].created_on(), Local::today().naive_local());
+            assert_eq!(sanctions[1].created_at, start_of_day(created_at));
+            assert_eq!(sanctions[1].created_on(), created_at);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_create_sanction_fails() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let error = conn
+                .create_sanctions(&[CreateSanction::default()])
+                .unwrap_err();
+
+            assert_eq!(
+                error,
+                DbError::ForeignKeyViolation(String::from(
+                    "The key team_id doesn\'t refer to anything"
+                ))
+            );
+
+            Ok(())
+        });
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+
+            let error = conn
+                .create_sanctions(&[CreateSanction {
+                    team_id,
+                    ..Default::default()
+                }])
+                .unwrap_err();
+
+            assert_eq!(
+                error,
+                DbError::ForeignKeyViolation(String::from(
+                    "The key user_id doesn\'t refer to anything"
+                ))
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_delete_sanction() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            let sanctions = conn
+                .create_sanctions(&[CreateSanction {
+                    team_id,
+                    user_id,
+                    ..Default::default()
+                }])
+                .unwrap();
+
+            let sanction_deleted = conn
+                .delete_sanction(team_id, sanctions[0].id, Some("coach"))
+                .unwrap();
+            assert_eq!(sanctions[0].id, sanction_deleted.id);
+
+            let audit: DeletedSanctionAudit = deleted_sanctions_audit::table
+                .filter(deleted_sanctions_audit::sanction_id.eq(sanctions[0].id))
+                .get_result(conn.deref())
+                .unwrap();
+            assert_eq!(audit.team_id, team_id);
+            assert_eq!(audit.actor, Some(String::from("coach")));
+            assert_eq!(audit.sanction, serde_json::to_value(&sanctions[0]).unwrap());
+            assert!(Utc::now() - audit.deleted_at < Duration::minutes(1));
+
+            let sanctions = conn
+                .get_sanctions(team_id, None, &SanctionFilter::default())
+                .unwrap();
+            assert_eq!(sanctions.len(), 0);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_delete_sanction_fails() {
+        let conn = init_connection();
+
+        let error = conn
+            .delete_sanction(Uuid::new_v4(), Uuid::new_v4(), None)
+            .unwrap_err();
+
+        assert_eq!(error, DbError::NotFound);
+    }
+
+    #[test]
+    fn test_get_unpaid_older_than() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let today = Local::today().naive_local();
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            let old_sanction = conn
+                .create_sanctions(&[
+                    CreateSanction {
+                        user_id,
+                        team_id,
+                        created_at: Some(start_of_day(today - Duration::days(30))),
+                        ..Default::default()
+                    },
+                    CreateSanction {
+                        id: Uuid::new_v4(),
+                        user_id,
+                        team_id,
+                        created_at: Some(start_of_day(today)),
+                        ..Default::default()
+                    },
+                    CreateSanction {
+                        id: Uuid::new_v4(),
+                        user_id,
+                        team_id,
+                        created_at: Some(start_of_day(today - Duration::days(30))),
+                        paid_at: Some(today - Duration::days(1)),
+                        ..Default::default()
+                    },
+                ])
+                .unwrap()
+                .remove(0);
+
+            let result = conn.get_unpaid_older_than(team_id, 7).unwrap();
+
+            assert_eq!(result.len(), 1);
+            assert_eq!(result[0].0.id, user_id);
+            assert_eq!(result[0].1, vec![old_sanction]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_sanctions_by_season() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            let sanction = |season: &str| CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                season: Some(String::from(season)),
+                ..Default::default()
+            };
+            let sanctions = conn
+                .create_sanctions(&[
+                    sanction("2018-2019"),
+                    sanction("2019-2020"),
+                    sanction("2019-2020"),
+                ])
+                .unwrap();
+
+            let season_sanctions = conn
+                .get_sanctions(
+                    team_id,
+                    None,
+                    &SanctionFilter {
+                        season: Some(String::from("2018-2019")),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+
+            assert_eq!(season_sanctions, vec![sanctions[0].clone()]);
+            assert_eq!(
+                conn.get_sanctions(team_id, None, &SanctionFilter::default())
+                    .unwrap()
+                    .len(),
+                3
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_sanctions_with_filter() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let today = Local::today().naive_local();
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            let created_sanctions = conn
+                .create_sanctions(&[
+                    CreateSanction {
+                        user_id,
+                        team_id,
+                        ..Default::default()
+                    },
+                    CreateSanction {
+                        id: Uuid::new_v4(),
+                        user_id,
+                        team_id,
+                        paid_at: Some(today),
+                        ..Default::default()
+                    },
+                    CreateSanction {
+                        id: Uuid::new_v4(),
+                        user_id,
+                        team_id,
+                        waived_at: Some(today),
+                        ..Default::default()
+                    },
+                ])
+                .unwrap();
+            let (unpaid, paid, waived) = (
+                &created_sanctions[0],
+                &created_sanctions[1],
+                &created_sanctions[2],
+            );
+
+            let get_ids = |include_paid, include_waived| -> Vec<Uuid> {
+                let mut ids: Vec<Uuid> = conn
+                    .get_sanctions(
+                        team_id,
+                        None,
+                        &SanctionFilter {
+                            include_paid,
+                            include_waived,
+                            ..Default::default()
+                        },
+                    )
+                    .unwrap()
+                    .into_iter()
+                    .map(|sanction| sanction.id)
+                    .collect();
+                ids.sort();
+                ids
+            };
+            let sorted = |mut ids: Vec<Uuid>| {
+                ids.sort();
+                ids
+            };
+
+            assert_eq!(get_ids(true, false), sorted(vec![unpaid.id, paid.id]));
+            assert_eq!(get_ids(false, false), vec![unpaid.id]);
+            assert_eq!(get_ids(false, true), sorted(vec![unpaid.id, waived.id]));
+            assert_eq!(
+                get_ids(true, true),
+                sorted(vec![unpaid.id, paid.id, waived.id])
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_rule_revenue() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let rules: Vec<Rule> = (0..3)
+                .map(|_| Rule {
+                    id: Uuid::new_v4(),
+                    ..Default::default()
+                })
+                .collect();
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: rules.clone(),
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            let sanction = |rule: &Rule, price: Amount| CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                sanction_info: SanctionInfo {
+                    associated_rule: rule.id,
+                    extra_info: ExtraInfo::None,
+                },
+                price,
+                ..Default::default()
+            };
+
+            conn.create_sanctions(&[
+                sanction(&rules[1], Amount::from_cents(250)),
+                sanction(&rules[1], Amount::from_cents(250)),
+                sanction(&rules[1], Amount::from_cents(250)),
+                sanction(&rules[2], Amount::from_cents(1000)),
+                CreateSanction {
+                    waived_at: Some(Local::today().naive_local()),
+                    ..sanction(&rules[1], Amount::from_cents(250))
+                },
+            ])
+            .unwrap();
+
+            let revenue = conn.get_rule_revenue(team_id).unwrap();
+
+            assert_eq!(
+                revenue,
+                vec![
+                    (rules[2].clone(), 1, Amount::from_cents(1000)),
+                    (rules[1].clone(), 3, Amount::from_cents(750)),
+                    (rules[0].clone(), 0, Amount::from_cents(0)),
+                ]
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_balance() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let coach_id = conn
+                .create_user(&User {
+                    id: Uuid::new_v4(),
+                    team_id,
+                    fine_multiplier: 2.0,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            conn.create_sanctions(&[
+                CreateSanction {
+                    user_id,
+                    team_id,
+                    price: Amount::from_cents(250),
+                    ..Default::default()
+                },
+                CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id: coach_id,
+                    team_id,
+                    price: Amount::from_cents(250),
+                    ..Default::default()
+                },
+                CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    team_id,
+                    price: Amount::from_cents(250),
+                    paid_at: Some(Local::today().naive_local()),
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+            assert_eq!(
+                conn.get_balance(team_id, user_id).unwrap(),
+                Amount::from_cents(250)
+            );
+            assert_eq!(
+                conn.get_balance(team_id, coach_id).unwrap(),
+                Amount::from_cents(500)
+            );
+            assert_eq!(
+                conn.get_balance(team_id, Uuid::new_v4()).unwrap_err(),
+                DbError::NotFound
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_balance_sums_small_amounts_exactly() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            let sanctions: Vec<CreateSanction> = (0..1000)
+                .map(|_| CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    team_id,
+                    price: Amount::from_decimal(0.1),
+                    ..Default::default()
+                })
+                .collect();
+            conn.create_sanctions(&sanctions).unwrap();
+
+            assert_eq!(
+                conn.get_balance(team_id, user_id).unwrap(),
+                Amount::from_cents(10_000)
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_mark_user_sanctions_paid() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let paid_at = NaiveDate::from_ymd(2019, 10, 1);
+            let today = Local::today().naive_local();
+
+            conn.create_sanctions(&[
+                CreateSanction {
+                    user_id,
+                    team_id,
+                    ..Default::default()
+                },
+                CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    team_id,
+                    ..Default::default()
+                },
+                CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    team_id,
+                    paid_at: Some(paid_at),
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+            let count = conn
+                .mark_user_sanctions_paid(team_id, user_id, today, None)
+                .unwrap();
+
+            assert_eq!(count, 2);
+
+            let mut paid_dates: Vec<Option<NaiveDate>> = conn
+                .get_sanctions(team_id, None, &SanctionFilter::default())
+                .unwrap()
+                .into_iter()
+                .map(|sanction| sanction.paid_at)
+                .collect();
+            paid_dates.sort();
+
+            assert_eq!(paid_dates, vec![Some(paid_at), Some(today), Some(today)]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_user_timeline() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let rule = Rule {
+                id: Uuid::new_v4(),
+                name: String::from("Late"),
+                ..Default::default()
+            };
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: vec![rule.clone()],
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let sanction_id = conn
+                .create_sanctions(&[CreateSanction {
+                    user_id,
+                    team_id,
+                    sanction_info: SanctionInfo {
+                        associated_rule: rule.id,
+                        extra_info: ExtraInfo::None,
+                    },
+                    price: Amount::from_cents(250),
+                    created_at: Some(start_of_day(NaiveDate::from_ymd(2019, 10, 5))),
+                    paid_at: Some(NaiveDate::from_ymd(2019, 10, 12)),
+                    ..Default::default()
+                }])
+                .unwrap()[0]
+                .id;
+
+            let event = |kind, date| TimelineEvent {
+                kind,
+                date,
+                sanction_id,
+                rule_name: Some(String::from("Late")),
+                amount: Amount::from_cents(250),
+            };
+
+            assert_eq!(
+                conn.get_user_timeline(team_id, user_id).unwrap(),
+                vec![
+                    event(TimelineEventKind::Fined, NaiveDate::from_ymd(2019, 10, 5)),
+                    event(TimelineEventKind::Paid, NaiveDate::from_ymd(2019, 10, 12)),
+                ]
+            );
+            assert_eq!(
+                conn.get_user_timeline(team_id, Uuid::new_v4()).unwrap_err(),
+                DbError::NotFound
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_create_sanctions_max_per_day() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let rule = Rule {
+                id: Uuid::new_v4(),
+                max_per_day: Some(1),
+                ..Default::default()
+            };
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: vec![rule.clone()],
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            let sanction = |date| CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                sanction_info: SanctionInfo {
+                    associated_rule: rule.id,
+                    extra_info: ExtraInfo::None,
+                },
+                created_at: Some(start_of_day(date)),
+                ..Default::default()
+            };
+            let day = NaiveDate::from_ymd(2019, 10, 5);
+
+            assert!(conn.create_sanctions(&[sanction(day)]).is_ok());
+            match conn.create_sanctions(&[sanction(day)]).unwrap_err() {
+                DbError::Forbidden(_) => {}
+                err => panic!("Unexpected error {:?}", err),
+            }
+            assert!(conn.create_sanctions(&[sanction(day.succ())]).is_ok());
+            match conn
+                .create_sanctions(&[sanction(day.succ().succ()), sanction(day.succ().succ())])
+                .unwrap_err()
+            {
+                DbError::Forbidden(_) => {}
+                err => panic!("Unexpected error {:?}", err),
+            }
+
+            let waived_day = day.succ().succ().succ();
+            assert!(conn
+                .create_sanctions(&[CreateSanction {
+                    waived_at: Some(waived_day),
+                    ..sanction(waived_day)
+                }])
+                .is_ok());
+            assert!(conn.create_sanctions(&[sanction(waived_day)]).is_ok());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_sanctions_by_category() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let game_rule = Rule {
+                id: Uuid::new_v4(),
+                category: RuleCategory::GameDay,
+                ..Default::default()
+            };
+            let training_rule = Rule {
+                id: Uuid::new_v4(),
+                category: RuleCategory::TrainingDay,
+                ..Default::default()
+            };
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: vec![game_rule.clone(), training_rule.clone()],
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            let sanction = |rule: &Rule| CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                sanction_info: SanctionInfo {
+                    associated_rule: rule.id,
+                    extra_info: ExtraInfo::None,
+                },
+                ..Default::default()
+            };
+            let sanctions = conn
+                .create_sanctions(&[
+                    sanction(&game_rule),
+                    sanction(&training_rule),
+                    sanction(&training_rule),
+                ])
+                .unwrap();
+
+            assert_eq!(
+                conn.get_sanctions_by_category(team_id, RuleCategory::GameDay)
+                    .unwrap(),
+                vec![sanctions[0].clone()]
+            );
+            assert_eq!(
+                conn.get_sanctions_by_category(team_id, RuleCategory::TrainingDay)
+                    .unwrap()
+                    .len(),
+                2
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_preview_balance_with() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let rule = Rule {
+                id: Uuid::new_v4(),
+                kind: RuleKind::Basic {
+                    price: Amount::from_cents(250),
+                },
+                ..Default::default()
+            };
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: vec![rule.clone()],
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            conn.create_sanctions(&[CreateSanction {
+                user_id,
+                team_id,
+                sanction_info: SanctionInfo {
+                    associated_rule: rule.id,
+                    extra_info: ExtraInfo::None,
+                },
+                price: Amount::from_cents(250),
+                ..Default::default()
+            }])
+            .unwrap();
+
+            assert_eq!(
+                conn.preview_balance_with(team_id, user_id, rule.id, &ExtraInfo::None)
+                    .unwrap(),
+                Amount::from_cents(500)
+            );
+            assert_eq!(
+                conn.get_balance(team_id, user_id).unwrap(),
+                Amount::from_cents(250)
+            );
+            match conn
+                .preview_balance_with(
+                    team_id,
+                    user_id,
+                    rule.id,
+                    &ExtraInfo::Multiplication { factor: 2 },
+                )
+                .unwrap_err()
+            {
+                DbError::NotValid(_) => {}
+                err => panic!("Unexpected error {:?}", err),
+            }
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_unbroken_rules() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let rules: Vec<Rule> = (0..2)
+                .map(|_| Rule {
+                    id: Uuid::new_v4(),
+                    ..Default::default()
+                })
+                .collect();
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: rules.clone(),
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            conn.create_sanctions(&[CreateSanction {
+                user_id,
+                team_id,
+                sanction_info: SanctionInfo {
+                    associated_rule: rules[0].id,
+                    extra_info: ExtraInfo::None,
+                },
+                ..Default::default()
+            }])
+            .unwrap();
+
+            assert_eq!(
+                conn.get_unbroken_rules(team_id, user_id).unwrap(),
+                vec![rules[1].clone()]
+            );
+            assert_eq!(
+                conn.get_unbroken_rules(team_id, Uuid::new_v4())
+                    .unwrap_err(),
+                DbError::NotFound
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_collection_rate() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            assert_eq!(conn.get_collection_rate(team_id).unwrap(), 0.0);
+
+            conn.create_sanctions(&[
+                CreateSanction {
+                    user_id,
+                    team_id,
+                    price: Amount::from_cents(250),
+                    paid_at: Some(Local::today().naive_local()),
+                    ..Default::default()
+                },
+                CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    team_id,
+                    price: Amount::from_cents(250),
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+            assert_eq!(conn.get_collection_rate(team_id).unwrap(), 0.5);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_archive_season() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            conn.create_sanctions(&[
+                CreateSanction {
+                    user_id,
+                    team_id,
+                    price: Amount::from_cents(300),
+                    paid_at: Some(Local::today().naive_local()),
+                    season: Some("2025-2026".to_string()),
+                    ..Default::default()
+                },
+                CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    team_id,
+                    price: Amount::from_cents(100),
+                    season: Some("2025-2026".to_string()),
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+            assert_eq!(
+                conn.get_balance(team_id, user_id).unwrap(),
+                Amount::from_cents(100)
+            );
+
+            let archive = conn.archive_season(team_id, "2025-2026").unwrap();
+
+            assert_eq!(archive.season, "2025-2026");
+            assert_eq!(archive.total, Amount::from_cents(400));
+            assert_eq!(
+                archive.users,
+                vec![SeasonUserTotal {
+                    user_id,
+                    total: Amount::from_cents(400),
+                }]
+            );
+            assert_eq!(archive.collection_rate, 0.75);
+            assert_eq!(
+                conn.get_balance(team_id, user_id).unwrap(),
+                Amount::from_cents(0)
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_archive_season_unexisting_team() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            assert_eq!(
+                conn.archive_season(Uuid::new_v4(), "2025-2026"),
+                Err(DbError::NotFound)
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_daily_sanction_counts() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let today = Local::today().naive_local();
+            let yesterday = today - Duration::days(1);
+
+            conn.create_sanctions(&[
+                CreateSanction {
+                    user_id,
+                    team_id,
+                    created_at: Some(start_of_day(yesterday)),
+                    ..Default::default()
+                },
+                CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    team_id,
+                    created_at: Some(start_of_day(today)),
+                    ..Default::default()
+                },
+                CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    team_id,
+                    created_at: Some(start_of_day(today)),
+                    ..Default::default()
+                },
+                CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    team_id,
+                    created_at: Some(start_of_day(today - Duration::days(10))),
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+            assert_eq!(
+                conn.get_daily_sanction_counts(team_id, today - Duration::days(7), today)
+                    .unwrap(),
+                vec![(yesterday, 1), (today, 2)]
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_biggest_fine() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap();
+
+            assert_eq!(conn.get_biggest_fine(team_id).unwrap(), None);
+
+            let sanctions = conn
+                .create_sanctions(&[
+                    CreateSanction {
+                        user_id: user.id,
+                        team_id,
+                        price: Amount::from_cents(250),
+                        ..Default::default()
+                    },
+                    CreateSanction {
+                        id: Uuid::new_v4(),
+                        user_id: user.id,
+                        team_id,
+                        price: Amount::from_cents(1000),
+                        ..Default::default()
+                    },
+                ])
+                .unwrap();
+
+            assert_eq!(
+                conn.get_biggest_fine(team_id).unwrap(),
+                Some((user, sanctions[1].clone(), Amount::from_cents(1000)))
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_global_unpaid_total() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let mut sanctions = Vec::new();
+            for (name, price) in &[("Les Arbres", 250), ("Les Pierres", 400)] {
+                let team_id = conn
+                    .create_team(
+                        &Team {
+                            id: Uuid::new_v4(),
+                            name: String::from(*name),
+                            ..team_with_rule()
+                        },
+                        &unique_admin(),
+                    )
+                    .unwrap()
+                    .id;
+                let user_id = conn
+                    .create_user(&User {
+                        id: Uuid::new_v4(),
+                        team_id,
+                        ..Default::default()
+                    })
+                    .unwrap()
+                    .id;
+
+                sanctions.push(CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    team_id,
+                    price: Amount::from_cents(*price),
+                    ..Default::default()
+                });
+                sanctions.push(CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    team_id,
+                    price: Amount::from_cents(*price),
+                    paid_at: Some(Local::today().naive_local()),
+                    ..Default::default()
+                });
+            }
+            conn.create_sanctions(&sanctions).unwrap();
+
+            assert_eq!(
+                conn.get_global_unpaid_total().unwrap(),
+                Amount::from_cents(650)
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_clean_streak() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let today = Local::today().naive_local();
+
+            assert_eq!(conn.get_clean_streak(team_id, user_id).unwrap(), 0);
+
+            conn.create_sanctions(&[CreateSanction {
+                user_id,
+                team_id,
+                created_at: Some(start_of_day(today - Duration::weeks(4))),
+                ..Default::default()
+            }])
+            .unwrap();
+
+            assert_eq!(conn.get_clean_streak(team_id, user_id).unwrap(), 3);
+
+            conn.create_sanctions(&[CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                created_at: Some(start_of_day(today)),
+                ..Default::default()
+            }])
+            .unwrap();
+
+            assert_eq!(conn.get_clean_streak(team_id, user_id).unwrap(), 0);
+            assert_eq!(
+                conn.get_clean_streak(team_id, Uuid::new_v4()),
+                Err(DbError::NotFound)
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_balances() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let first_user_id = conn
+                .create_user(&User {
+                    id: Uuid::new_v4(),
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let second_user_id = conn
+                .create_user(&User {
+                    id: Uuid::new_v4(),
+                    team_id,
+                    fine_multiplier: 2.0,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            conn.create_sanctions(&[
+                CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id: first_user_id,
+                    team_id,
+                    price: Amount::from_cents(250),
+                    ..Default::default()
+                },
+                CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id: second_user_id,
+                    team_id,
+                    price: Amount::from_cents(100),
+                    ..Default::default()
+                },
+                CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id: second_user_id,
+                    team_id,
+                    price: Amount::from_cents(300),
+                    paid_at: Some(Local::today().naive_local()),
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+            let balances = conn
+                .get_balances(team_id, &[first_user_id, second_user_id, Uuid::new_v4()])
+                .unwrap();
+
+            assert_eq!(balances.len(), 2);
+            assert_eq!(balances[&first_user_id], Amount::from_cents(250));
+            assert_eq!(balances[&second_user_id], Amount::from_cents(200));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_find_future_dated_sanctions() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let today = Local::today().naive_local();
+
+            let sanctions = conn
+                .create_sanctions(&[
+                    CreateSanction {
+                        user_id,
+                        team_id,
+                        created_at: Some(start_of_day(today)),
+                        ..Default::default()
+                    },
+                    CreateSanction {
+                        id: Uuid::new_v4(),
+                        user_id,
+                        team_id,
+                        created_at: Some(start_of_day(today + Duration::days(30))),
+                        ..Default::default()
+                    },
+                ])
+                .unwrap();
+
+            assert_eq!(
+                conn.find_future_dated_sanctions(team_id).unwrap(),
+                vec![sanctions[1].clone()]
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_undo_last_sanction() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let today = Local::today().naive_local();
+
+            assert_eq!(
+                conn.undo_last_sanction(team_id, user_id),
+                Err(DbError::NotFound)
+            );
+
+            let sanctions = conn
+                .create_sanctions(&[
+                    CreateSanction {
+                        id: Uuid::new_v4(),
+                        user_id,
+                        team_id,
+                        created_at: Some(start_of_day(today - Duration::days(1))),
+                        ..Default::default()
+                    },
+                    CreateSanction {
+                        id: Uuid::new_v4(),
+                        user_id,
+                        team_id,
+                        created_at: Some(start_of_day(today)),
+                        ..Default::default()
+                    },
+                ])
+                .unwrap();
+
+            assert_eq!(
+                conn.undo_last_sanction(team_id, user_id).unwrap(),
+                sanctions[1]
+            );
+            assert_eq!(
+                conn.get_sanctions(team_id, None, &SanctionFilter::default())
+                    .unwrap(),
+                vec![sanctions[0].clone()]
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_create_sanctions_checks_user_team() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let other_team_id = conn
+                .create_team(
+                    &Team {
+                        id: Uuid::new_v4(),
+                        name: String::from("Les Pierres"),
+                        ..team_with_rule()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            assert_eq!(
+                conn.create_sanctions(&[CreateSanction {
+                    user_id,
+                    team_id: other_team_id,
+                    ..Default::default()
+                }]),
+                Err(DbError::NotValid(format!(
+                    "The user {} doesn't belong to the team {}",
+                    user_id, other_team_id
+                )))
+            );
+            assert!(conn
+                .create_sanctions(&[CreateSanction {
+                    user_id,
+                    team_id,
+                    ..Default::default()
+                }])
+                .is_ok());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_create_sanctions_checks_rule_team() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let rule = Rule {
+                id: Uuid::new_v4(),
+                ..Default::default()
+            };
+            let foreign_rule = Rule {
+                id: Uuid::new_v4(),
+                ..Default::default()
+            };
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: vec![rule.clone()],
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            conn.create_team(
+                &Team {
+                    id: Uuid::new_v4(),
+                    name: String::from("Les Pierres"),
+                    rules: vec![foreign_rule.clone()],
+                    ..Default::default()
+                },
+                &unique_admin(),
+            )
+            .unwrap();
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let sanction = |rule_id: Uuid| CreateSanction {
+                user_id,
+                team_id,
+                sanction_info: SanctionInfo {
+                    associated_rule: rule_id,
+                    extra_info: ExtraInfo::None,
+                },
+                ..Default::default()
+            };
+
+            assert_eq!(
+                conn.create_sanctions(&[sanction(foreign_rule.id)]),
+                Err(DbError::NotValid(format!(
+                    "The rule {} doesn't belong to the team {}",
+                    foreign_rule.id, team_id
+                )))
+            );
+            assert!(conn.create_sanctions(&[sanction(rule.id)]).is_ok());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_fine_distribution() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            assert_eq!(
+                conn.get_fine_distribution(team_id).unwrap(),
+                FineStats::default()
+            );
+
+            let sanctions: Vec<CreateSanction> = [250, 250, 1000]
+                .iter()
+                .map(|price| CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    team_id,
+                    price: Amount::from_cents(*price),
+                    ..Default::default()
+                })
+                .collect();
+            conn.create_sanctions(&sanctions).unwrap();
+
+            assert_eq!(
+                conn.get_fine_distribution(team_id).unwrap(),
+                FineStats {
+                    count: 3,
+                    mean: Amount::from_cents(500),
+                    median: Amount::from_cents(250),
+                    min: Amount::from_cents(250),
+                    max: Amount::from_cents(1000),
+                }
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_disputes() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let sanctions = conn
+                .create_sanctions(&[
+                    CreateSanction {
+                        user_id,
+                        team_id,
+                        ..Default::default()
+                    },
+                    CreateSanction {
+                        id: Uuid::new_v4(),
+                        user_id,
+                        team_id,
+                        ..Default::default()
+                    },
+                ])
+                .unwrap();
+            let (upheld_id, overturned_id) = (sanctions[0].id, sanctions[1].id);
+
+            assert_eq!(
+                conn.resolve_dispute(team_id, upheld_id, true).unwrap_err(),
+                DbError::Forbidden(format!("The sanction {} is not disputed", upheld_id))
+            );
+
+            let disputed = conn
+                .dispute_sanction(team_id, upheld_id, Some("I was on time"))
+                .unwrap();
+            assert!(disputed.disputed);
+            assert_eq!(disputed.dispute_note, Some(String::from("I was on time")));
+            assert!(
+                conn.dispute_sanction(team_id, overturned_id, None)
+                    .unwrap()
+                    .disputed
+            );
+
+            let upheld = conn.resolve_dispute(team_id, upheld_id, true).unwrap();
+            assert!(!upheld.disputed);
+            assert_eq!(upheld.waived_at, None);
+            assert_eq!(upheld.dispute_note, Some(String::from("I was on time")));
+
+            let overturned = conn.resolve_dispute(team_id, overturned_id, false).unwrap();
+            assert!(!overturned.disputed);
+            assert_eq!(overturned.waived_at, Some(Local::today().naive_local()));
+
+            assert_eq!(
+                conn.dispute_sanction(team_id, overturned_id, None)
+                    .unwrap_err(),
+                DbError::Forbidden(format!("The sanction {} is already waived", overturned_id))
+            );
+            assert_eq!(
+                conn.dispute_sanction(Uuid::new_v4(), upheld_id, None)
+                    .unwrap_err(),
+                DbError::NotFound
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_amount_by_category() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let game_day_rule = Rule {
+                id: Uuid::new_v4(),
+                category: RuleCategory::GameDay,
+                ..Default::default()
+            };
+            let training_day_rule = Rule {
+                id: Uuid::new_v4(),
+                category: RuleCategory::TrainingDay,
+                ..Default::default()
+            };
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: vec![game_day_rule.clone(), training_day_rule.clone()],
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            let sanction = |rule_id: Uuid, price: i64| CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                sanction_info: SanctionInfo {
+                    associated_rule: rule_id,
+                    extra_info: ExtraInfo::None,
+                },
+                price: Amount::from_cents(price),
+                ..Default::default()
+            };
+
+            assert_eq!(conn.get_amount_by_category(team_id).unwrap(), vec![]);
+
+            conn.create_sanctions(&[
+                sanction(game_day_rule.id, 500),
+                sanction(game_day_rule.id, 250),
+                sanction(training_day_rule.id, 100),
+                CreateSanction {
+                    paid_at: Some(Local::today().naive_local()),
+                    ..sanction(training_day_rule.id, 1000)
+                },
+                CreateSanction {
+                    waived_at: Some(Local::today().naive_local()),
+                    ..sanction(game_day_rule.id, 1000)
+                },
+            ])
+            .unwrap();
+            // A sanction whose rule isn't the team's can only predate the checks
+            diesel::insert_into(sanctions::table)
+                .values(&sanction(Uuid::new_v4(), 300))
+                .execute(conn.deref())
+                .unwrap();
+
+            assert_eq!(
+                conn.get_amount_by_category(team_id).unwrap(),
+                vec![
+                    (Some(RuleCategory::GameDay), Amount::from_cents(750)),
+                    (Some(RuleCategory::TrainingDay), Amount::from_cents(100)),
+                    (None, Amount::from_cents(300)),
+                ]
+            );
+            assert_eq!(
+                conn.get_amount_by_category(Uuid::new_v4()).unwrap_err(),
+                DbError::NotFound
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_recent_sanctions() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let sanction = |hours_ago: i64| CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                recorded_at: Some(Utc::now() - Duration::hours(hours_ago)),
+                ..Default::default()
+            };
+
+            let sanctions = conn
+                .create_sanctions(&[sanction(5), sanction(1), sanction(30)])
+                .unwrap();
+            let just_now = conn
+                .create_sanctions(&[CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    team_id,
+                    ..Default::default()
+                }])
+                .unwrap();
+
+            let recent = conn.get_recent_sanctions(team_id, 6).unwrap();
+
+            assert_eq!(
+                recent
+                    .iter()
+                    .map(|sanction| sanction.id)
+                    .collect::<Vec<Uuid>>(),
+                vec![just_now[0].id, sanctions[1].id, sanctions[0].id]
+            );
+            assert_eq!(conn.get_recent_sanctions(team_id, 48).unwrap().len(), 4);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_created_at_is_a_timestamp() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let yesterday = Utc::today().naive_utc().pred();
+
+            let sanctions = conn
+                .create_sanctions(&[
+                    CreateSanction {
+                        user_id,
+                        team_id,
+                        ..Default::default()
+                    },
+                    CreateSanction {
+                        id: Uuid::new_v4(),
+                        user_id,
+                        team_id,
+                        created_at: Some(start_of_day(yesterday) + Duration::hours(23)),
+                        ..Default::default()
+                    },
+                ])
+                .unwrap();
+
+            assert!(Utc::now() - sanctions[0].created_at < Duration::minutes(1));
+            assert_eq!(sanctions[1].created_on(), yesterday);
+
+            let filtered = conn
+                .get_sanctions(
+                    team_id,
+                    Some((yesterday, yesterday)),
+                    &SanctionFilter::default(),
+                )
+                .unwrap();
+            assert_eq!(filtered, vec![sanctions[1].clone()]);
+            assert_eq!(
+                conn.get_daily_sanction_counts(team_id, yesterday, yesterday)
+                    .unwrap(),
+                vec![(yesterday, 1)]
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_unused_rules() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let rules: Vec<Rule> = (0..3)
+                .map(|_| Rule {
+                    id: Uuid::new_v4(),
+                    ..Default::default()
+                })
+                .collect();
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: rules.clone(),
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let since = NaiveDate::from_ymd(2019, 10, 1);
+            let sanction = |rule: &Rule, date: NaiveDate| CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                sanction_info: SanctionInfo {
+                    associated_rule: rule.id,
+                    extra_info: ExtraInfo::None,
+                },
+                created_at: Some(start_of_day(date)),
+                ..Default::default()
+            };
+
+            conn.create_sanctions(&[
+                sanction(&rules[0], since),
+                sanction(&rules[1], since.pred()),
+            ])
+            .unwrap();
+
+            assert_eq!(
+                conn.get_unused_rules(team_id, since).unwrap(),
+                vec![rules[1].clone(), rules[2].clone()]
+            );
+            assert_eq!(
+                conn.get_unused_rules(Uuid::new_v4(), since).unwrap_err(),
+                DbError::NotFound
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_simulate_rule_price_change() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let rules: Vec<Rule> = (0..2)
+                .map(|_| Rule {
+                    id: Uuid::new_v4(),
+                    kind: RuleKind::Basic {
+                        price: Amount::from_cents(250),
+                    },
+                    ..Default::default()
+                })
+                .collect();
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: rules.clone(),
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let sanction = |rule: &Rule| CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                sanction_info: SanctionInfo {
+                    associated_rule: rule.id,
+                    extra_info: ExtraInfo::None,
+                },
+                price: Amount::from_cents(250),
+                ..Default::default()
+            };
+
+            conn.create_sanctions(&[
+                sanction(&rules[0]),
+                sanction(&rules[0]),
+                sanction(&rules[1]),
+            ])
+            .unwrap();
+
+            let (current_total, simulated_total) = conn
+                .simulate_rule_price_change(team_id, rules[0].id, Amount::from_cents(500))
+                .unwrap();
+            assert_eq!(current_total, Amount::from_cents(750));
+            assert_eq!(simulated_total, Amount::from_cents(1250));
+            assert_eq!(
+                simulated_total.cents() - current_total.cents(),
+                Amount::from_cents(500).cents()
+            );
+            assert_eq!(
+                conn.get_balance(team_id, user_id).unwrap(),
+                Amount::from_cents(750)
+            );
+            match conn
+                .simulate_rule_price_change(team_id, rules[0].id, Amount::from_cents(-100))
+                .unwrap_err()
+            {
+                DbError::NotValid(_) => {}
+                err => panic!("Unexpected error {:?}", err),
+            }
+            assert_eq!(
+                conn.simulate_rule_price_change(team_id, Uuid::new_v4(), Amount::from_cents(500))
+                    .unwrap_err(),
+                DbError::NotFound
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_sanction_details() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let john = conn.add_admin(team_id, "john", "password").unwrap();
+            let jane = conn.add_admin(team_id, "jane", "password").unwrap();
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let sanction = conn
+                .create_sanctions(&[CreateSanction {
+                    user_id,
+                    team_id,
+                    created_by: Some(john.id),
+                    ..Default::default()
+                }])
+                .unwrap()
+                .remove(0);
+
+            let details = conn.get_sanction_details(team_id).unwrap();
+
+            assert_eq!(details[0].creator_name, Some(String::from("john")));
+            assert_eq!(details[0].settler_name, None);
+
+            conn.mark_user_sanctions_paid(
+                team_id,
+                user_id,
+                Local::today().naive_local(),
+                Some(jane.id),
+            )
+            .unwrap();
+
+            let details = conn.get_sanction_details(team_id).unwrap();
+
+            assert_eq!(details.len(), 1);
+            assert_eq!(details[0].sanction.id, sanction.id);
+            assert_eq!(details[0].sanction.paid_by, Some(jane.id));
+            assert_eq!(details[0].creator_name, Some(String::from("john")));
+            assert_eq!(details[0].settler_name, Some(String::from("jane")));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_create_sanctions_self_fine_policy() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        allow_self_fine: false,
+                        ..team_with_rule()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let coach = conn.add_admin(team_id, "coach", "password").unwrap();
+            conn.set_admin_user(team_id, "coach", Some(user_id))
+                .unwrap();
+            let self_fine = CreateSanction {
+                user_id,
+                team_id,
+                created_by: Some(coach.id),
+                ..Default::default()
+            };
+
+            match conn
+                .create_sanctions(std::slice::from_ref(&self_fine))
+                .unwrap_err()
+            {
+                DbError::Forbidden(_) => {}
+                err => panic!("Unexpected error {:?}", err),
+            }
+
+            let other_user_id = conn
+                .create_user(&User {
+                    id: Uuid::new_v4(),
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            conn.create_sanctions(&[CreateSanction {
+                id: Uuid::new_v4(),
+                user_id: other_user_id,
+                ..self_fine.clone()
+            }])
+            .unwrap();
+
+            diesel::update(teams::table.find(team_id))
+                .set(teams::allow_self_fine.eq(true))
+                .execute(conn.deref())?;
+
+            let sanctions = conn.create_sanctions(&[self_fine]).unwrap();
+
+            assert_eq!(sanctions[0].created_by, Some(coach.id));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_average_balance() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+
+            assert_eq!(
+                conn.get_average_balance(team_id).unwrap(),
+                Amount::default()
+            );
+
+            let user_ids: Vec<Uuid> = (0..2)
+                .map(|_| {
+                    conn.create_user(&User {
+                        id: Uuid::new_v4(),
+                        team_id,
+                        ..Default::default()
+                    })
+                    .unwrap()
+                    .id
+                })
+                .collect();
+            conn.create_sanctions(&[CreateSanction {
+                user_id: user_ids[0],
+                team_id,
+                price: Amount::from_cents(500),
+                ..Default::default()
+            }])
+            .unwrap();
+
+            assert_eq!(
+                conn.get_average_balance(team_id).unwrap(),
+                Amount::from_cents(250)
+            );
+
+            let anonymized_id = conn
+                .create_user(&User {
+                    id: Uuid::new_v4(),
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            conn.create_sanctions(&[CreateSanction {
+                id: Uuid::new_v4(),
+                user_id: anonymized_id,
+                team_id,
+                price: Amount::from_cents(100),
+                ..Default::default()
+            }])
+            .unwrap();
+            conn.anonymize_user(team_id, anonymized_id).unwrap();
+
+            assert_eq!(
+                conn.get_average_balance(team_id).unwrap(),
+                Amount::from_cents(300)
+            );
+            assert_eq!(
+                conn.get_average_balance(Uuid::new_v4()).unwrap_err(),
+                DbError::NotFound
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_waive_sanctions_where() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let rules: Vec<Rule> = (0..2)
+                .map(|_| Rule {
+                    id: Uuid::new_v4(),
+                    ..Default::default()
+                })
+                .collect();
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: rules.clone(),
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let sanction = |rule: &Rule| CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                sanction_info: SanctionInfo {
+                    associated_rule: rule.id,
+                    extra_info: ExtraInfo::None,
+                },
+                price: Amount::from_cents(250),
+                ..Default::default()
+            };
+            conn.create_sanctions(&[
+                sanction(&rules[0]),
+                sanction(&rules[0]),
+                sanction(&rules[1]),
+            ])
+            .unwrap();
+
+            assert_eq!(
+                conn.get_balance(team_id, user_id).unwrap(),
+                Amount::from_cents(750)
+            );
+
+            let filter = SanctionFilter {
+                rule_id: Some(rules[0].id),
+                ..Default::default()
+            };
+
+            assert_eq!(
+                conn.waive_sanctions_where(team_id, None, &filter, "Cancelled game")
+                    .unwrap(),
+                2
+            );
+            assert_eq!(
+                conn.get_balance(team_id, user_id).unwrap(),
+                Amount::from_cents(250)
+            );
+
+            let waived = conn
+                .get_sanctions(
+                    team_id,
+                    None,
+                    &SanctionFilter {
+                        include_waived: true,
+                        ..filter.clone()
+                    },
+                )
+                .unwrap();
+
+            assert!(waived
+                .iter()
+                .all(|sanction| sanction.waive_reason == Some(String::from("Cancelled game"))));
+            assert_eq!(
+                conn.waive_sanctions_where(team_id, None, &filter, "Again")
+                    .unwrap(),
+                0
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_rules_with_usage() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let rules: Vec<Rule> = (0..2)
+                .map(|_| Rule {
+                    id: Uuid::new_v4(),
+                    ..Default::default()
+                })
+                .collect();
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: rules.clone(),
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            let sanction = || CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                sanction_info: SanctionInfo {
+                    associated_rule: rules[0].id,
+                    extra_info: ExtraInfo::None,
+                },
+                ..Default::default()
+            };
+
+            conn.create_sanctions(&[sanction(), sanction()]).unwrap();
+
+            assert_eq!(
+                conn.get_rules_with_usage(team_id).unwrap(),
+                vec![(rules[0].clone(), 2), (rules[1].clone(), 0)]
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_create_sanctions_max_balance() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            conn.set_max_balance(team_id, Some(Amount::from_cents(5000)), false)
+                .unwrap();
+
+            let sanction = |price: i64| CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                price: Amount::from_cents(price),
+                ..Default::default()
+            };
+
+            let sanctions = conn
+                .create_sanctions(&[sanction(2000), sanction(2000)])
+                .unwrap();
+
+            assert_eq!(sanctions[1].price, Amount::from_cents(2000));
+
+            match conn.create_sanctions(&[sanction(2000)]).unwrap_err() {
+                DbError::Forbidden(_) => {}
+                err => panic!("Unexpected error {:?}", err),
+            }
+            assert_eq!(
+                conn.get_balance(team_id, user_id).unwrap(),
+                Amount::from_cents(4000)
+            );
+
+            conn.set_max_balance(team_id, Some(Amount::from_cents(5000)), true)
+                .unwrap();
+
+            let sanctions = conn
+                .create_sanctions(&[sanction(2000), sanction(500)])
+                .unwrap();
+
+            assert_eq!(sanctions[0].price, Amount::from_cents(1000));
+            assert_eq!(sanctions[1].price, Amount::from_cents(0));
+            assert_eq!(
+                conn.get_balance(team_id, user_id).unwrap(),
+                Amount::from_cents(5000)
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_sanctions_modified_since() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            let sanction = || CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                ..Default::default()
+            };
+            let sanctions = conn.create_sanctions(&[sanction(), sanction()]).unwrap();
+
+            // Every statement of the test transaction sees the same current time, so
+            // the sanctions are backdated to tell the later change apart.
+            diesel::update(sanctions::table.filter(sanctions::team_id.eq(team_id)))
+                .set(sanctions::updated_at.eq(Utc::now() - Duration::hours(1)))
+                .execute(conn.deref())?;
+
+            let since = Utc::now() - Duration::minutes(30);
+            assert_eq!(
+                conn.get_sanctions_modified_since(team_id, since).unwrap(),
+                vec![]
+            );
+
+            conn.dispute_sanction(team_id, sanctions[1].id, None)
+                .unwrap();
+
+            let modified = conn.get_sanctions_modified_since(team_id, since).unwrap();
+
+            assert_eq!(modified.len(), 1);
+            assert_eq!(modified[0].id, sanctions[1].id);
+            assert!(modified[0].disputed);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_sanction_audit() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            let treasurer = conn.add_admin(team_id, "treasurer", "password").unwrap();
+
+            let sanction = conn
+                .create_sanctions(&[CreateSanction {
+                    user_id,
+                    team_id,
+                    created_by: Some(treasurer.id),
+                    ..Default::default()
+                }])
+                .unwrap()
+                .remove(0);
+            conn.mark_user_sanctions_paid(
+                team_id,
+                user_id,
+                Local::today().naive_local(),
+                Some(treasurer.id),
+            )
+            .unwrap();
+            conn.dispute_sanction(team_id, sanction.id, Some("I was on time"))
+                .unwrap();
+
+            let events = conn.get_sanction_audit(team_id, sanction.id).unwrap();
+
+            assert_eq!(
+                events
+                    .iter()
+                    .map(|event| (event.kind, event.actor))
+                    .collect::<Vec<(AuditEventKind, Option<Uuid>)>>(),
+                vec![
+                    (AuditEventKind::Created, Some(treasurer.id)),
+                    (AuditEventKind::Paid, Some(treasurer.id)),
+                    (AuditEventKind::Disputed, None),
+                ]
+            );
+            assert!(events
+                .windows(2)
+                .all(|pair| pair[0].occurred_at <= pair[1].occurred_at));
+
+            assert_eq!(
+                conn.get_sanction_audit(Uuid::new_v4(), sanction.id)
+                    .unwrap_err(),
+                DbError::NotFound
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_user_vs_average() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_ids: Vec<Uuid> = (0..2)
+                .map(|_| {
+                    conn.create_user(&User {
+                        id: Uuid::new_v4(),
+                        team_id,
+                        ..Default::default()
+                    })
+                    .unwrap()
+                    .id
+                })
+                .collect();
+
+            assert_eq!(
+                conn.get_user_vs_average(team_id, user_ids[0]).unwrap(),
+                (Amount::default(), Amount::default(), 0.0)
+            );
+
+            conn.create_sanctions(&[CreateSanction {
+                user_id: user_ids[0],
+                team_id,
+                price: Amount::from_cents(500),
+                ..Default::default()
+            }])
+            .unwrap();
+
+            assert_eq!(
+                conn.get_user_vs_average(team_id, user_ids[0]).unwrap(),
+                (Amount::from_cents(500), Amount::from_cents(250), 2.0)
+            );
+            assert_eq!(
+                conn.get_user_vs_average(team_id, user_ids[1]).unwrap(),
+                (Amount::default(), Amount::from_cents(250), 0.0)
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_top_rule_per_user() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let rules: Vec<Rule> = ["A", "B"]
+                .iter()
+                .map(|name| Rule {
+                    id: Uuid::new_v4(),
+                    name: name.to_string(),
+                    ..Default::default()
+                })
+                .collect();
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: rules.clone(),
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user = |lastname: &str| {
+                conn.create_user(&User {
+                    id: Uuid::new_v4(),
+                    team_id,
+                    lastname: lastname.to_string(),
+                    ..Default::default()
+                })
+                .unwrap()
+            };
+            let first_user = user("Doe");
+            let second_user = user("Smith");
+
+            let sanction = |user_id: Uuid, rule: &Rule| CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                sanction_info: SanctionInfo {
+                    associated_rule: rule.id,
+                    extra_info: ExtraInfo::None,
+                },
+                ..Default::default()
+            };
+
+            conn.create_sanctions(&[
+                sanction(first_user.id, &rules[1]),
+                sanction(first_user.id, &rules[0]),
+                sanction(first_user.id, &rules[0]),
+                sanction(first_user.id, &rules[0]),
+            ])
+            .unwrap();
 
-        Ok(sanction)
-    }
-}
+            assert_eq!(
+                conn.get_top_rule_per_user(team_id).unwrap(),
+                vec![
+                    (first_user.clone(), Some(rules[0].clone()), 3),
+                    (second_user.clone(), None, 0),
+                ]
+            );
 
-#[cfg(test)]
-mod tests {
-    use chrono::Local;
-    use diesel::result::Error;
+            conn.create_sanctions(&[
+                sanction(second_user.id, &rules[1]),
+                sanction(second_user.id, &rules[0]),
+            ])
+            .unwrap();
 
-    use super::*;
-    use crate::teams::{interface::TeamsDb, models::Team};
-    use crate::test_utils::postgres::init_connection;
-    use crate::users::{interface::UsersDb, models::User};
+            assert_eq!(
+                conn.get_top_rule_per_user(team_id).unwrap()[1],
+                (second_user, Some(rules[0].clone()), 1)
+            );
+
+            Ok(())
+        });
+    }
 
     #[test]
-    fn test_get_sanctions() {
+    fn test_create_sanctions_user_on_leave() {
         let conn = init_connection();
+
         conn.deref().test_transaction::<_, Error, _>(|| {
-            let team_id = conn.create_team(&Team::default()).unwrap().id;
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let today = Local::today().naive_local();
             let user_id = conn
                 .create_user(&User {
                     team_id,
+                    on_leave_until: Some(today + Duration::days(7)),
                     ..Default::default()
                 })
                 .unwrap()
                 .id;
-            let sanction = conn
-                .create_sanctions(&[CreateSanction {
+
+            let sanction = |created_at: Option<DateTime<Utc>>| CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                created_at,
+                ..Default::default()
+            };
+
+            assert_eq!(
+                conn.create_sanctions(&[sanction(None)]).unwrap_err(),
+                DbError::Forbidden(format!(
+                    "The user {} is on leave until {}",
                     user_id,
-                    team_id,
-                    ..Default::default()
-                }])
-                .unwrap();
+                    today + Duration::days(7)
+                ))
+            );
 
-            let team_id_2 = conn
-                .create_team(&Team {
+            let after_leave = Utc::now() + Duration::days(8);
+            assert_eq!(
+                conn.create_sanctions(&[sanction(Some(after_leave))])
+                    .unwrap()
+                    .len(),
+                1
+            );
+
+            assert_eq!(
+                conn.create_sanctions_overriding_leave(&[sanction(None)])
+                    .unwrap()
+                    .len(),
+                1
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_rule_trend() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let rules: Vec<Rule> = (0..2)
+                .map(|_| Rule {
                     id: Uuid::new_v4(),
-                    name: String::from("CHBC"),
                     ..Default::default()
                 })
+                .collect();
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: rules.clone(),
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
                 .unwrap()
                 .id;
-            let user_id_2 = conn
+            let user_id = conn
                 .create_user(&User {
-                    id: Uuid::new_v4(),
-                    team_id: team_id_2,
+                    team_id,
                     ..Default::default()
                 })
                 .unwrap()
                 .id;
-            let sanction_2 = conn
-                .create_sanctions(&[CreateSanction {
-                    id: Uuid::new_v4(),
-                    user_id: user_id_2,
-                    team_id: team_id_2,
-                    ..Default::default()
-                }])
-                .unwrap();
 
-            let sanctions: Vec<Sanction> = conn.get_sanctions(team_id, None).unwrap();
-            let sanctions_2: Vec<Sanction> = conn.get_sanctions(team_id_2, None).unwrap();
+            let sanction = |rule: &Rule, date: NaiveDate| CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                sanction_info: SanctionInfo {
+                    associated_rule: rule.id,
+                    extra_info: ExtraInfo::None,
+                },
+                created_at: Some(start_of_day(date)),
+                ..Default::default()
+            };
+            let date = |month: u32, day: u32| NaiveDate::from_ymd(2020, month, day);
 
-            assert_eq!(sanction, sanctions);
-            assert_eq!(sanction_2, sanctions_2);
+            conn.create_sanctions(&[
+                sanction(&rules[0], date(3, 2)),
+                sanction(&rules[0], date(3, 20)),
+                sanction(&rules[0], date(3, 31)),
+                sanction(&rules[0], date(4, 15)),
+                sanction(&rules[1], date(4, 15)),
+                sanction(&rules[0], date(5, 1)),
+            ])
+            .unwrap();
+
+            assert_eq!(
+                conn.get_rule_trend(team_id, rules[0].id, date(3, 1), date(4, 30), Bucket::Month)
+                    .unwrap(),
+                vec![(date(3, 1), 3), (date(4, 1), 1)]
+            );
+            assert_eq!(
+                conn.get_rule_trend(team_id, rules[0].id, date(3, 1), date(3, 31), Bucket::Week)
+                    .unwrap(),
+                vec![(date(3, 2), 1), (date(3, 16), 1), (date(3, 30), 1)]
+            );
 
             Ok(())
         });
     }
 
     #[test]
-    fn test_get_sanctions_with_date_interval() {
+    fn test_create_sanctions_excluded_rules() {
         let conn = init_connection();
 
         conn.deref().test_transaction::<_, Error, _>(|| {
-            let team_id = conn.create_team(&Team::default()).unwrap().id;
+            let late_id = Uuid::new_v4();
+            let absent_id = Uuid::new_v4();
+            let rules = vec![
+                Rule {
+                    id: late_id,
+                    name: String::from("Late"),
+                    excludes: vec![absent_id],
+                    ..Default::default()
+                },
+                Rule {
+                    id: absent_id,
+                    name: String::from("Absent"),
+                    ..Default::default()
+                },
+            ];
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules,
+                        ..team_with_rule()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
             let user_id = conn
                 .create_user(&User {
                     team_id,
@@ -135,58 +4349,56 @@ mod tests {
                 .unwrap()
                 .id;
 
-            let sanction = conn
-                .create_sanctions(&[CreateSanction {
-                    user_id,
-                    team_id,
-                    created_at: Some(NaiveDate::from_ymd(2019, 10, 13)),
-                    ..Default::default()
-                }])
-                .unwrap();
-
-            conn.create_sanctions(&[
-                CreateSanction {
-                    id: Uuid::new_v4(),
-                    user_id,
-                    team_id,
-                    created_at: Some(NaiveDate::from_ymd(2019, 10, 5)),
-                    ..Default::default()
-                },
-                CreateSanction {
-                    id: Uuid::new_v4(),
-                    user_id,
-                    team_id,
-                    created_at: Some(NaiveDate::from_ymd(2019, 10, 25)),
-                    ..Default::default()
+            let sanction = |rule_id: Uuid, created_at: DateTime<Utc>| CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                sanction_info: SanctionInfo {
+                    associated_rule: rule_id,
+                    extra_info: ExtraInfo::None,
                 },
-            ])
-            .unwrap();
+                created_at: Some(created_at),
+                ..Default::default()
+            };
+            let today = start_of_day(Local::today().naive_local());
 
-            let returned_sanctions: Vec<Sanction> = conn
-                .get_sanctions(
-                    team_id,
-                    Some((
-                        NaiveDate::from_ymd(2019, 10, 6),
-                        NaiveDate::from_ymd(2019, 10, 20),
-                    )),
-                )
+            conn.create_sanctions(&[sanction(absent_id, today)])
                 .unwrap();
 
-            assert_eq!(sanction, returned_sanctions);
+            assert_eq!(
+                conn.create_sanctions(&[sanction(late_id, today)])
+                    .unwrap_err(),
+                DbError::Forbidden(String::from(
+                    "The rule Late can't be applied the same day as the rule Absent"
+                ))
+            );
+            assert_eq!(
+                conn.create_sanctions(&[
+                    sanction(late_id, today + Duration::days(1)),
+                    sanction(absent_id, today + Duration::days(1)),
+                ])
+                .unwrap_err(),
+                DbError::Forbidden(String::from(
+                    "The rule Absent can't be applied the same day as the rule Late"
+                ))
+            );
+            assert!(conn
+                .create_sanctions(&[sanction(late_id, today + Duration::days(1))])
+                .is_ok());
 
             Ok(())
-        })
+        });
     }
 
     #[test]
-    fn test_create_sanctions() {
+    fn test_get_balance_grace_days() {
         let conn = init_connection();
 
         conn.deref().test_transaction::<_, Error, _>(|| {
-            let id = Uuid::new_v4();
-
-            let team_id = conn.create_team(&Team::default()).unwrap().id;
-
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
             let user_id = conn
                 .create_user(&User {
                     team_id,
@@ -195,69 +4407,142 @@ mod tests {
                 .unwrap()
                 .id;
 
-            let created_at = NaiveDate::from_ymd(2019, 10, 15);
+            let sanction = |days_ago: i64, price: i64| CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                price: Amount::from_cents(price),
+                created_at: Some(Utc::now() - Duration::days(days_ago)),
+                ..Default::default()
+            };
 
-            let sanctions = conn
-                .create_sanctions(&[
-                    CreateSanction {
-                        id,
-                        user_id,
-                        team_id,
-                        ..Default::default()
-                    },
-                    CreateSanction {
-                        user_id,
-                        team_id,
-                        created_at: Some(created_at),
-                        ..Default::default()
-                    },
-                ])
+            conn.create_sanctions(&[sanction(2, 100), sanction(10, 250)])
                 .unwrap();
 
-            assert_eq!(sanctions[0].id, id);
-            assert_eq!(sanctions[0].user_id, user_id);
-            assert_eq!(sanctions[0].team_id, team_id);
-            assert_eq!(sanctions[0].created_at, Local::today().naive_local());
-            assert_eq!(sanctions[1].created_at, created_at);
+            assert_eq!(
+                conn.get_balance(team_id, user_id).unwrap(),
+                Amount::from_cents(350)
+            );
+
+            conn.set_grace_days(team_id, 7).unwrap();
+
+            assert_eq!(
+                conn.get_balance(team_id, user_id).unwrap(),
+                Amount::from_cents(250)
+            );
+            assert_eq!(
+                conn.get_balances(team_id, &[user_id]).unwrap()[&user_id],
+                Amount::from_cents(250)
+            );
 
             Ok(())
         });
     }
 
     #[test]
-    fn test_create_sanction_fails() {
+    fn test_get_fine_gini() {
         let conn = init_connection();
 
         conn.deref().test_transaction::<_, Error, _>(|| {
-            let error = conn
-                .create_sanctions(&[CreateSanction::default()])
-                .unwrap_err();
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_ids: Vec<Uuid> = (0..4)
+                .map(|_| {
+                    conn.create_user(&User {
+                        id: Uuid::new_v4(),
+                        team_id,
+                        ..Default::default()
+                    })
+                    .unwrap()
+                    .id
+                })
+                .collect();
 
-            assert_eq!(
-                error,
-                DbError::ForeignKeyViolation(String::from(
-                    "The key team_id doesn\'t refer to anything"
-                ))
-            );
+            assert_eq!(conn.get_fine_gini(team_id).unwrap(), 0.0);
+
+            conn.create_sanctions(&[CreateSanction {
+                id: Uuid::new_v4(),
+                user_id: user_ids[3],
+                team_id,
+                price: Amount::from_cents(1000),
+                ..Default::default()
+            }])
+            .unwrap();
+
+            // Each of the 3 users fined nothing differs by 10 from the fourth one, both
+            // ways, so the Gini coefficient is 2 * 3 * 10 / (2 * 4² * 2.5).
+            assert_eq!(conn.get_fine_gini(team_id).unwrap(), 0.75);
 
             Ok(())
         });
+    }
 
-        conn.deref().test_transaction::<_, Error, _>(|| {
-            let team_id = conn.create_team(&Team::default()).unwrap().id;
+    #[test]
+    fn test_rule_deletion_impact() {
+        let conn = init_connection();
 
-            let error = conn
-                .create_sanctions(&[CreateSanction {
-                    team_id,
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let rules: Vec<Rule> = (0..2)
+                .map(|_| Rule {
+                    id: Uuid::new_v4(),
                     ..Default::default()
-                }])
-                .unwrap_err();
+                })
+                .collect();
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: rules.clone(),
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user_ids: Vec<Uuid> = (0..2)
+                .map(|_| {
+                    conn.create_user(&User {
+                        id: Uuid::new_v4(),
+                        team_id,
+                        ..Default::default()
+                    })
+                    .unwrap()
+                    .id
+                })
+                .collect();
+
+            let sanction = |user_id: Uuid, rule: &Rule, price: i64| CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                sanction_info: SanctionInfo {
+                    associated_rule: rule.id,
+                    extra_info: ExtraInfo::None,
+                },
+                price: Amount::from_cents(price),
+                ..Default::default()
+            };
+
+            conn.create_sanctions(&[
+                sanction(user_ids[0], &rules[0], 200),
+                sanction(user_ids[1], &rules[0], 350),
+                sanction(user_ids[0], &rules[1], 1000),
+            ])
+            .unwrap();
 
             assert_eq!(
-                error,
-                DbError::ForeignKeyViolation(String::from(
-                    "The key user_id doesn\'t refer to anything"
-                ))
+                conn.rule_deletion_impact(team_id, rules[0].id).unwrap(),
+                RuleImpact {
+                    sanctions_count: 2,
+                    total: Amount::from_cents(550),
+                    users_count: 2,
+                }
+            );
+            assert_eq!(
+                conn.rule_deletion_impact(team_id, Uuid::new_v4())
+                    .unwrap_err(),
+                DbError::NotFound
             );
 
             Ok(())
@@ -265,46 +4550,155 @@ mod tests {
     }
 
     #[test]
-    fn test_delete_sanction() {
+    fn test_get_fine_leaderboard() {
         let conn = init_connection();
 
         conn.deref().test_transaction::<_, Error, _>(|| {
-            let team_id = conn.create_team(&Team::default()).unwrap().id;
-
-            let user_id = conn
-                .create_user(&User {
-                    team_id,
-                    ..Default::default()
-                })
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
                 .unwrap()
                 .id;
+            let user_ids: Vec<Uuid> = ["Robert", "Martin", "Durand", "Petit", "Bernard"]
+                .iter()
+                .map(|lastname| {
+                    conn.create_user(&User {
+                        id: Uuid::new_v4(),
+                        team_id,
+                        lastname: lastname.to_string(),
+                        ..Default::default()
+                    })
+                    .unwrap()
+                    .id
+                })
+                .collect();
 
-            let sanctions = conn
-                .create_sanctions(&[CreateSanction {
+            let sanctions: Vec<CreateSanction> = [(2, 3000), (1, 1000), (4, 1000)]
+                .iter()
+                .map(|(user, cents)| CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id: user_ids[*user],
                     team_id,
-                    user_id,
+                    price: Amount::from_cents(*cents),
                     ..Default::default()
-                }])
-                .unwrap();
+                })
+                .collect();
+            conn.create_sanctions(&sanctions).unwrap();
 
-            let sanction_deleted = conn.delete_sanction(team_id, sanctions[0].id).unwrap();
-            assert_eq!(sanctions[0].id, sanction_deleted.id);
+            let pages: Vec<Paginated<(User, Amount)>> = [0, 2, 4]
+                .iter()
+                .map(|offset| {
+                    conn.get_fine_leaderboard(
+                        team_id,
+                        Some(&Pagination::new(Some(2), Some(*offset), 200)),
+                    )
+                    .unwrap()
+                })
+                .collect();
 
-            let sanctions = conn.get_sanctions(team_id, None).unwrap();
-            assert_eq!(sanctions.len(), 0);
+            assert!(pages.iter().all(|page| page.total == 5));
+            assert_eq!(
+                pages
+                    .iter()
+                    .map(|page| page
+                        .items
+                        .iter()
+                        .map(|(user, total)| (user.lastname.as_str(), total.cents()))
+                        .collect::<Vec<(&str, i64)>>())
+                    .collect::<Vec<Vec<(&str, i64)>>>(),
+                vec![
+                    vec![("Durand", 3000), ("Bernard", 1000)],
+                    vec![("Martin", 1000), ("Petit", 0)],
+                    vec![("Robert", 0)],
+                ]
+            );
+
+            let leaderboard = conn.get_fine_leaderboard(team_id, None).unwrap();
+            assert_eq!(leaderboard.items.len(), 5);
+            assert_eq!(leaderboard.total, 5);
 
             Ok(())
         });
     }
 
     #[test]
-    fn test_delete_sanction_fails() {
+    fn test_query_sanctions() {
         let conn = init_connection();
 
-        let error = conn
-            .delete_sanction(Uuid::new_v4(), Uuid::new_v4())
-            .unwrap_err();
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&team_with_rule(), &unique_admin())
+                .unwrap()
+                .id;
+            let user_ids: Vec<Uuid> = (0..2)
+                .map(|_| {
+                    conn.create_user(&User {
+                        id: Uuid::new_v4(),
+                        team_id,
+                        ..Default::default()
+                    })
+                    .unwrap()
+                    .id
+                })
+                .collect();
 
-        assert_eq!(error, DbError::NotFound);
+            let sanctions: Vec<CreateSanction> = user_ids
+                .iter()
+                .flat_map(|user_id| {
+                    (1..=6).map(move |day| CreateSanction {
+                        id: Uuid::new_v4(),
+                        user_id: *user_id,
+                        team_id,
+                        created_at: Some(start_of_day(NaiveDate::from_ymd(2021, 3, day))),
+                        ..Default::default()
+                    })
+                })
+                .collect();
+            conn.create_sanctions(&sanctions).unwrap();
+
+            let query = SanctionQuery {
+                user_id: Some(user_ids[0]),
+                from: Some(NaiveDate::from_ymd(2021, 3, 2)),
+                to: Some(NaiveDate::from_ymd(2021, 3, 5)),
+                order: SanctionOrder::OldestFirst,
+                limit: Some(2),
+                offset: Some(2),
+                ..Default::default()
+            };
+            let page = conn.query_sanctions(team_id, query.clone()).unwrap();
+
+            assert_eq!(page.total, 4);
+            assert_eq!((page.limit, page.offset), (2, 2));
+            assert_eq!(
+                page.items
+                    .iter()
+                    .map(|sanction| sanction.created_at)
+                    .collect::<Vec<DateTime<Utc>>>(),
+                vec![
+                    start_of_day(NaiveDate::from_ymd(2021, 3, 4)),
+                    start_of_day(NaiveDate::from_ymd(2021, 3, 5)),
+                ]
+            );
+            assert!(page
+                .items
+                .iter()
+                .all(|sanction| sanction.user_id == user_ids[0]));
+
+            match conn
+                .query_sanctions(
+                    team_id,
+                    SanctionQuery {
+                        from: query.to,
+                        to: query.from,
+                        ..query
+                    },
+                )
+                .unwrap_err()
+            {
+                DbError::NotValid(_) => {}
+                err => panic!("Unexpected error {:?}", err),
+            }
+
+            Ok(())
+        });
     }
 }
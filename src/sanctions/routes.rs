@@ -1,23 +1,32 @@
-use rouille::{input::json::json_input, router, Request};
+use rouille::{router, Request};
 use serde::Serialize;
 use std::collections::HashMap;
 use uuid::Uuid;
 
 use super::{
     interface::SanctionsDb,
-    models::{CreateSanction, Sanction, UpdateSanctionRequest},
+    models::{
+        CreateSanction, FormattedSanction, LeaderboardEntry, SanctionInfo, SanctionResponse,
+        UpdateSanctionRequest,
+    },
     utils::{formatter::map_by_users, parameters::ParametersHandler},
 };
-use crate::api::models::ErrorResponse;
-use crate::database::postgres::DbError;
+use crate::api::{
+    models::{ErrorResponse, NotFoundAs, Resource},
+    utils::{parse_json_body, parse_pagination, parse_uuid},
+};
+use crate::database::{pagination::Paginated, postgres::DbError};
 use crate::teams::interface::TeamsDb;
 
 #[derive(Serialize, Debug)]
 #[serde(untagged)]
 pub enum ResultWrapper {
-    Sanctions(Vec<Sanction>),
-    MappedSanctions(HashMap<Uuid, Vec<Sanction>>),
-    Sanction(Sanction),
+    Sanctions(Vec<SanctionResponse>),
+    MappedSanctions(HashMap<Uuid, Vec<SanctionResponse>>),
+    FormattedSanctions(Vec<FormattedSanction>),
+    MappedFormattedSanctions(HashMap<Uuid, Vec<FormattedSanction>>),
+    Sanction(Box<SanctionResponse>),
+    Leaderboard(Paginated<LeaderboardEntry>),
 }
 
 pub fn handle_request<T>(request: &Request, db: &T) -> Result<ResultWrapper, ErrorResponse>
@@ -25,40 +34,85 @@ where
     T: SanctionsDb + TeamsDb,
 {
     router!(request,
-        (GET) (/teams/{team_id: Uuid}/sanctions) => {
-            let parameters_handler = ParametersHandler::from_request(request)?;
+        (GET) (/teams/{team_id: String}/sanctions) => {
+            let team_id = parse_uuid(&team_id)?;
 
-            let result = db.get_sanctions(team_id, parameters_handler.date_interval())?;
+            let parameters_handler = ParametersHandler::from_request(request)?;
 
-            if parameters_handler.must_be_formatted() {
-                Ok(ResultWrapper::MappedSanctions(map_by_users(result)))
-            } else {
-                Ok(ResultWrapper::Sanctions(result))
+            let result = db.get_sanctions(
+                team_id,
+                parameters_handler.date_interval(),
+                &parameters_handler.sanction_filter(),
+            )?;
+
+            match parameters_handler.amount_format() {
+                Some((currency, locale)) => {
+                    let result: Vec<FormattedSanction> = result
+                        .into_iter()
+                        .map(|sanction| (sanction, currency, locale).into())
+                        .collect();
+
+                    if parameters_handler.must_be_formatted() {
+                        Ok(ResultWrapper::MappedFormattedSanctions(map_by_users(result)))
+                    } else {
+                        Ok(ResultWrapper::FormattedSanctions(result))
+                    }
+                }
+                None => {
+                    let result: Vec<SanctionResponse> =
+                        result.into_iter().map(SanctionResponse::from).collect();
+
+                    if parameters_handler.must_be_formatted() {
+                        Ok(ResultWrapper::MappedSanctions(map_by_users(result)))
+                    } else {
+                        Ok(ResultWrapper::Sanctions(result))
+                    }
+                }
             }
         },
-        (POST) (/teams/{team_id: Uuid}/sanctions) => {
-            let input = json_input::<Vec<UpdateSanctionRequest>>(request)?;
+        (POST) (/teams/{team_id: String}/sanctions) => {
+            let team_id = parse_uuid(&team_id)?;
+
+            let parameters_handler = ParametersHandler::from_request(request)?;
+            let input = parse_json_body::<Vec<UpdateSanctionRequest>>(request)?;
 
             let mut error : Option<ErrorResponse> = None;
             let mut sanctions: Vec<CreateSanction> = vec![];
 
             input.into_iter().map(|update_sanction| {
-                let rule = db
+                update_sanction.validate()?;
+
+                let team = db
                     .get_team(team_id)
                     .map_err(|err| match err {
                         DbError::NotFound => {
                             DbError::ForeignKeyViolation(String::from("The key team_id doesn't refer to anything"))
                         }
                         _ => err,
-                    })?
-                    .get_rule(update_sanction.sanction_info.associated_rule)
-                    .ok_or_else(|| DbError::ForeignKeyViolation(String::from(
-                            "The key associated_rule doesn't refer to anything",
+                    })?;
+
+                let associated_rule = update_sanction
+                    .sanction_info
+                    .associated_rule
+                    .or(team.default_rule_id)
+                    .ok_or_else(|| DbError::NotValid(String::from(
+                            "The field associated_rule must be set when the team has no default rule",
+                    )))?;
+
+                let rule = team
+                    .get_rule(associated_rule)
+                    .ok_or_else(|| DbError::NotValid(format!(
+                            "The rule {} doesn't belong to the team {}",
+                            associated_rule, team_id,
                     )))?;
 
-                let price = update_sanction.sanction_info.get_price(rule)?;
+                let sanction_info = SanctionInfo {
+                    associated_rule,
+                    extra_info: update_sanction.sanction_info.extra_info.clone(),
+                };
+                let price = sanction_info.get_price(rule)?;
 
-                let sanction: CreateSanction = (update_sanction, team_id, price).into();
+                let sanction: CreateSanction = (update_sanction, team_id, sanction_info, price).into();
 
                 Ok(sanction)
             })
@@ -73,15 +127,36 @@ where
             match error {
                 Some(err)=>Err(err),
                 None=> {
-                    let result = db.create_sanctions(&sanctions)?;
-                    Ok(ResultWrapper::Sanctions(result))
+                    let result = if parameters_handler.override_leave() {
+                        db.create_sanctions_overriding_leave(&sanctions)?
+                    } else {
+                        db.create_sanctions(&sanctions)?
+                    };
+                    Ok(ResultWrapper::Sanctions(
+                        result.into_iter().map(SanctionResponse::from).collect(),
+                    ))
                 }
             }
         },
-        (DELETE) (/teams/{team_id: Uuid}/sanctions/{sanction_id: Uuid}) => {
-            let result = db.delete_sanction(team_id, sanction_id)?;
+        (DELETE) (/teams/{team_id: String}/sanctions/{sanction_id: String}) => {
+            let team_id = parse_uuid(&team_id)?;
+            let sanction_id = parse_uuid(&sanction_id)?;
 
-            Ok(ResultWrapper::Sanction(result))
+            let result = db
+                .delete_sanction(team_id, sanction_id, request.header("X-Actor"))
+                .not_found_as(Resource::Sanction)?;
+
+            Ok(ResultWrapper::Sanction(Box::new(result.into())))
+        },
+        (GET) (/teams/{team_id: String}/leaderboard) => {
+            let team_id = parse_uuid(&team_id)?;
+
+            let pagination = parse_pagination(request)?;
+            let result = db
+                .get_fine_leaderboard(team_id, pagination.as_ref())
+                .not_found_as(Resource::Team)?;
+
+            Ok(ResultWrapper::Leaderboard(result.map(LeaderboardEntry::from)))
         },
         _ => {
             Err(ErrorResponse::not_found())
@@ -91,13 +166,18 @@ where
 
 #[cfg(test)]
 mod tests {
-    use chrono::{naive::NaiveDate, Local};
+    use chrono::{naive::NaiveDate, Duration, Local, Utc};
     use serde_json::json;
 
     use super::*;
     use crate::api::models::{test_utils::RequestBuilder, ErrorKind};
-    use crate::teams::models::{Rule, RuleKind};
-    use crate::test_utils::routes::{DbMock, SanctionsDbMock, TeamsDbMock};
+    use crate::database::amount::Amount;
+    use crate::teams::models::{Rule, RuleKind, Team};
+    use crate::test_utils::{
+        memory::MemoryDb,
+        routes::{DbMock, SanctionsDbMock, TeamsDbMock},
+    };
+    use crate::users::models::User;
 
     #[test]
     fn test_get_sanctions() {
@@ -110,7 +190,7 @@ mod tests {
         .unwrap());
 
         for i in 0..3 {
-            assert_eq!(response[i]["team_id"], json!(team_id));
+            assert_eq!(response[i]["teamId"], json!(team_id));
         }
     }
 
@@ -140,6 +220,23 @@ mod tests {
         assert_eq!(response.as_object().unwrap().len(), 3);
     }
 
+    #[test]
+    fn test_get_sanctions_with_formatted_amounts() {
+        let team_id = Uuid::new_v4();
+
+        let response = json!(handle_request(
+            &RequestBuilder::get(format!(
+                "/teams/{}/sanctions?locale=en-US&currency=USD",
+                team_id
+            )),
+            &DbMock::default(),
+        )
+        .unwrap());
+
+        assert_eq!(response[0]["price"], json!(0.0));
+        assert_eq!(response[0]["formattedPrice"], json!("$0.00"));
+    }
+
     #[test]
     fn test_get_sanctions_with_uncorrect_parameters() {
         let team_id = Uuid::new_v4();
@@ -188,7 +285,7 @@ mod tests {
 
         let rule_1 = Rule {
             kind: RuleKind::Multiplication {
-                price_to_multiply: 3.5,
+                price_to_multiply: Amount::from_cents(350),
             },
             ..Default::default()
         };
@@ -230,11 +327,180 @@ mod tests {
         )
         .unwrap());
 
-        assert_eq!(response[0]["team_id"], json!(team_id));
-        assert_eq!(response[0]["user_id"], sanctions[0]["user_id"]);
+        assert_eq!(response[0]["teamId"], json!(team_id));
+        assert_eq!(response[0]["userId"], sanctions[0]["user_id"]);
         assert_eq!(response[0]["price"], json!(7.0));
-        assert_eq!(response[0]["created_at"], json!(Local::today().naive_utc()));
-        assert_eq!(response[1]["created_at"], json!(created_at));
+        assert!(response[0]["createdAt"]
+            .as_str()
+            .unwrap()
+            .starts_with(&Utc::today().naive_utc().to_string()));
+        assert_eq!(response[1]["createdAt"], json!("2019-10-16T00:00:00Z"));
+    }
+
+    #[test]
+    fn test_create_percentage_sanction() {
+        let team_id = Uuid::new_v4();
+
+        let rule = Rule {
+            kind: RuleKind::Percentage { rate: 0.1 },
+            ..Default::default()
+        };
+
+        let sanctions = json!([{
+            "user_id": Uuid::new_v4(),
+            "sanction_info": {
+                "associated_rule": rule.id,
+                "extra_info": {
+                    "type": "PERCENTAGE",
+                    "base": 100.0
+                }
+            }
+        }]);
+
+        let response = json!(handle_request(
+            &RequestBuilder::post(format!("/teams/{}/sanctions", team_id), &sanctions),
+            &DbMock {
+                teams_db: TeamsDbMock::SuccessWithRules(vec![rule]),
+                ..Default::default()
+            },
+        )
+        .unwrap());
+
+        assert_eq!(response[0]["price"], json!(10.0));
+    }
+
+    #[test]
+    fn test_create_sanction_with_default_rule() {
+        let team_id = Uuid::new_v4();
+
+        let rule = Rule {
+            kind: RuleKind::Basic {
+                price: Amount::from_cents(250),
+            },
+            ..Default::default()
+        };
+
+        let sanctions = json!([{
+            "user_id": Uuid::new_v4(),
+            "sanction_info": {
+                "extra_info": {
+                    "type": "NONE"
+                }
+            }
+        }]);
+
+        let response = json!(handle_request(
+            &RequestBuilder::post(format!("/teams/{}/sanctions", team_id), &sanctions),
+            &DbMock {
+                teams_db: TeamsDbMock::SuccessWithDefaultRule(rule.clone()),
+                ..Default::default()
+            },
+        )
+        .unwrap());
+
+        assert_eq!(
+            response[0]["sanctionInfo"]["associated_rule"],
+            json!(rule.id)
+        );
+        assert_eq!(response[0]["price"], json!(2.5));
+
+        let error = handle_request(
+            &RequestBuilder::post(format!("/teams/{}/sanctions", team_id), &sanctions),
+            &DbMock {
+                teams_db: TeamsDbMock::SuccessWithRules(vec![rule]),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::NotValid);
+        assert_eq!(
+            error.description,
+            "The field associated_rule must be set when the team has no default rule"
+        );
+    }
+
+    #[test]
+    fn test_create_future_dated_sanction_fails() {
+        let team_id = Uuid::new_v4();
+        let rule = Rule::default();
+
+        let sanctions = json!([{
+            "user_id": Uuid::new_v4(),
+            "sanction_info": {
+                "associated_rule": rule.id,
+                "extra_info": {
+                    "type": "NONE"
+                }
+            },
+            "created_at": Local::today().naive_local() + Duration::days(1)
+        }]);
+
+        let error = handle_request(
+            &RequestBuilder::post(format!("/teams/{}/sanctions", team_id), &sanctions),
+            &DbMock {
+                teams_db: TeamsDbMock::SuccessWithRules(vec![rule]),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::NotValid);
+        assert_eq!(
+            error.description,
+            "The field created_at must not be in the future"
+        );
+    }
+
+    #[test]
+    fn test_create_paid_sanction() {
+        let rule = Rule::default();
+        let team = Team {
+            rules: vec![rule.clone()],
+            ..Default::default()
+        };
+        let user = User {
+            team_id: team.id,
+            ..Default::default()
+        };
+        let db = MemoryDb::default();
+        db.teams.borrow_mut().push(team.clone());
+        db.users.borrow_mut().push(user.clone());
+
+        let sanction = |paid_at: &str| {
+            json!([{
+                "user_id": user.id,
+                "sanction_info": {
+                    "associated_rule": rule.id,
+                    "extra_info": {
+                        "type": "NONE"
+                    }
+                },
+                "created_at": "2019-10-16",
+                "paid_at": paid_at
+            }])
+        };
+        let sanctions_url = format!("/teams/{}/sanctions", team.id);
+
+        let response = json!(handle_request(
+            &RequestBuilder::post(sanctions_url.clone(), &sanction("2019-10-18")),
+            &db,
+        )
+        .unwrap());
+
+        assert_eq!(response[0]["paidAt"], json!("2019-10-18"));
+
+        let error = handle_request(
+            &RequestBuilder::post(sanctions_url, &sanction("2019-10-15")),
+            &db,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::NotValid);
+        assert_eq!(
+            error.description,
+            "The field paid_at must not be before created_at"
+        );
     }
 
     #[test]
@@ -243,7 +509,7 @@ mod tests {
 
         let rule = Rule {
             kind: RuleKind::Multiplication {
-                price_to_multiply: 3.5,
+                price_to_multiply: Amount::from_cents(350),
             },
             ..Default::default()
         };
@@ -264,7 +530,14 @@ mod tests {
         )
         .unwrap_err();
 
-        assert_eq!(error.kind, ErrorKind::BadReference);
+        assert_eq!(error.kind, ErrorKind::NotValid);
+        assert_eq!(
+            error.description,
+            format!(
+                "The rule {} doesn't belong to the team {}",
+                rule.id, team_id
+            )
+        );
 
         let error = handle_request(
             &RequestBuilder::post(format!("/teams/{}/sanctions", team_id), &sanction),
@@ -288,6 +561,73 @@ mod tests {
         assert_eq!(error.kind, ErrorKind::Json);
     }
 
+    #[test]
+    fn test_create_sanction_for_user_on_leave() {
+        let rule = Rule {
+            kind: RuleKind::Basic {
+                price: Amount::from_cents(250),
+            },
+            ..Default::default()
+        };
+        let team = Team {
+            rules: vec![rule.clone()],
+            ..Default::default()
+        };
+        let user = User {
+            team_id: team.id,
+            on_leave_until: Some(NaiveDate::from_ymd(2019, 10, 20)),
+            ..Default::default()
+        };
+        let db = MemoryDb::default();
+        db.teams.borrow_mut().push(team.clone());
+        db.users.borrow_mut().push(user.clone());
+
+        let sanction = |created_at: &str| {
+            json!([{
+                "user_id": user.id,
+                "sanction_info": {
+                    "associated_rule": rule.id,
+                    "extra_info": {
+                        "type": "NONE"
+                    }
+                },
+                "created_at": created_at
+            }])
+        };
+        let sanctions_url = format!("/teams/{}/sanctions", team.id);
+
+        let error = handle_request(
+            &RequestBuilder::post(sanctions_url.clone(), &sanction("2019-10-20")),
+            &db,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::Forbidden);
+        assert_eq!(
+            error.description,
+            format!("The user {} is on leave until 2019-10-20", user.id)
+        );
+
+        let response = json!(handle_request(
+            &RequestBuilder::post(sanctions_url.clone(), &sanction("2019-10-21")),
+            &db,
+        )
+        .unwrap());
+
+        assert_eq!(response[0]["createdAt"], json!("2019-10-21T00:00:00Z"));
+
+        let response = json!(handle_request(
+            &RequestBuilder::post(
+                format!("{}?override_leave=true", sanctions_url),
+                &sanction("2019-10-20")
+            ),
+            &db,
+        )
+        .unwrap());
+
+        assert_eq!(response[0]["createdAt"], json!("2019-10-20T00:00:00Z"));
+    }
+
     #[test]
     fn test_delete_sanction() {
         let team_id = Uuid::new_v4();
@@ -299,7 +639,7 @@ mod tests {
         )
         .unwrap());
 
-        assert_eq!(response["team_id"], json!(team_id));
+        assert_eq!(response["teamId"], json!(team_id));
         assert_eq!(response["id"], json!(sanction_id));
     }
 
@@ -318,5 +658,32 @@ mod tests {
         .unwrap_err();
 
         assert_eq!(error.kind, ErrorKind::NotFound);
+        assert_eq!(error.description, "Sanction not found");
+    }
+
+    #[test]
+    fn test_malformed_sanction_id() {
+        let error = handle_request(
+            &RequestBuilder::delete(format!("/teams/{}/sanctions/not-an-id", Uuid::new_v4())),
+            &DbMock::default(),
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::BadRequest);
+    }
+
+    #[test]
+    fn test_get_leaderboard() {
+        let response = json!(handle_request(
+            &RequestBuilder::get(format!(
+                "/teams/{}/leaderboard?limit=2&offset=4",
+                Uuid::new_v4()
+            )),
+            &DbMock::default(),
+        )
+        .unwrap());
+
+        assert_eq!(response["limit"], json!(2));
+        assert_eq!(response["offset"], json!(4));
     }
 }
@@ -2,6 +2,8 @@ pub mod parameters {
     use chrono::NaiveDate;
     use rouille::Request;
 
+    use super::super::models::SanctionFilter;
+
     #[derive(Debug)]
     pub struct ParameterError {
         pub parameter_name: String,
@@ -26,19 +28,55 @@ pub mod parameters {
     pub struct ParametersHandler {
         format: Option<FormatParameter>,
         year_and_month: Option<(YearParameter, MonthParameter)>,
+        locale_and_currency: Option<(LocaleParameter, CurrencyParameter)>,
+        include_paid: Option<IncludePaidParameter>,
+        include_waived: Option<IncludeWaivedParameter>,
+        season: Option<SeasonParameter>,
+        override_leave: Option<OverrideLeaveParameter>,
     }
 
     impl ParametersHandler {
         pub fn from_request(request: &Request) -> Result<ParametersHandler, ParameterError> {
             let format = FormatParameter::from_request(request)?;
             let year_and_month = Self::extract_year_and_month(request)?;
+            let locale_and_currency = Self::extract_locale_and_currency(request)?;
+            let include_paid = IncludePaidParameter::from_request(request)?;
+            let include_waived = IncludeWaivedParameter::from_request(request)?;
+            let season = SeasonParameter::from_request(request)?;
+            let override_leave = OverrideLeaveParameter::from_request(request)?;
 
             Ok(ParametersHandler {
                 format,
                 year_and_month,
+                locale_and_currency,
+                include_paid,
+                include_waived,
+                season,
+                override_leave,
             })
         }
 
+        fn extract_locale_and_currency(
+            request: &Request,
+        ) -> Result<Option<(LocaleParameter, CurrencyParameter)>, ParameterError> {
+            match (
+                LocaleParameter::from_request(request)?,
+                CurrencyParameter::from_request(request)?,
+            ) {
+                (None, Some(_)) => Err(ParameterError {
+                    parameter_name: CurrencyParameter::parameter_name(),
+                    kind: ParameterErrorKind::UnvalidCombination {
+                        missing_parameters: vec![LocaleParameter::parameter_name()],
+                    },
+                }),
+                (None, None) => Ok(None),
+                (Some(locale), currency) => Ok(Some((
+                    locale,
+                    currency.unwrap_or_else(|| CurrencyParameter(String::from("EUR"))),
+                ))),
+            }
+        }
+
         fn extract_year_and_month(
             request: &Request,
         ) -> Result<Option<(YearParameter, MonthParameter)>, ParameterError> {
@@ -78,12 +116,43 @@ pub mod parameters {
             }
         }
 
+        pub fn amount_format(&self) -> Option<(&str, &str)> {
+            match &self.locale_and_currency {
+                Some((locale, currency)) => Some((&currency.0, &locale.0)),
+                None => None,
+            }
+        }
+
+        pub fn sanction_filter(&self) -> SanctionFilter {
+            let default_filter = SanctionFilter::default();
+
+            SanctionFilter {
+                include_paid: match self.include_paid {
+                    Some(IncludePaidParameter(include_paid)) => include_paid,
+                    None => default_filter.include_paid,
+                },
+                include_waived: match self.include_waived {
+                    Some(IncludeWaivedParameter(include_waived)) => include_waived,
+                    None => default_filter.include_waived,
+                },
+                season: match &self.season {
+                    Some(SeasonParameter(season)) => Some(season.clone()),
+                    None => default_filter.season,
+                },
+                ..default_filter
+            }
+        }
+
         pub fn must_be_formatted(&self) -> bool {
             match self.format {
                 Some(FormatParameter(true)) => true,
                 _ => false,
             }
         }
+
+        pub fn override_leave(&self) -> bool {
+            matches!(self.override_leave, Some(OverrideLeaveParameter(true)))
+        }
     }
 
     trait Parameter<T> {
@@ -184,6 +253,142 @@ pub mod parameters {
         }
     }
 
+    #[derive(Debug)]
+    struct IncludePaidParameter(bool);
+
+    impl Parameter<Self> for IncludePaidParameter {
+        fn from_string(string: &str) -> Result<Self, ParameterError> {
+            match string.parse::<bool>() {
+                Ok(boolean) => Ok(IncludePaidParameter(boolean)),
+                Err(_) => Err(ParameterError {
+                    parameter_name: Self::parameter_name(),
+                    kind: ParameterErrorKind::UnvalidType {
+                        expected_type: String::from("boolean"),
+                    },
+                }),
+            }
+        }
+
+        fn parameter_name() -> String {
+            String::from("include_paid")
+        }
+    }
+
+    #[derive(Debug)]
+    struct IncludeWaivedParameter(bool);
+
+    impl Parameter<Self> for IncludeWaivedParameter {
+        fn from_string(string: &str) -> Result<Self, ParameterError> {
+            match string.parse::<bool>() {
+                Ok(boolean) => Ok(IncludeWaivedParameter(boolean)),
+                Err(_) => Err(ParameterError {
+                    parameter_name: Self::parameter_name(),
+                    kind: ParameterErrorKind::UnvalidType {
+                        expected_type: String::from("boolean"),
+                    },
+                }),
+            }
+        }
+
+        fn parameter_name() -> String {
+            String::from("include_waived")
+        }
+    }
+
+    #[derive(Debug)]
+    struct OverrideLeaveParameter(bool);
+
+    impl Parameter<Self> for OverrideLeaveParameter {
+        fn from_string(string: &str) -> Result<Self, ParameterError> {
+            match string.parse::<bool>() {
+                Ok(boolean) => Ok(OverrideLeaveParameter(boolean)),
+                Err(_) => Err(ParameterError {
+                    parameter_name: Self::parameter_name(),
+                    kind: ParameterErrorKind::UnvalidType {
+                        expected_type: String::from("boolean"),
+                    },
+                }),
+            }
+        }
+
+        fn parameter_name() -> String {
+            String::from("override_leave")
+        }
+    }
+
+    #[derive(Debug)]
+    struct SeasonParameter(String);
+
+    impl Parameter<Self> for SeasonParameter {
+        fn from_string(string: &str) -> Result<Self, ParameterError> {
+            if string.is_empty() {
+                Err(ParameterError {
+                    parameter_name: Self::parameter_name(),
+                    kind: ParameterErrorKind::UnvalidValue {
+                        parameter_value: String::from(string),
+                        reason: String::from("This value must not be empty"),
+                    },
+                })
+            } else {
+                Ok(SeasonParameter(String::from(string)))
+            }
+        }
+
+        fn parameter_name() -> String {
+            String::from("season")
+        }
+    }
+
+    #[derive(Debug)]
+    struct LocaleParameter(String);
+
+    impl Parameter<Self> for LocaleParameter {
+        fn from_string(string: &str) -> Result<Self, ParameterError> {
+            if !string.is_empty()
+                && string
+                    .split('-')
+                    .all(|part| !part.is_empty() && part.chars().all(char::is_alphabetic))
+            {
+                Ok(LocaleParameter(String::from(string)))
+            } else {
+                Err(ParameterError {
+                    parameter_name: Self::parameter_name(),
+                    kind: ParameterErrorKind::UnvalidValue {
+                        parameter_value: String::from(string),
+                        reason: String::from("This value must be a locale such as fr-FR"),
+                    },
+                })
+            }
+        }
+
+        fn parameter_name() -> String {
+            String::from("locale")
+        }
+    }
+
+    #[derive(Debug)]
+    struct CurrencyParameter(String);
+
+    impl Parameter<Self> for CurrencyParameter {
+        fn from_string(string: &str) -> Result<Self, ParameterError> {
+            if string.len() == 3 && string.chars().all(|c| c.is_ascii_uppercase()) {
+                Ok(CurrencyParameter(String::from(string)))
+            } else {
+                Err(ParameterError {
+                    parameter_name: Self::parameter_name(),
+                    kind: ParameterErrorKind::UnvalidValue {
+                        parameter_value: String::from(string),
+                        reason: String::from("This value must be a currency code such as EUR"),
+                    },
+                })
+            }
+        }
+
+        fn parameter_name() -> String {
+            String::from("currency")
+        }
+    }
+
     #[cfg(test)]
     mod tests {
         use super::*;
@@ -221,6 +426,51 @@ pub mod parameters {
             );
         }
 
+        #[test]
+        fn test_sanction_filter() {
+            let request = RequestBuilder::get(String::from("/root_url"));
+
+            let param_handler = ParametersHandler::from_request(&request).unwrap();
+
+            assert_eq!(param_handler.sanction_filter(), SanctionFilter::default());
+
+            let request = RequestBuilder::get(String::from(
+                "/root_url?include_paid=false&include_waived=true",
+            ));
+
+            let param_handler = ParametersHandler::from_request(&request).unwrap();
+
+            assert_eq!(
+                param_handler.sanction_filter(),
+                SanctionFilter {
+                    include_paid: false,
+                    include_waived: true,
+                    ..Default::default()
+                }
+            );
+        }
+
+        #[test]
+        fn test_amount_format() {
+            let request = RequestBuilder::get(String::from("/root_url?locale=fr-FR"));
+
+            let param_handler = ParametersHandler::from_request(&request).unwrap();
+
+            assert_eq!(param_handler.amount_format(), Some(("EUR", "fr-FR")));
+
+            let request = RequestBuilder::get(String::from("/root_url?currency=USD"));
+
+            let error = ParametersHandler::from_request(&request).unwrap_err();
+
+            assert_eq!(error.parameter_name, String::from("currency"));
+            assert_eq!(
+                error.kind,
+                ParameterErrorKind::UnvalidCombination {
+                    missing_parameters: vec![String::from("locale")]
+                }
+            );
+        }
+
         #[test]
         fn test_parameter_handler_returns_unvalid_type_error() {
             let request = RequestBuilder::get(String::from("/root_url?format=t"));
@@ -287,26 +537,99 @@ pub mod formatter {
     use std::collections::HashMap;
     use uuid::Uuid;
 
-    use super::super::models::Sanction;
+    use super::super::models::{FormattedSanction, Sanction, SanctionResponse};
+    use crate::database::amount::Amount;
+
+    pub trait UserSanction {
+        fn user_id(&self) -> Uuid;
+    }
 
-    pub fn map_by_users(result: Vec<Sanction>) -> HashMap<Uuid, Vec<Sanction>> {
+    impl UserSanction for Sanction {
+        fn user_id(&self) -> Uuid {
+            self.user_id
+        }
+    }
+
+    impl UserSanction for SanctionResponse {
+        fn user_id(&self) -> Uuid {
+            self.user_id
+        }
+    }
+
+    impl UserSanction for FormattedSanction {
+        fn user_id(&self) -> Uuid {
+            self.sanction.user_id
+        }
+    }
+
+    pub fn map_by_users<T>(result: Vec<T>) -> HashMap<Uuid, Vec<T>>
+    where
+        T: UserSanction + Clone,
+    {
         let mut mapped_result = HashMap::new();
 
         result.into_iter().for_each(|sanction| {
             mapped_result
-                .entry(sanction.user_id)
-                .and_modify(|v: &mut Vec<Sanction>| v.push(sanction.clone()))
+                .entry(sanction.user_id())
+                .and_modify(|v: &mut Vec<T>| v.push(sanction.clone()))
                 .or_insert_with(|| vec![sanction]);
         });
 
         mapped_result
     }
 
+    pub fn format_amount(amount: Amount, currency: &str, locale: &str) -> String {
+        let symbol = currency_symbol(currency);
+        let sign = if amount.cents() < 0 { "-" } else { "" };
+        let units = (amount.cents().abs() / 100).to_string();
+        let cents = amount.cents().abs() % 100;
+
+        match locale.split('-').next() {
+            Some("fr") | Some("de") | Some("es") | Some("it") => format!(
+                "{}{},{:02} {}",
+                sign,
+                group_thousands(&units, " "),
+                cents,
+                symbol
+            ),
+            _ => format!(
+                "{}{}{}.{:02}",
+                sign,
+                symbol,
+                group_thousands(&units, ","),
+                cents
+            ),
+        }
+    }
+
+    fn currency_symbol(currency: &str) -> String {
+        match currency {
+            "EUR" => String::from("€"),
+            "USD" => String::from("$"),
+            "GBP" => String::from("£"),
+            _ => String::from(currency),
+        }
+    }
+
+    fn group_thousands(units: &str, separator: &str) -> String {
+        let mut groups: Vec<&str> = vec![];
+        let mut end = units.len();
+
+        while end > 3 {
+            groups.push(&units[end - 3..end]);
+            end -= 3;
+        }
+        groups.push(&units[..end]);
+        groups.reverse();
+
+        groups.join(separator)
+    }
+
     #[cfg(test)]
     mod tests {
-        use chrono::naive::NaiveDate;
+        use chrono::{naive::NaiveDate, TimeZone, Utc};
 
-        use super::super::super::models::{ExtraInfo, SanctionInfo};
+        use super::super::super::models::{start_of_day, ExtraInfo, SanctionInfo};
         use super::*;
 
         fn create_default_sanction(user_id: Uuid) -> Sanction {
@@ -318,8 +641,19 @@ pub mod formatter {
                     associated_rule: Uuid::new_v4(),
                     extra_info: ExtraInfo::None,
                 },
-                price: 0.0,
-                created_at: NaiveDate::from_ymd(2019, 10, 5),
+                price: Default::default(),
+                created_at: start_of_day(NaiveDate::from_ymd(2019, 10, 5)),
+                paid_at: None,
+                waived_at: None,
+                season: None,
+                archived_at: None,
+                disputed: false,
+                dispute_note: None,
+                recorded_at: Utc.ymd(2019, 10, 5).and_hms(0, 0, 0),
+                created_by: None,
+                paid_by: None,
+                waive_reason: None,
+                updated_at: Utc.ymd(2019, 10, 5).and_hms(0, 0, 0),
             }
         }
 
@@ -341,5 +675,453 @@ pub mod formatter {
             assert_eq!(mapped_users.get(&user_1).unwrap().len(), 3);
             assert_eq!(mapped_users.get(&user_2).unwrap().len(), 2);
         }
+
+        #[test]
+        fn test_format_amount() {
+            assert_eq!(
+                format_amount(Amount::from_cents(250), "EUR", "fr-FR"),
+                String::from("2,50 €")
+            );
+            assert_eq!(
+                format_amount(Amount::from_cents(250), "EUR", "en-US"),
+                String::from("€2.50")
+            );
+            assert_eq!(
+                format_amount(Amount::from_cents(-123_450), "USD", "en-US"),
+                String::from("-$1,234.50")
+            );
+        }
+    }
+}
+
+pub mod calendar {
+    use chrono::{DateTime, Utc};
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    use super::super::{
+        interface::SanctionsDb,
+        models::{Sanction, SanctionFilter},
+    };
+    use crate::database::postgres::DbError;
+    use crate::teams::interface::TeamsDb;
+    use crate::users::interface::UsersDb;
+
+    const MAX_LINE_LENGTH: usize = 75;
+
+    pub fn escape_text(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+
+        for c in text.chars() {
+            match c {
+                '\\' => escaped.push_str("\\\\"),
+                ';' => escaped.push_str("\\;"),
+                ',' => escaped.push_str("\\,"),
+                '\n' => escaped.push_str("\\n"),
+                '\r' => {}
+                _ => escaped.push(c),
+            }
+        }
+
+        escaped
+    }
+
+    fn fold_line(line: &str) -> String {
+        let mut folded = String::with_capacity(line.len());
+        let mut length = 0;
+
+        for c in line.chars() {
+            if length + c.len_utf8() > MAX_LINE_LENGTH {
+                folded.push_str("\r\n ");
+                length = 1;
+            }
+            folded.push(c);
+            length += c.len_utf8();
+        }
+        folded.push_str("\r\n");
+
+        folded
+    }
+
+    fn format_timestamp(timestamp: DateTime<Utc>) -> String {
+        timestamp.format("%Y%m%dT%H%M%SZ").to_string()
+    }
+
+    pub fn sanctions_to_ics(sanctions: &[(Sanction, String, String)]) -> String {
+        let mut lines: Vec<String> = vec![
+            String::from("BEGIN:VCALENDAR"),
+            String::from("VERSION:2.0"),
+            String::from("PRODID:-//caisse_noire//sanctions//EN"),
+        ];
+
+        for (sanction, user_name, rule_name) in sanctions {
+            lines.extend(vec![
+                String::from("BEGIN:VEVENT"),
+                format!("UID:{}@caisse_noire", sanction.id),
+                format!("DTSTAMP:{}", format_timestamp(sanction.recorded_at)),
+                format!(
+                    "DTSTART;VALUE=DATE:{}",
+                    sanction.created_on().format("%Y%m%d")
+                ),
+                format!(
+                    "SUMMARY:{}",
+                    escape_text(&format!(
+                        "{} - {} - {:.2}",
+                        user_name,
+                        rule_name,
+                        sanction.price.to_decimal()
+                    ))
+                ),
+                String::from("END:VEVENT"),
+            ]);
+        }
+        lines.push(String::from("END:VCALENDAR"));
+
+        lines.iter().map(|line| fold_line(line)).collect()
+    }
+
+    pub fn export_sanctions_ics<T>(db: &T, team_id: Uuid) -> Result<String, DbError>
+    where
+        T: TeamsDb + UsersDb + SanctionsDb,
+    {
+        let team = db.get_team(team_id)?;
+        let user_names: HashMap<Uuid, String> = db
+            .get_users(team_id)?
+            .into_iter()
+            .map(|user| (user.id, format!("{} {}", user.firstname, user.lastname)))
+            .collect();
+
+        let mut sanctions = db.get_sanctions(team_id, None, &SanctionFilter::default())?;
+        sanctions.sort_by_key(|sanction| (sanction.created_at, sanction.id));
+
+        let events: Vec<(Sanction, String, String)> = sanctions
+            .into_iter()
+            .map(|sanction| {
+                let user_name = user_names
+                    .get(&sanction.user_id)
+                    .cloned()
+                    .unwrap_or_default();
+                let rule_name = team
+                    .rules
+                    .iter()
+                    .find(|rule| rule.id == sanction.sanction_info.associated_rule)
+                    .map(|rule| rule.name.clone())
+                    .unwrap_or_else(|| String::from("Removed rule"));
+
+                (sanction, user_name, rule_name)
+            })
+            .collect();
+
+        Ok(sanctions_to_ics(&events))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use chrono::NaiveDate;
+        use diesel::{result::Error, Connection};
+        use std::ops::Deref;
+
+        use super::*;
+        use crate::database::amount::Amount;
+        use crate::sanctions::models::{start_of_day, CreateSanction, ExtraInfo, SanctionInfo};
+        use crate::teams::models::{Rule, Team};
+        use crate::test_utils::postgres::{init_connection, unique_admin};
+        use crate::users::models::User;
+
+        #[test]
+        fn test_escape_text() {
+            assert_eq!(
+                escape_text("Late; again, really\\\nyes"),
+                String::from("Late\\; again\\, really\\\\\\nyes")
+            );
+        }
+
+        #[test]
+        fn test_fold_line() {
+            let line = "é".repeat(50);
+            let folded = fold_line(&line);
+
+            assert!(folded
+                .split("\r\n")
+                .all(|folded_line| folded_line.len() <= MAX_LINE_LENGTH));
+            assert_eq!(folded.replace("\r\n ", ""), format!("{}\r\n", line));
+        }
+
+        #[test]
+        fn test_export_sanctions_ics() {
+            let conn = init_connection();
+
+            conn.deref().test_transaction::<_, Error, _>(|| {
+                let rule = Rule {
+                    id: Uuid::new_v4(),
+                    name: String::from("Late, again"),
+                    ..Default::default()
+                };
+                let team_id = conn
+                    .create_team(
+                        &Team {
+                            rules: vec![rule.clone()],
+                            ..Default::default()
+                        },
+                        &unique_admin(),
+                    )
+                    .unwrap()
+                    .id;
+                let user_id = conn
+                    .create_user(&User {
+                        team_id,
+                        firstname: String::from("John"),
+                        lastname: String::from("Doe"),
+                        ..Default::default()
+                    })
+                    .unwrap()
+                    .id;
+                let sanction = |date: NaiveDate| CreateSanction {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    team_id,
+                    sanction_info: SanctionInfo {
+                        associated_rule: rule.id,
+                        extra_info: ExtraInfo::None,
+                    },
+                    price: Amount::from_cents(250),
+                    created_at: Some(start_of_day(date)),
+                    ..Default::default()
+                };
+
+                conn.create_sanctions(&[
+                    sanction(NaiveDate::from_ymd(2019, 10, 5)),
+                    sanction(NaiveDate::from_ymd(2019, 10, 12)),
+                ])
+                .unwrap();
+
+                let ics = export_sanctions_ics(&conn, team_id).unwrap();
+                let lines: Vec<&str> = ics.split("\r\n").collect();
+
+                assert_eq!(lines.first(), Some(&"BEGIN:VCALENDAR"));
+                assert_eq!(
+                    lines.iter().filter(|line| **line == "BEGIN:VEVENT").count(),
+                    2
+                );
+                assert_eq!(
+                    lines.iter().filter(|line| **line == "END:VEVENT").count(),
+                    2
+                );
+                assert!(lines.contains(&"DTSTART;VALUE=DATE:20191005"));
+                assert!(lines.contains(&"SUMMARY:John Doe - Late\\, again - 2.50"));
+                assert_eq!(
+                    export_sanctions_ics(&conn, Uuid::new_v4()).unwrap_err(),
+                    DbError::NotFound
+                );
+
+                Ok(())
+            });
+        }
+    }
+}
+
+pub mod report {
+    use uuid::Uuid;
+
+    use super::super::{
+        interface::{SanctionsDb, SanctionsLedgerDb},
+        models::{Sanction, SanctionFilter},
+    };
+    use crate::database::{amount::Amount, postgres::DbError};
+    use crate::teams::interface::TeamsDb;
+    use crate::users::interface::UsersDb;
+
+    pub struct UserReport {
+        pub name: String,
+        pub fines: Vec<(Sanction, String)>,
+        pub balance: Amount,
+    }
+
+    pub fn escape_html(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+
+        for c in text.chars() {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                '\'' => escaped.push_str("&#39;"),
+                _ => escaped.push(c),
+            }
+        }
+
+        escaped
+    }
+
+    pub fn render_team_report(team_name: &str, users: &[UserReport]) -> String {
+        let team_name = escape_html(team_name);
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+             <style>table {{ border-collapse: collapse; margin-bottom: 1em; }} \
+             th, td {{ border: 1px solid black; padding: 0.2em 0.5em; }}</style>\n\
+             </head>\n<body>\n<h1>{}</h1>\n",
+            team_name, team_name
+        );
+
+        for user in users {
+            html.push_str(&format!(
+                "<h2>{}</h2>\n<table>\n<tr><th>Date</th><th>Rule</th><th>Amount</th><th>Paid</th></tr>\n",
+                escape_html(&user.name)
+            ));
+            for (sanction, rule_name) in &user.fines {
+                html.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td><td>{:.2}</td><td>{}</td></tr>\n",
+                    sanction.created_on().format("%Y-%m-%d"),
+                    escape_html(rule_name),
+                    sanction.price.to_decimal(),
+                    if sanction.paid_at.is_some() {
+                        "Yes"
+                    } else {
+                        "No"
+                    }
+                ));
+            }
+            html.push_str(&format!(
+                "<tr><th colspan=\"2\">Total owed</th><th colspan=\"2\">{:.2}</th></tr>\n</table>\n",
+                user.balance.to_decimal()
+            ));
+        }
+        html.push_str("</body>\n</html>\n");
+
+        html
+    }
+
+    pub fn render_team_report_html<T>(db: &T, team_id: Uuid) -> Result<String, DbError>
+    where
+        T: TeamsDb + UsersDb + SanctionsDb + SanctionsLedgerDb,
+    {
+        let team = db.get_team(team_id)?;
+
+        let mut users = db.get_users(team_id)?;
+        users.sort_by(|a, b| {
+            (&a.lastname, &a.firstname, a.id).cmp(&(&b.lastname, &b.firstname, b.id))
+        });
+        let user_ids: Vec<Uuid> = users.iter().map(|user| user.id).collect();
+        let balances = db.get_balances(team_id, &user_ids)?;
+
+        let mut sanctions: Vec<Sanction> = db
+            .get_sanctions(team_id, None, &SanctionFilter::default())?
+            .into_iter()
+            .filter(|sanction| sanction.archived_at.is_none())
+            .collect();
+        sanctions.sort_by_key(|sanction| (sanction.created_at, sanction.id));
+
+        let reports: Vec<UserReport> = users
+            .into_iter()
+            .map(|user| UserReport {
+                name: format!("{} {}", user.firstname, user.lastname),
+                fines: sanctions
+                    .iter()
+                    .filter(|sanction| sanction.user_id == user.id)
+                    .map(|sanction| {
+                        let rule_name = team
+                            .rules
+                            .iter()
+                            .find(|rule| rule.id == sanction.sanction_info.associated_rule)
+                            .map(|rule| rule.name.clone())
+                            .unwrap_or_else(|| String::from("Removed rule"));
+
+                        (sanction.clone(), rule_name)
+                    })
+                    .collect(),
+                balance: balances.get(&user.id).copied().unwrap_or_default(),
+            })
+            .collect();
+
+        Ok(render_team_report(&team.name, &reports))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use diesel::{result::Error, Connection};
+        use std::ops::Deref;
+
+        use super::*;
+        use crate::sanctions::models::{CreateSanction, ExtraInfo, SanctionInfo};
+        use crate::teams::models::{Rule, Team};
+        use crate::test_utils::postgres::{init_connection, unique_admin};
+        use crate::users::models::User;
+
+        #[test]
+        fn test_escape_html() {
+            assert_eq!(
+                escape_html("<b>\"Tom\" & 'Jerry'</b>"),
+                String::from("&lt;b&gt;&quot;Tom&quot; &amp; &#39;Jerry&#39;&lt;/b&gt;")
+            );
+        }
+
+        #[test]
+        fn test_render_team_report_html() {
+            let conn = init_connection();
+
+            conn.deref().test_transaction::<_, Error, _>(|| {
+                let rule = Rule {
+                    id: Uuid::new_v4(),
+                    name: String::from("Late"),
+                    ..Default::default()
+                };
+                let team_id = conn
+                    .create_team(
+                        &Team {
+                            rules: vec![rule.clone()],
+                            ..Default::default()
+                        },
+                        &unique_admin(),
+                    )
+                    .unwrap()
+                    .id;
+                let user_id = conn
+                    .create_user(&User {
+                        team_id,
+                        firstname: String::from("<script>alert(1)</script>"),
+                        lastname: String::from("Doe"),
+                        ..Default::default()
+                    })
+                    .unwrap()
+                    .id;
+
+                conn.create_sanctions(&[
+                    CreateSanction {
+                        id: Uuid::new_v4(),
+                        user_id,
+                        team_id,
+                        sanction_info: SanctionInfo {
+                            associated_rule: rule.id,
+                            extra_info: ExtraInfo::None,
+                        },
+                        price: Amount::from_cents(250),
+                        ..Default::default()
+                    },
+                    CreateSanction {
+                        id: Uuid::new_v4(),
+                        user_id,
+                        team_id,
+                        sanction_info: SanctionInfo {
+                            associated_rule: rule.id,
+                            extra_info: ExtraInfo::None,
+                        },
+                        price: Amount::from_cents(500),
+                        ..Default::default()
+                    },
+                ])
+                .unwrap();
+
+                let report = render_team_report_html(&conn, team_id).unwrap();
+
+                assert!(report.starts_with("<!DOCTYPE html>"));
+                assert!(report.contains("&lt;script&gt;alert(1)&lt;/script&gt; Doe"));
+                assert!(!report.contains("<script>"));
+                assert!(report.contains("<td>Late</td>"));
+                assert!(report.contains("<th colspan=\"2\">7.50</th>"));
+
+                Ok(())
+            });
+        }
     }
 }
@@ -0,0 +1,24 @@
+use chrono::naive::NaiveDate;
+use uuid::Uuid;
+
+use super::models::Session;
+use crate::database::postgres::DbError;
+use crate::users::models::User;
+
+pub trait SessionsDb {
+    fn create_session(&self, session: &Session) -> Result<Session, DbError>;
+
+    fn record_attendance(
+        &self,
+        team_id: Uuid,
+        session_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(), DbError>;
+
+    /// The anonymized users and those on leave are left out.
+    fn get_missing_attendance(
+        &self,
+        team_id: Uuid,
+        session_date: NaiveDate,
+    ) -> Result<Vec<User>, DbError>;
+}
@@ -0,0 +1,3 @@
+pub mod interface;
+pub mod models;
+pub mod postgres;
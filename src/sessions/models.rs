@@ -0,0 +1,23 @@
+use chrono::naive::NaiveDate;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::database::schema::{session_attendances, sessions};
+use crate::teams::models::RuleCategory;
+
+#[derive(Queryable, Insertable, Serialize, Debug, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "sessions"]
+pub struct Session {
+    pub id: Uuid,
+    pub team_id: Uuid,
+    pub date: NaiveDate,
+    pub kind: RuleCategory,
+}
+
+#[derive(Queryable, Insertable, Debug, PartialEq, Clone)]
+#[table_name = "session_attendances"]
+pub struct Attendance {
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+}
@@ -0,0 +1,181 @@
+use chrono::naive::NaiveDate;
+use diesel::prelude::*;
+use std::ops::Deref;
+use uuid::Uuid;
+
+use super::{
+    interface::SessionsDb,
+    models::{Attendance, Session},
+};
+use crate::database::{
+    postgres::{DbConnection, DbError},
+    schema::{sanctions, session_attendances, sessions, users},
+};
+use crate::sanctions::models::start_of_day;
+use crate::users::models::User;
+
+impl SessionsDb for DbConnection {
+    fn create_session(&self, session: &Session) -> Result<Session, DbError> {
+        let session: Session = diesel::insert_into(sessions::table)
+            .values(session)
+            .get_result(self.deref())?;
+
+        Ok(session)
+    }
+
+    fn record_attendance(
+        &self,
+        team_id: Uuid,
+        session_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(), DbError> {
+        sessions::table
+            .filter(
+                sessions::team_id
+                    .eq(team_id)
+                    .and(sessions::id.eq(session_id)),
+            )
+            .select(sessions::id)
+            .get_result::<Uuid>(self.deref())?;
+        users::table
+            .filter(users::team_id.eq(team_id).and(users::id.eq(user_id)))
+            .select(users::id)
+            .get_result::<Uuid>(self.deref())?;
+
+        diesel::insert_into(session_attendances::table)
+            .values(&Attendance {
+                session_id,
+                user_id,
+            })
+            .on_conflict_do_nothing()
+            .execute(self.deref())?;
+
+        Ok(())
+    }
+
+    fn get_missing_attendance(
+        &self,
+        team_id: Uuid,
+        session_date: NaiveDate,
+    ) -> Result<Vec<User>, DbError> {
+        let session_id: Uuid = sessions::table
+            .filter(
+                sessions::team_id
+                    .eq(team_id)
+                    .and(sessions::date.eq(session_date)),
+            )
+            .select(sessions::id)
+            .get_result(self.deref())?;
+
+        let attendees: Vec<Uuid> = session_attendances::table
+            .filter(session_attendances::session_id.eq(session_id))
+            .select(session_attendances::user_id)
+            .get_results(self.deref())?;
+        let sanctioned: Vec<Uuid> = sanctions::table
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::created_at.ge(start_of_day(session_date)))
+                    .and(sanctions::created_at.lt(start_of_day(session_date.succ()))),
+            )
+            .select(sanctions::user_id)
+            .get_results(self.deref())?;
+
+        let users: Vec<User> = users::table
+            .filter(
+                users::team_id
+                    .eq(team_id)
+                    .and(users::anonymized.eq(false))
+                    .and(
+                        users::on_leave_until
+                            .is_null()
+                            .or(users::on_leave_until.lt(session_date)),
+                    ),
+            )
+            .order_by((users::lastname, users::firstname, users::id))
+            .get_results(self.deref())?;
+
+        Ok(users
+            .into_iter()
+            .filter(|user| !attendees.contains(&user.id) && !sanctioned.contains(&user.id))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::result::Error;
+
+    use super::*;
+    use crate::sanctions::{interface::SanctionsDb, models::CreateSanction};
+    use crate::teams::{
+        interface::TeamsDb,
+        models::{Rule, RuleCategory, Team},
+    };
+    use crate::test_utils::postgres::{init_connection, unique_admin};
+    use crate::users::interface::UsersDb;
+
+    #[test]
+    fn test_get_missing_attendance() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: vec![Rule::default()],
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user = |lastname: &str| {
+                conn.create_user(&User {
+                    id: Uuid::new_v4(),
+                    team_id,
+                    lastname: lastname.to_string(),
+                    ..Default::default()
+                })
+                .unwrap()
+            };
+            let sanctioned_user = user("Doe");
+            let missing_user = user("Smith");
+            let date = NaiveDate::from_ymd(2020, 3, 4);
+
+            let session = conn
+                .create_session(&Session {
+                    id: Uuid::new_v4(),
+                    team_id,
+                    date,
+                    kind: RuleCategory::TrainingDay,
+                })
+                .unwrap();
+            conn.create_sanctions(&[CreateSanction {
+                id: Uuid::new_v4(),
+                user_id: sanctioned_user.id,
+                team_id,
+                created_at: Some(start_of_day(date)),
+                ..Default::default()
+            }])
+            .unwrap();
+
+            assert_eq!(
+                conn.get_missing_attendance(team_id, date).unwrap(),
+                vec![missing_user.clone()]
+            );
+
+            conn.record_attendance(team_id, session.id, missing_user.id)
+                .unwrap();
+
+            assert_eq!(conn.get_missing_attendance(team_id, date).unwrap(), vec![]);
+            assert_eq!(
+                conn.get_missing_attendance(team_id, date.succ())
+                    .unwrap_err(),
+                DbError::NotFound
+            );
+
+            Ok(())
+        });
+    }
+}
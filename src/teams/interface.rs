@@ -1,14 +1,51 @@
 use uuid::Uuid;
 
-use super::models::{Team, UpdateTeam};
-use crate::database::postgres::DbError;
+use super::models::{Rule, RuleInput, Team, UpdateTeam};
+use crate::admins::models::NewAdmin;
+use crate::database::{amount::Amount, postgres::DbError};
+use crate::users::models::User;
 
 pub trait TeamsDb {
-    fn login(&self, name: &str, admin_password: &Option<String>) -> Result<Uuid, DbError>;
+    /// The admin's credentials are checked apart, with `AdminsDb::verify_admin`.
+    fn login(&self, name: &str) -> Result<Uuid, DbError>;
 
     fn get_team(&self, id: Uuid) -> Result<Team, DbError>;
 
-    fn create_team(&self, team: &Team) -> Result<Team, DbError>;
+    fn create_team(&self, team: &Team, admin: &NewAdmin) -> Result<Team, DbError>;
 
     fn update_team(&self, id: Uuid, team: &UpdateTeam) -> Result<Team, DbError>;
+
+    fn add_rule(&self, team_id: Uuid, rule: &Rule) -> Result<Rule, DbError>;
+
+    fn copy_rules(&self, source_team: Uuid, target_team: Uuid) -> Result<Vec<Rule>, DbError>;
+
+    fn import_rules(&self, team_id: Uuid, rules: Vec<RuleInput>) -> Result<Vec<Rule>, DbError>;
+
+    fn set_current_season(&self, team_id: Uuid, season: Option<&str>) -> Result<Team, DbError>;
+
+    fn get_team_with_users(&self, team_id: Uuid) -> Result<(Team, Vec<User>), DbError>;
+
+    fn get_rules_by_tag(&self, team_id: Uuid, tag: &str) -> Result<Vec<Rule>, DbError>;
+
+    /// Refused while a sanction or the default rule refers to a removed rule.
+    fn replace_rules(&self, team_id: Uuid, rules: &[Rule]) -> Result<Vec<Rule>, DbError>;
+
+    fn set_max_balance(
+        &self,
+        team_id: Uuid,
+        max_balance: Option<Amount>,
+        clamp: bool,
+    ) -> Result<Team, DbError>;
+
+    fn set_max_rules(&self, team_id: Uuid, max_rules: u32) -> Result<Team, DbError>;
+
+    fn set_grace_days(&self, team_id: Uuid, grace_days: u32) -> Result<Team, DbError>;
+
+    /// Only the hash is stored, so the token is only returned here.
+    fn rotate_team_token(&self, team_id: Uuid) -> Result<String, DbError>;
+
+    fn verify_team_token(&self, team_id: Uuid, token: &str) -> Result<Uuid, DbError>;
+
+    /// The token hashes are left out of the returned teams.
+    fn get_teams_for_email(&self, email: &str) -> Result<Vec<Team>, DbError>;
 }
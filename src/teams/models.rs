@@ -1,20 +1,34 @@
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
 use diesel::{Insertable, Queryable};
 use diesel_as_jsonb::*;
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::io::Write;
 use uuid::Uuid;
 
+use crate::admins::{models::NewAdmin, utils::password::validate_password};
+use crate::api::models::ValidationError;
+use crate::database::amount::Amount;
 use crate::database::schema::teams;
 
+pub const DEFAULT_MAX_RULES: i32 = 100;
+
 #[derive(Deserialize)]
 pub struct LoginRequest {
     pub name: String,
+    pub admin_username: Option<String>,
     pub admin_password: Option<String>,
+    pub admin_code: Option<String>,
 }
 
 #[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct LoginResponse {
     pub id: Uuid,
+    pub admin_username: Option<String>,
     pub admin_password: Option<String>,
 }
 
@@ -22,17 +36,145 @@ impl From<(LoginRequest, Uuid)> for LoginResponse {
     fn from((login_request, team_id): (LoginRequest, Uuid)) -> LoginResponse {
         LoginResponse {
             id: team_id,
+            admin_username: login_request.admin_username,
             admin_password: login_request.admin_password,
         }
     }
 }
 
+#[derive(Deserialize)]
+pub struct TeamLoginRequest {
+    pub username: String,
+    pub password: String,
+    pub code: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyLoginCodeRequest {
+    pub username: String,
+    pub code: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginCodeResponse {
+    pub validity_minutes: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TokenResponse {
+    pub token: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamSummary {
+    pub id: Uuid,
+    pub name: String,
+}
+
+impl From<Team> for TeamSummary {
+    fn from(team: Team) -> TeamSummary {
+        TeamSummary {
+            id: team.id,
+            name: team.name,
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct UpdateTeamRequest {
     pub id: Option<Uuid>,
     pub name: String,
-    pub admin_password: String,
     pub rules: Vec<UpdateRuleRequest>,
+    #[serde(default)]
+    pub current_season: Option<String>,
+    #[serde(default)]
+    pub default_rule_id: Option<Uuid>,
+    #[serde(default)]
+    pub allow_self_fine: Option<bool>,
+}
+
+impl UpdateTeamRequest {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if let Some(season) = &self.current_season {
+            validate_season(season)?;
+        }
+        if let Some(default_rule_id) = self.default_rule_id {
+            if !self
+                .rules
+                .iter()
+                .any(|rule| rule.id == Some(default_rule_id))
+            {
+                return Err(ValidationError {
+                    field: String::from("default_rule_id"),
+                    reason: String::from("must refer to one of the team's rules"),
+                });
+            }
+        }
+
+        self.rules.iter().try_for_each(UpdateRuleRequest::validate)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateTeamRequest {
+    #[serde(flatten)]
+    pub team: UpdateTeamRequest,
+    pub admin_username: Option<String>,
+    pub admin_password: Option<String>,
+}
+
+impl CreateTeamRequest {
+    pub fn validate(&self, password_min_length: usize) -> Result<(), ValidationError> {
+        self.team.validate()?;
+        self.admin(password_min_length)?;
+
+        Ok(())
+    }
+
+    pub fn admin(&self, password_min_length: usize) -> Result<NewAdmin, ValidationError> {
+        let username = match self.admin_username.as_deref().map(str::trim) {
+            Some(username) if !username.is_empty() => username,
+            _ => {
+                return Err(ValidationError {
+                    field: String::from("admin_username"),
+                    reason: String::from("must be given to create a team"),
+                })
+            }
+        };
+        let password = self
+            .admin_password
+            .as_deref()
+            .ok_or_else(|| ValidationError {
+                field: String::from("admin_password"),
+                reason: String::from("must be given to create a team"),
+            })?;
+        validate_password("admin_password", password, password_min_length)?;
+
+        Ok(NewAdmin {
+            username: String::from(username),
+            password: String::from(password),
+        })
+    }
+}
+
+pub fn validate_season(season: &str) -> Result<(), ValidationError> {
+    let years: Vec<Option<i32>> = season
+        .split('-')
+        .map(|year| match year.len() {
+            4 if year.chars().all(|c| c.is_ascii_digit()) => year.parse().ok(),
+            _ => None,
+        })
+        .collect();
+
+    match years.as_slice() {
+        [Some(start), Some(end)] if start + 1 == *end => Ok(()),
+        _ => Err(ValidationError {
+            field: String::from("season"),
+            reason: String::from("must be two consecutive years such as 2019-2020"),
+        }),
+    }
 }
 
 impl From<UpdateTeamRequest> for Team {
@@ -40,12 +182,15 @@ impl From<UpdateTeamRequest> for Team {
         Team {
             id: update_request.id.unwrap_or_else(Uuid::new_v4),
             name: update_request.name,
-            admin_password: update_request.admin_password,
             rules: update_request
                 .rules
                 .into_iter()
                 .map(|update_rule_request| update_rule_request.into())
                 .collect(),
+            current_season: update_request.current_season,
+            default_rule_id: update_request.default_rule_id,
+            allow_self_fine: update_request.allow_self_fine.unwrap_or(true),
+            ..Default::default()
         }
     }
 }
@@ -54,23 +199,51 @@ impl From<UpdateTeamRequest> for UpdateTeam {
     fn from(update_request: UpdateTeamRequest) -> UpdateTeam {
         UpdateTeam {
             name: update_request.name,
-            admin_password: update_request.admin_password,
             rules: update_request
                 .rules
                 .into_iter()
                 .map(|update_rule_request| update_rule_request.into())
                 .collect(),
+            current_season: update_request.current_season,
+            default_rule_id: update_request.default_rule_id,
+            allow_self_fine: update_request.allow_self_fine,
         }
     }
 }
 
-#[derive(Debug, Queryable, Insertable, Serialize, PartialEq, Clone, Default)]
+#[derive(Debug, Queryable, Insertable, Serialize, PartialEq, Clone)]
 #[table_name = "teams"]
 pub struct Team {
     pub id: Uuid,
     pub name: String,
-    pub admin_password: String,
     pub rules: Vec<Rule>,
+    pub current_season: Option<String>,
+    pub default_rule_id: Option<Uuid>,
+    pub allow_self_fine: bool,
+    pub max_balance: Option<Amount>,
+    pub clamp_to_max_balance: bool,
+    pub max_rules: i32,
+    #[serde(skip_serializing)]
+    pub token_hash: Option<String>,
+    pub grace_days: i32,
+}
+
+impl Default for Team {
+    fn default() -> Team {
+        Team {
+            id: Default::default(),
+            name: Default::default(),
+            rules: Default::default(),
+            current_season: None,
+            default_rule_id: None,
+            allow_self_fine: true,
+            max_balance: None,
+            clamp_to_max_balance: false,
+            max_rules: DEFAULT_MAX_RULES,
+            token_hash: None,
+            grace_days: 0,
+        }
+    }
 }
 
 impl Team {
@@ -79,12 +252,46 @@ impl Team {
     }
 }
 
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub rules: Vec<Rule>,
+    pub current_season: Option<String>,
+    pub default_rule_id: Option<Uuid>,
+    pub allow_self_fine: bool,
+    pub max_balance: Option<Amount>,
+    pub clamp_to_max_balance: bool,
+    pub max_rules: i32,
+    pub grace_days: i32,
+}
+
+impl From<Team> for TeamResponse {
+    fn from(team: Team) -> TeamResponse {
+        TeamResponse {
+            id: team.id,
+            name: team.name,
+            rules: team.rules,
+            current_season: team.current_season,
+            default_rule_id: team.default_rule_id,
+            allow_self_fine: team.allow_self_fine,
+            max_balance: team.max_balance,
+            clamp_to_max_balance: team.clamp_to_max_balance,
+            max_rules: team.max_rules,
+            grace_days: team.grace_days,
+        }
+    }
+}
+
 #[derive(AsChangeset, Default)]
 #[table_name = "teams"]
 pub struct UpdateTeam {
     pub name: String,
-    pub admin_password: String,
     pub rules: Vec<Rule>,
+    pub current_season: Option<String>,
+    pub default_rule_id: Option<Uuid>,
+    pub allow_self_fine: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -94,6 +301,17 @@ pub struct UpdateRuleRequest {
     pub category: RuleCategory,
     pub description: String,
     pub kind: RuleKind,
+    pub max_per_day: Option<u32>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub excludes: Vec<Uuid>,
+}
+
+impl UpdateRuleRequest {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.kind.validate()
+    }
 }
 
 impl From<UpdateRuleRequest> for Rule {
@@ -104,6 +322,48 @@ impl From<UpdateRuleRequest> for Rule {
             category: update_request.category,
             description: update_request.description,
             kind: update_request.kind,
+            max_per_day: update_request.max_per_day,
+            tags: normalize_tags(&update_request.tags),
+            excludes: update_request.excludes,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct RuleInput {
+    pub name: String,
+    pub category: RuleCategory,
+    pub description: String,
+    pub kind: RuleKind,
+    pub max_per_day: Option<u32>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl RuleInput {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.name.trim().is_empty() {
+            return Err(ValidationError {
+                field: String::from("name"),
+                reason: String::from("must not be empty"),
+            });
+        }
+
+        self.kind.validate()
+    }
+}
+
+impl From<RuleInput> for Rule {
+    fn from(input: RuleInput) -> Rule {
+        Rule {
+            id: Uuid::new_v4(),
+            name: input.name,
+            category: input.category,
+            description: input.description,
+            kind: input.kind,
+            max_per_day: input.max_per_day,
+            tags: normalize_tags(&input.tags),
+            excludes: vec![],
         }
     }
 }
@@ -115,10 +375,67 @@ pub struct Rule {
     pub category: RuleCategory,
     pub description: String,
     pub kind: RuleKind,
+    #[serde(default)]
+    pub max_per_day: Option<u32>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The exclusion goes both ways.
+    #[serde(default)]
+    pub excludes: Vec<Uuid>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub fn validate_rules(rules: &[Rule]) -> Result<(), ValidationError> {
+    for (index, rule) in rules.iter().enumerate() {
+        rule.kind.validate()?;
+
+        if rules[..index].iter().any(|other| other.name == rule.name) {
+            return Err(ValidationError {
+                field: String::from("name"),
+                reason: format!("must be unique but {} is used twice", rule.name),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+pub fn validate_rules_count(rules_count: usize, max_rules: i32) -> Result<(), ValidationError> {
+    if rules_count > max_rules.max(0) as usize {
+        return Err(ValidationError {
+            field: String::from("rules"),
+            reason: format!("must not be more than {}", max_rules),
+        });
+    }
+
+    Ok(())
+}
+
+pub fn validate_max_balance(max_balance: Amount) -> Result<(), ValidationError> {
+    if max_balance.cents() < 0 {
+        return Err(ValidationError {
+            field: String::from("max_balance"),
+            reason: String::from("must not be negative"),
+        });
+    }
+
+    Ok(())
+}
+
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+
+    for tag in tags.iter().map(|tag| tag.trim()) {
+        if !tag.is_empty() && !normalized.iter().any(|existing| existing == tag) {
+            normalized.push(tag.to_string());
+        }
+    }
+
+    normalized
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, AsExpression, FromSqlRow)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sql_type = "Text"]
 pub enum RuleCategory {
     GameDay,
     TrainingDay,
@@ -130,22 +447,98 @@ impl Default for RuleCategory {
     }
 }
 
+impl RuleCategory {
+    fn as_str(&self) -> &'static str {
+        match self {
+            RuleCategory::GameDay => "GAME_DAY",
+            RuleCategory::TrainingDay => "TRAINING_DAY",
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for RuleCategory {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        ToSql::<Text, Pg>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for RuleCategory {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<RuleCategory> {
+        let category = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+
+        [RuleCategory::GameDay, RuleCategory::TrainingDay]
+            .iter()
+            .find(|known_category| known_category.as_str() == category)
+            .cloned()
+            .ok_or_else(|| format!("Unknown rule category {}", category).into())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE", tag = "type")]
 pub enum RuleKind {
     Basic {
-        price: f32,
+        price: Amount,
     },
     Multiplication {
-        price_to_multiply: f32,
+        price_to_multiply: Amount,
     },
     TimeMultiplication {
-        price_per_time_unit: f32,
+        price_per_time_unit: Amount,
         time_unit: TimeUnit,
     },
     Monthly {
-        price: f32,
+        price: Amount,
     },
+    Percentage {
+        rate: f32,
+    },
+}
+
+impl RuleKind {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let (field, price) = match self {
+            RuleKind::Percentage { rate } if !(0.0..=1.0).contains(rate) => {
+                return Err(ValidationError {
+                    field: String::from("rate"),
+                    reason: String::from("must be between 0 and 1"),
+                })
+            }
+            RuleKind::Percentage { .. } => return Ok(()),
+            RuleKind::Basic { price } | RuleKind::Monthly { price } => ("price", price),
+            RuleKind::Multiplication { price_to_multiply } => {
+                ("price_to_multiply", price_to_multiply)
+            }
+            RuleKind::TimeMultiplication {
+                price_per_time_unit,
+                ..
+            } => ("price_per_time_unit", price_per_time_unit),
+        };
+
+        if price.cents() < 0 {
+            return Err(ValidationError {
+                field: String::from(field),
+                reason: String::from("must not be negative"),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn with_price(&self, price: Amount) -> Option<RuleKind> {
+        match self {
+            RuleKind::Basic { .. } => Some(RuleKind::Basic { price }),
+            RuleKind::Monthly { .. } => Some(RuleKind::Monthly { price }),
+            RuleKind::Multiplication { .. } => Some(RuleKind::Multiplication {
+                price_to_multiply: price,
+            }),
+            RuleKind::TimeMultiplication { time_unit, .. } => Some(RuleKind::TimeMultiplication {
+                price_per_time_unit: price,
+                time_unit: time_unit.clone(),
+            }),
+            RuleKind::Percentage { .. } => None,
+        }
+    }
 }
 
 impl Default for RuleKind {
@@ -163,6 +556,7 @@ impl fmt::Display for RuleKind {
             RuleKind::Multiplication { .. } => write!(f, "MULTIPLICATION"),
             RuleKind::TimeMultiplication { .. } => write!(f, "TIME_MULTIPLICATION"),
             RuleKind::Monthly { .. } => write!(f, "MONTHLY"),
+            RuleKind::Percentage { .. } => write!(f, "PERCENTAGE"),
         }
     }
 }
@@ -1,28 +1,104 @@
 use diesel::prelude::*;
+use diesel::sql_types::{Nullable, Varchar};
 use std::ops::Deref;
 use uuid::Uuid;
 
 use super::{
     interface::TeamsDb,
-    models::{Team, UpdateTeam},
+    models::{
+        normalize_tags, validate_max_balance, validate_rules, validate_rules_count,
+        validate_season, Rule, RuleInput, Team, UpdateTeam,
+    },
+};
+use crate::admins::{
+    models::NewAdmin,
+    postgres::insert_admin,
+    utils::{
+        password::{hash_password, verify_password},
+        token::generate_token,
+    },
 };
 use crate::database::{
+    amount::Amount,
     postgres::{DbConnection, DbError},
-    schema::teams,
+    schema::{sanctions, teams, users},
 };
+use crate::sanctions::models::SanctionInfo;
+use crate::users::models::User;
+
+sql_function!(fn lower(x: Nullable<Varchar>) -> Nullable<Varchar>);
+
+/// Locks the team so that the rule mutations, which rewrite the whole column, are serialized.
+fn lock_team(conn: &DbConnection, id: Uuid) -> Result<Team, DbError> {
+    let team: Team = teams::table
+        .find(id)
+        .for_update()
+        .get_result(conn.deref())?;
+
+    Ok(team)
+}
+
+fn check_rules_replacement(
+    conn: &DbConnection,
+    team: &Team,
+    rules: &[Rule],
+    default_rule_id: Option<Uuid>,
+) -> Result<Vec<Rule>, DbError> {
+    validate_rules(rules)?;
+    let rules: Vec<Rule> = rules
+        .iter()
+        .map(|rule| Rule {
+            tags: normalize_tags(&rule.tags),
+            ..rule.clone()
+        })
+        .collect();
+    validate_rules_count(rules.len(), team.max_rules)?;
+
+    let removed_rules: Vec<&Rule> = team
+        .rules
+        .iter()
+        .filter(|rule| !rules.iter().any(|new_rule| new_rule.id == rule.id))
+        .collect();
+
+    if let Some(default_rule) = removed_rules
+        .iter()
+        .find(|rule| default_rule_id == Some(rule.id))
+    {
+        return Err(DbError::Forbidden(format!(
+            "The rule {} can't be removed as it is the team's default rule",
+            default_rule.name
+        )));
+    }
+
+    let used_rules: Vec<Uuid> = sanctions::table
+        .filter(sanctions::team_id.eq(team.id))
+        .select(sanctions::sanction_info)
+        .get_results::<SanctionInfo>(conn.deref())?
+        .into_iter()
+        .map(|sanction_info| sanction_info.associated_rule)
+        .collect();
+
+    if let Some(used_rule) = removed_rules
+        .iter()
+        .find(|rule| used_rules.contains(&rule.id))
+    {
+        return Err(DbError::Forbidden(format!(
+            "The rule {} can't be removed as sanctions refer to it",
+            used_rule.name
+        )));
+    }
+
+    Ok(rules)
+}
 
 impl TeamsDb for DbConnection {
-    fn login(&self, name: &str, admin_password: &Option<String>) -> Result<Uuid, DbError> {
-        let team: Team = match admin_password {
-            Some(password) => teams::table
-                .filter(teams::name.eq(name).and(teams::admin_password.eq(password)))
-                .get_result(self.deref())?,
-            None => teams::table
-                .filter(teams::name.eq(name))
-                .get_result(self.deref())?,
-        };
+    fn login(&self, name: &str) -> Result<Uuid, DbError> {
+        let team_id: Uuid = teams::table
+            .filter(teams::name.eq(name))
+            .select(teams::id)
+            .get_result(self.deref())?;
 
-        Ok(team.id)
+        Ok(team_id)
     }
 
     fn get_team(&self, id: Uuid) -> Result<Team, DbError> {
@@ -31,29 +107,245 @@ impl TeamsDb for DbConnection {
         Ok(team)
     }
 
-    fn create_team(&self, team: &Team) -> Result<Team, DbError> {
-        let team: Team = diesel::insert_into(teams::table)
-            .values(team)
+    fn create_team(&self, team: &Team, admin: &NewAdmin) -> Result<Team, DbError> {
+        self.deref().transaction::<_, DbError, _>(|| {
+            let created_team: Team = diesel::insert_into(teams::table)
+                .values(team)
+                .get_result(self.deref())?;
+
+            insert_admin(self, created_team.id, &admin.username, &admin.password)?;
+
+            Ok(created_team)
+        })
+    }
+
+    fn update_team(&self, id: Uuid, team: &UpdateTeam) -> Result<Team, DbError> {
+        self.deref().transaction::<_, DbError, _>(|| {
+            let stored_team = lock_team(self, id)?;
+            let rules = check_rules_replacement(
+                self,
+                &stored_team,
+                &team.rules,
+                team.default_rule_id.or(stored_team.default_rule_id),
+            )?;
+
+            let team: Team = diesel::update(teams::table.find(id))
+                .set(&UpdateTeam {
+                    name: team.name.clone(),
+                    rules,
+                    current_season: team.current_season.clone(),
+                    default_rule_id: team.default_rule_id,
+                    allow_self_fine: team.allow_self_fine,
+                })
+                .get_result(self.deref())?;
+
+            Ok(team)
+        })
+    }
+
+    fn add_rule(&self, team_id: Uuid, rule: &Rule) -> Result<Rule, DbError> {
+        self.deref().transaction::<_, DbError, _>(|| {
+            let rule = Rule {
+                tags: normalize_tags(&rule.tags),
+                ..rule.clone()
+            };
+            let team = lock_team(self, team_id)?;
+            validate_rules_count(team.rules.len() + 1, team.max_rules)?;
+            let mut rules = team.rules;
+            rules.push(rule.clone());
+
+            diesel::update(teams::table.find(team_id))
+                .set(teams::rules.eq(rules))
+                .execute(self.deref())?;
+
+            Ok(rule)
+        })
+    }
+
+    fn copy_rules(&self, source_team: Uuid, target_team: Uuid) -> Result<Vec<Rule>, DbError> {
+        self.deref().transaction::<_, DbError, _>(|| {
+            let source: Team = teams::table.find(source_team).get_result(self.deref())?;
+            let target: Team = lock_team(self, target_team)?;
+            validate_rules_count(target.rules.len() + source.rules.len(), target.max_rules)?;
+
+            let mut rules = target.rules;
+            rules.extend(source.rules.into_iter().map(|rule| Rule {
+                id: Uuid::new_v4(),
+                ..rule
+            }));
+
+            let team: Team = diesel::update(teams::table.find(target_team))
+                .set(teams::rules.eq(rules))
+                .get_result(self.deref())?;
+
+            Ok(team.rules)
+        })
+    }
+
+    fn import_rules(&self, team_id: Uuid, rules: Vec<RuleInput>) -> Result<Vec<Rule>, DbError> {
+        for rule in &rules {
+            rule.validate()?;
+        }
+        let imported_rules: Vec<Rule> = rules.into_iter().map(Rule::from).collect();
+
+        self.deref().transaction::<_, DbError, _>(|| {
+            let team = lock_team(self, team_id)?;
+            let mut rules = team.rules;
+            rules.extend(imported_rules.iter().cloned());
+            validate_rules(&rules)?;
+            validate_rules_count(rules.len(), team.max_rules)?;
+
+            diesel::update(teams::table.find(team_id))
+                .set(teams::rules.eq(rules))
+                .execute(self.deref())?;
+
+            Ok(imported_rules)
+        })
+    }
+
+    fn set_current_season(&self, team_id: Uuid, season: Option<&str>) -> Result<Team, DbError> {
+        if let Some(season) = season {
+            validate_season(season)?;
+        }
+
+        let team: Team = diesel::update(teams::table.find(team_id))
+            .set(teams::current_season.eq(season))
             .get_result(self.deref())?;
 
         Ok(team)
     }
 
-    fn update_team(&self, id: Uuid, team: &UpdateTeam) -> Result<Team, DbError> {
-        let team: Team = diesel::update(teams::table.find(id))
-            .set(team)
+    fn get_team_with_users(&self, team_id: Uuid) -> Result<(Team, Vec<User>), DbError> {
+        let rows: Vec<(Team, Option<User>)> = teams::table
+            .left_join(users::table)
+            .filter(teams::id.eq(team_id))
+            .order_by((users::lastname, users::firstname, users::id))
+            .get_results(self.deref())?;
+
+        let team = match rows.first() {
+            Some((team, _)) => team.clone(),
+            None => return Err(DbError::NotFound),
+        };
+        let users = rows.into_iter().filter_map(|(_, user)| user).collect();
+
+        Ok((team, users))
+    }
+
+    fn get_rules_by_tag(&self, team_id: Uuid, tag: &str) -> Result<Vec<Rule>, DbError> {
+        let team: Team = teams::table.find(team_id).get_result(self.deref())?;
+        let tag = tag.trim();
+
+        Ok(team
+            .rules
+            .into_iter()
+            .filter(|rule| rule.tags.iter().any(|rule_tag| rule_tag == tag))
+            .collect())
+    }
+
+    fn replace_rules(&self, team_id: Uuid, rules: &[Rule]) -> Result<Vec<Rule>, DbError> {
+        self.deref().transaction::<_, DbError, _>(|| {
+            let team = lock_team(self, team_id)?;
+            let rules = check_rules_replacement(self, &team, rules, team.default_rule_id)?;
+
+            let team: Team = diesel::update(teams::table.find(team_id))
+                .set(teams::rules.eq(rules))
+                .get_result(self.deref())?;
+
+            Ok(team.rules)
+        })
+    }
+
+    fn set_max_balance(
+        &self,
+        team_id: Uuid,
+        max_balance: Option<Amount>,
+        clamp: bool,
+    ) -> Result<Team, DbError> {
+        if let Some(max_balance) = max_balance {
+            validate_max_balance(max_balance)?;
+        }
+
+        let team: Team = diesel::update(teams::table.find(team_id))
+            .set((
+                teams::max_balance.eq(max_balance),
+                teams::clamp_to_max_balance.eq(clamp),
+            ))
             .get_result(self.deref())?;
 
         Ok(team)
     }
+
+    fn set_max_rules(&self, team_id: Uuid, max_rules: u32) -> Result<Team, DbError> {
+        let team: Team = diesel::update(teams::table.find(team_id))
+            .set(teams::max_rules.eq(max_rules as i32))
+            .get_result(self.deref())?;
+
+        Ok(team)
+    }
+
+    fn set_grace_days(&self, team_id: Uuid, grace_days: u32) -> Result<Team, DbError> {
+        let team: Team = diesel::update(teams::table.find(team_id))
+            .set(teams::grace_days.eq(grace_days as i32))
+            .get_result(self.deref())?;
+
+        Ok(team)
+    }
+
+    fn rotate_team_token(&self, team_id: Uuid) -> Result<String, DbError> {
+        let token = generate_token();
+
+        diesel::update(teams::table.find(team_id))
+            .set(teams::token_hash.eq(hash_password(&token)?))
+            .get_result::<Team>(self.deref())?;
+
+        Ok(token)
+    }
+
+    fn verify_team_token(&self, team_id: Uuid, token: &str) -> Result<Uuid, DbError> {
+        let token_hash: Option<String> = teams::table
+            .find(team_id)
+            .select(teams::token_hash)
+            .get_result(self.deref())?;
+
+        match token_hash {
+            Some(token_hash) if verify_password(token, &token_hash) => Ok(team_id),
+            _ => Err(DbError::NotFound),
+        }
+    }
+
+    fn get_teams_for_email(&self, email: &str) -> Result<Vec<Team>, DbError> {
+        let teams: Vec<Team> = teams::table
+            .filter(
+                teams::id.eq_any(
+                    users::table
+                        .filter(lower(users::email).eq(lower(Some(email))))
+                        .select(users::team_id),
+                ),
+            )
+            .order_by((teams::name, teams::id))
+            .get_results(self.deref())?;
+
+        Ok(teams
+            .into_iter()
+            .map(|team| Team {
+                token_hash: None,
+                ..team
+            })
+            .collect())
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use diesel::result::Error;
+    use diesel::{dsl::sql, result::Error, sql_types::Bool};
+    use std::sync::{Arc, Barrier};
 
     use super::*;
-    use crate::test_utils::postgres::init_connection;
+    use crate::admins::interface::AdminsDb;
+    use crate::sanctions::{interface::SanctionsDb, models::CreateSanction};
+    use crate::teams::models::{RuleKind, DEFAULT_MAX_RULES};
+    use crate::test_utils::postgres::{init_connection, unique_admin};
+    use crate::users::interface::UsersDb;
 
     #[test]
     fn test_login() {
@@ -61,13 +353,16 @@ mod tests {
 
         conn.deref().test_transaction::<_, Error, _>(|| {
             let created_team = conn
-                .create_team(&Team {
-                    name: String::from("CHBC"),
-                    ..Default::default()
-                })
+                .create_team(
+                    &Team {
+                        name: String::from("CHBC"),
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
                 .unwrap();
 
-            let team_id = conn.login(&created_team.name, &None).unwrap();
+            let team_id = conn.login(&created_team.name).unwrap();
 
             assert_eq!(team_id, created_team.id);
 
@@ -76,129 +371,897 @@ mod tests {
     }
 
     #[test]
-    fn test_login_with_password() {
+    fn test_login_fails() {
         let conn = init_connection();
 
         conn.deref().test_transaction::<_, Error, _>(|| {
-            let created_team = conn
-                .create_team(&Team {
-                    name: String::from("CHBC"),
-                    admin_password: String::from("password"),
-                    ..Default::default()
-                })
+            conn.create_team(&Team::default(), &unique_admin()).unwrap();
+
+            let error = conn.login(&String::from("CHBC")).unwrap_err();
+
+            assert_eq!(error, DbError::NotFound);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_team() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let created_team = conn.create_team(&Team::default(), &unique_admin()).unwrap();
+
+            let team = conn.get_team(created_team.id).unwrap();
+
+            assert_eq!(team, created_team);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_unexisting_team() {
+        let conn = init_connection();
+
+        let error = conn.get_team(Uuid::new_v4()).unwrap_err();
+
+        assert_eq!(error, DbError::NotFound);
+    }
+
+    #[test]
+    fn test_create_team() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let admin = unique_admin();
+            let team = conn.create_team(&Team::default(), &admin).unwrap();
+
+            let admin = conn
+                .verify_admin(team.id, &admin.username, &admin.password)
                 .unwrap();
 
-            let team_id = conn
-                .login(&created_team.name, &Some(created_team.admin_password))
+            assert_eq!(admin.team_id, team.id);
+            assert!(!conn.find_teams_without_admin().unwrap().contains(&team.id));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_create_team_with_used_admin_username() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let admin = unique_admin();
+            conn.create_team(&Team::default(), &admin).unwrap();
+
+            let error = conn
+                .create_team(
+                    &Team {
+                        id: Uuid::new_v4(),
+                        name: String::from("Les Arbres"),
+                        ..Default::default()
+                    },
+                    &admin,
+                )
+                .unwrap_err();
+
+            assert_eq!(
+                error,
+                DbError::UniqueViolation(String::from(
+                    "The field username is already used by another user"
+                ))
+            );
+            assert_eq!(conn.login("Les Arbres").unwrap_err(), DbError::NotFound);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_update_team() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+
+            let name = String::from("New name");
+
+            let team = conn
+                .update_team(
+                    id,
+                    &UpdateTeam {
+                        name: name.clone(),
+                        ..Default::default()
+                    },
+                )
                 .unwrap();
 
-            assert_eq!(team_id, created_team.id);
+            assert_eq!(id, team.id);
+            assert_eq!(team.name, name);
 
             Ok(())
         });
     }
 
     #[test]
-    fn test_login_fails() {
+    fn test_update_unexisting_team() {
+        let conn = init_connection();
+
+        let error = conn
+            .update_team(Uuid::new_v4(), &UpdateTeam::default())
+            .unwrap_err();
+
+        assert_eq!(error, DbError::NotFound);
+    }
+
+    #[test]
+    fn test_add_rule() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+
+            let rule = conn.add_rule(id, &Rule::default()).unwrap();
+
+            assert_eq!(conn.get_team(id).unwrap().rules, vec![rule]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_add_rule_to_unexisting_team() {
+        let conn = init_connection();
+
+        let error = conn.add_rule(Uuid::new_v4(), &Rule::default()).unwrap_err();
+
+        assert_eq!(error, DbError::NotFound);
+    }
+
+    /// Deletes the team even when the test panics.
+    struct TeamCleanup(Uuid);
+
+    impl Drop for TeamCleanup {
+        fn drop(&mut self) {
+            diesel::delete(teams::table.find(self.0))
+                .execute(init_connection().deref())
+                .unwrap();
+        }
+    }
+
+    fn blocks_another_connection(conn: &DbConnection) -> bool {
+        diesel::select(sql::<Bool>(
+            "EXISTS (SELECT 1 FROM pg_locks WHERE NOT granted \
+             AND pg_backend_pid() = ANY(pg_blocking_pids(pid)))",
+        ))
+        .get_result(conn.deref())
+        .unwrap()
+    }
+
+    #[test]
+    fn test_concurrent_add_rule() {
+        let conn = init_connection();
+
+        let id = conn
+            .create_team(
+                &Team {
+                    id: Uuid::new_v4(),
+                    name: Uuid::new_v4().to_string(),
+                    ..Default::default()
+                },
+                &unique_admin(),
+            )
+            .unwrap()
+            .id;
+        let _cleanup = TeamCleanup(id);
+
+        let concurrent_edit = conn
+            .deref()
+            .transaction::<_, DbError, _>(|| {
+                conn.add_rule(
+                    id,
+                    &Rule {
+                        id: Uuid::new_v4(),
+                        ..Default::default()
+                    },
+                )?;
+
+                let connected = Arc::new(Barrier::new(2));
+                let thread_connected = connected.clone();
+                let concurrent_edit = std::thread::spawn(move || {
+                    let conn = init_connection();
+                    thread_connected.wait();
+
+                    conn.add_rule(
+                        id,
+                        &Rule {
+                            id: Uuid::new_v4(),
+                            ..Default::default()
+                        },
+                    )
+                    .unwrap();
+                });
+                connected.wait();
+
+                // The transaction is only committed once the other edit waits for it.
+                for _ in 0..500 {
+                    if blocks_another_connection(&conn) {
+                        return Ok(concurrent_edit);
+                    }
+                    std::thread::yield_now();
+                }
+                panic!("The concurrent edit never waited for the team");
+            })
+            .unwrap();
+        concurrent_edit.join().unwrap();
+
+        assert_eq!(conn.get_team(id).unwrap().rules.len(), 2);
+    }
+
+    #[test]
+    fn test_copy_rules() {
         let conn = init_connection();
 
         conn.deref().test_transaction::<_, Error, _>(|| {
-            conn.create_team(&Team::default()).unwrap();
+            let rule = Rule {
+                id: Uuid::new_v4(),
+                name: String::from("Late"),
+                ..Default::default()
+            };
+
+            let source_id = conn
+                .create_team(
+                    &Team {
+                        name: String::from("CHBC"),
+                        rules: vec![rule.clone()],
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let target_id = conn
+                .create_team(
+                    &Team {
+                        id: Uuid::new_v4(),
+                        name: String::from("CHBC 2"),
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
 
-            let error = conn.login(&String::from("CHBC"), &None).unwrap_err();
+            let rules = conn.copy_rules(source_id, target_id).unwrap();
+
+            assert_eq!(rules.len(), 1);
+            assert_eq!(rules[0].name, rule.name);
+            assert_ne!(rules[0].id, rule.id);
+            assert_eq!(conn.get_team(target_id).unwrap().rules, rules);
+            assert_eq!(conn.get_team(source_id).unwrap().rules, vec![rule]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_copy_rules_to_unexisting_team() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let source_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+
+            let error = conn.copy_rules(source_id, Uuid::new_v4()).unwrap_err();
 
             assert_eq!(error, DbError::NotFound);
 
             Ok(())
         });
+    }
+
+    #[test]
+    fn test_set_current_season() {
+        let conn = init_connection();
 
         conn.deref().test_transaction::<_, Error, _>(|| {
-            let created_team = conn
-                .create_team(&Team {
-                    name: String::from("CHBC"),
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: vec![Rule::default()],
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
                     ..Default::default()
                 })
+                .unwrap()
+                .id;
+
+            let team = conn.set_current_season(team_id, Some("2019-2020")).unwrap();
+
+            assert_eq!(team.current_season, Some(String::from("2019-2020")));
+            assert_eq!(
+                conn.get_team(team_id).unwrap().current_season,
+                Some(String::from("2019-2020"))
+            );
+
+            let sanctions = conn
+                .create_sanctions(&[
+                    CreateSanction {
+                        user_id,
+                        team_id,
+                        ..Default::default()
+                    },
+                    CreateSanction {
+                        id: Uuid::new_v4(),
+                        user_id,
+                        team_id,
+                        season: Some(String::from("2018-2019")),
+                        ..Default::default()
+                    },
+                ])
                 .unwrap();
 
-            let error = conn
-                .login(&created_team.name, &Some(String::from("password")))
-                .unwrap_err();
+            assert_eq!(sanctions[0].season, Some(String::from("2019-2020")));
+            assert_eq!(sanctions[1].season, Some(String::from("2018-2019")));
 
-            assert_eq!(error, DbError::NotFound);
+            match conn.set_current_season(team_id, Some("2019")).unwrap_err() {
+                DbError::NotValid(_) => {}
+                err => panic!("Unexpected error {:?}", err),
+            }
+            assert_eq!(
+                conn.set_current_season(team_id, None)
+                    .unwrap()
+                    .current_season,
+                None
+            );
 
             Ok(())
         });
     }
 
     #[test]
-    fn test_get_team() {
+    fn test_get_team_with_users() {
         let conn = init_connection();
 
         conn.deref().test_transaction::<_, Error, _>(|| {
-            let created_team = conn.create_team(&Team::default()).unwrap();
+            let team = conn
+                .create_team(
+                    &Team {
+                        name: String::from("Les Arbres"),
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap();
+            let first_user = conn
+                .create_user(&User {
+                    team_id: team.id,
+                    firstname: String::from("Alice"),
+                    lastname: String::from("Aubert"),
+                    ..Default::default()
+                })
+                .unwrap();
+            let second_user = conn
+                .create_user(&User {
+                    id: Uuid::new_v4(),
+                    team_id: team.id,
+                    firstname: String::from("Bruno"),
+                    lastname: String::from("Barbier"),
+                    ..Default::default()
+                })
+                .unwrap();
 
-            let team = conn.get_team(created_team.id).unwrap();
+            let (fetched_team, users) = conn.get_team_with_users(team.id).unwrap();
 
-            assert_eq!(team, created_team);
+            assert_eq!(fetched_team.id, team.id);
+            assert_eq!(fetched_team.name, team.name);
+            assert_eq!(users, vec![first_user, second_user]);
+
+            let (_, users) = conn
+                .get_team_with_users(
+                    conn.create_team(
+                        &Team {
+                            id: Uuid::new_v4(),
+                            name: String::from("Les Pierres"),
+                            ..Default::default()
+                        },
+                        &unique_admin(),
+                    )
+                    .unwrap()
+                    .id,
+                )
+                .unwrap();
+
+            assert!(users.is_empty());
+            assert_eq!(
+                conn.get_team_with_users(Uuid::new_v4()),
+                Err(DbError::NotFound)
+            );
 
             Ok(())
         });
     }
 
     #[test]
-    fn test_get_unexisting_team() {
+    fn test_get_rules_by_tag() {
         let conn = init_connection();
 
-        let error = conn.get_team(Uuid::new_v4()).unwrap_err();
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+            let late = conn
+                .add_rule(
+                    team_id,
+                    &Rule {
+                        id: Uuid::new_v4(),
+                        name: String::from("Late"),
+                        tags: vec![
+                            String::from(" attendance "),
+                            String::from("attendance"),
+                            String::from(""),
+                        ],
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            let forgotten_jersey = conn
+                .add_rule(
+                    team_id,
+                    &Rule {
+                        id: Uuid::new_v4(),
+                        name: String::from("Forgotten jersey"),
+                        tags: vec![String::from("equipment"), String::from("attendance")],
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
 
-        assert_eq!(error, DbError::NotFound);
+            assert_eq!(late.tags, vec![String::from("attendance")]);
+            assert_eq!(
+                conn.get_rules_by_tag(team_id, "attendance").unwrap(),
+                vec![late, forgotten_jersey.clone()]
+            );
+            assert_eq!(
+                conn.get_rules_by_tag(team_id, "equipment").unwrap(),
+                vec![forgotten_jersey]
+            );
+
+            Ok(())
+        });
     }
 
     #[test]
-    fn test_create_team() {
+    fn test_replace_rules() {
         let conn = init_connection();
 
         conn.deref().test_transaction::<_, Error, _>(|| {
-            conn.create_team(&Team::default()).unwrap();
+            let late = Rule {
+                id: Uuid::new_v4(),
+                name: String::from("Late"),
+                ..Default::default()
+            };
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: vec![late.clone()],
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let new_rules = vec![
+                Rule {
+                    id: Uuid::new_v4(),
+                    name: String::from("Forgotten jersey"),
+                    tags: vec![String::from(" equipment ")],
+                    ..Default::default()
+                },
+                Rule {
+                    id: Uuid::new_v4(),
+                    name: String::from("Missed training"),
+                    ..Default::default()
+                },
+            ];
+
+            let rules = conn.replace_rules(team_id, &new_rules).unwrap();
+
+            assert_eq!(rules.len(), 2);
+            assert_eq!(rules[0].tags, vec![String::from("equipment")]);
+            assert_eq!(rules[1], new_rules[1]);
+            assert_eq!(conn.get_team(team_id).unwrap().rules, rules);
+
+            match conn
+                .replace_rules(team_id, &[late.clone(), late.clone()])
+                .unwrap_err()
+            {
+                DbError::NotValid(_) => {}
+                err => panic!("Unexpected error {:?}", err),
+            }
+            assert_eq!(
+                conn.replace_rules(Uuid::new_v4(), &[late]).unwrap_err(),
+                DbError::NotFound
+            );
 
             Ok(())
-        })
+        });
     }
 
     #[test]
-    fn test_update_team() {
+    fn test_replace_rules_referenced_by_sanctions() {
         let conn = init_connection();
 
         conn.deref().test_transaction::<_, Error, _>(|| {
-            let id = conn.create_team(&Team::default()).unwrap().id;
+            let rules: Vec<Rule> = (0..2)
+                .map(|index| Rule {
+                    id: Uuid::new_v4(),
+                    name: format!("Rule {}", index),
+                    ..Default::default()
+                })
+                .collect();
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: rules.clone(),
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            conn.create_sanctions(&[CreateSanction {
+                user_id,
+                team_id,
+                sanction_info: SanctionInfo {
+                    associated_rule: rules[0].id,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }])
+            .unwrap();
 
-            let name = String::from("New name");
+            match conn
+                .replace_rules(team_id, &[rules[1].clone()])
+                .unwrap_err()
+            {
+                DbError::Forbidden(_) => {}
+                err => panic!("Unexpected error {:?}", err),
+            }
+            assert_eq!(conn.get_team(team_id).unwrap().rules, rules);
+
+            let rules = conn.replace_rules(team_id, &[rules[0].clone()]).unwrap();
+
+            assert_eq!(conn.get_team(team_id).unwrap().rules, rules);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_set_max_balance() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
 
             let team = conn
-                .update_team(
-                    id,
+                .set_max_balance(team_id, Some(Amount::from_cents(5000)), true)
+                .unwrap();
+
+            assert_eq!(team.max_balance, Some(Amount::from_cents(5000)));
+            assert!(team.clamp_to_max_balance);
+
+            match conn
+                .set_max_balance(team_id, Some(Amount::from_cents(-1)), false)
+                .unwrap_err()
+            {
+                DbError::NotValid(_) => {}
+                err => panic!("Unexpected error {:?}", err),
+            }
+
+            let team = conn.set_max_balance(team_id, None, false).unwrap();
+
+            assert_eq!(team.max_balance, None);
+            assert!(!team.clamp_to_max_balance);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_max_rules() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+            assert_eq!(conn.get_team(team_id).unwrap().max_rules, DEFAULT_MAX_RULES);
+
+            conn.set_max_rules(team_id, 2).unwrap();
+
+            let rule = |name: &str| Rule {
+                id: Uuid::new_v4(),
+                name: String::from(name),
+                ..Default::default()
+            };
+            conn.add_rule(team_id, &rule("first")).unwrap();
+            conn.add_rule(team_id, &rule("second")).unwrap();
+
+            assert_eq!(
+                conn.add_rule(team_id, &rule("third")).unwrap_err(),
+                DbError::NotValid(String::from("The field rules must not be more than 2"))
+            );
+            assert_eq!(
+                conn.replace_rules(team_id, &[rule("first"), rule("second"), rule("third")])
+                    .unwrap_err(),
+                DbError::NotValid(String::from("The field rules must not be more than 2"))
+            );
+            assert_eq!(conn.get_team(team_id).unwrap().rules.len(), 2);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_update_team_over_max_rules() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+            conn.set_max_rules(team_id, 2).unwrap();
+
+            let rule = |name: &str| Rule {
+                id: Uuid::new_v4(),
+                name: String::from(name),
+                ..Default::default()
+            };
+
+            assert_eq!(
+                conn.update_team(
+                    team_id,
                     &UpdateTeam {
-                        name: name.clone(),
+                        rules: vec![rule("first"), rule("second"), rule("third")],
+                        ..Default::default()
+                    },
+                )
+                .unwrap_err(),
+                DbError::NotValid(String::from("The field rules must not be more than 2"))
+            );
+            assert_eq!(
+                conn.update_team(
+                    team_id,
+                    &UpdateTeam {
+                        rules: vec![rule("first"), rule("first")],
+                        ..Default::default()
+                    },
+                )
+                .unwrap_err(),
+                DbError::NotValid(String::from(
+                    "The field name must be unique but first is used twice"
+                ))
+            );
+            assert!(conn.get_team(team_id).unwrap().rules.is_empty());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_rotate_team_token() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+
+            assert_eq!(
+                conn.verify_team_token(team_id, "").unwrap_err(),
+                DbError::NotFound
+            );
+
+            let old_token = conn.rotate_team_token(team_id).unwrap();
+
+            assert_eq!(
+                conn.verify_team_token(team_id, &old_token).unwrap(),
+                team_id
+            );
+            assert_ne!(
+                conn.get_team(team_id).unwrap().token_hash,
+                Some(old_token.clone())
+            );
+
+            let new_token = conn.rotate_team_token(team_id).unwrap();
+
+            assert_ne!(new_token, old_token);
+            assert_eq!(
+                conn.verify_team_token(team_id, &new_token).unwrap(),
+                team_id
+            );
+            assert_eq!(
+                conn.verify_team_token(team_id, &old_token).unwrap_err(),
+                DbError::NotFound
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_get_teams_for_email() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team = |name: &str| {
+                conn.create_team(
+                    &Team {
+                        id: Uuid::new_v4(),
+                        name: name.to_string(),
                         ..Default::default()
                     },
+                    &unique_admin(),
                 )
+                .unwrap()
+            };
+            let first_team = team("Team A");
+            let second_team = team("Team B");
+            let other_team = team("Team C");
+
+            for (team_id, email) in [
+                (second_team.id, "john@example.com"),
+                (first_team.id, "John@Example.com"),
+                (other_team.id, "jane@example.com"),
+            ] {
+                conn.create_user(&User {
+                    id: Uuid::new_v4(),
+                    team_id,
+                    email: Some(String::from(email)),
+                    ..Default::default()
+                })
                 .unwrap();
+            }
 
-            assert_eq!(id, team.id);
-            assert_eq!(team.name, name);
+            let teams = conn.get_teams_for_email("john@example.com").unwrap();
+
+            assert_eq!(
+                teams.iter().map(|team| team.id).collect::<Vec<Uuid>>(),
+                vec![first_team.id, second_team.id]
+            );
+            assert!(teams.iter().all(|team| team.token_hash.is_none()));
 
             Ok(())
         });
     }
 
     #[test]
-    fn test_update_unexisting_team() {
+    fn test_import_rules() {
         let conn = init_connection();
 
-        let error = conn
-            .update_team(Uuid::new_v4(), &UpdateTeam::default())
-            .unwrap_err();
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let late = Rule {
+                id: Uuid::new_v4(),
+                name: String::from("Late"),
+                ..Default::default()
+            };
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: vec![late.clone()],
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
 
-        assert_eq!(error, DbError::NotFound);
+            let inputs: Vec<RuleInput> = ["Absent", "Forgotten jersey", "Yellow card"]
+                .iter()
+                .map(|name| RuleInput {
+                    name: name.to_string(),
+                    kind: RuleKind::Basic {
+                        price: Amount::from_cents(200),
+                    },
+                    ..Default::default()
+                })
+                .collect();
+
+            let rules = conn.import_rules(team_id, inputs).unwrap();
+
+            assert_eq!(
+                rules
+                    .iter()
+                    .map(|rule| rule.name.as_str())
+                    .collect::<Vec<&str>>(),
+                vec!["Absent", "Forgotten jersey", "Yellow card"]
+            );
+            assert!(rules.iter().all(|rule| rule.id != late.id));
+            assert_eq!(conn.get_team(team_id).unwrap().rules.len(), 4);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_import_rules_with_duplicated_name() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let late = Rule {
+                id: Uuid::new_v4(),
+                name: String::from("Late"),
+                ..Default::default()
+            };
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: vec![late.clone()],
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+
+            let error = conn
+                .import_rules(
+                    team_id,
+                    vec![
+                        RuleInput {
+                            name: String::from("Absent"),
+                            ..Default::default()
+                        },
+                        RuleInput {
+                            name: String::from("Late"),
+                            ..Default::default()
+                        },
+                    ],
+                )
+                .unwrap_err();
+
+            assert_eq!(
+                error,
+                DbError::NotValid(String::from(
+                    "The field name must be unique but Late is used twice"
+                ))
+            );
+            assert_eq!(conn.get_team(team_id).unwrap().rules, vec![late]);
+
+            Ok(())
+        });
     }
 }
@@ -1,52 +1,206 @@
-use rouille::{input::json::json_input, router, Request};
+use log::error;
+use rouille::{router, Request};
 use serde::Serialize;
 use uuid::Uuid;
 
 use super::{
     interface::TeamsDb,
-    models::{LoginRequest, LoginResponse, Team, UpdateTeam, UpdateTeamRequest},
+    models::{
+        validate_rules, CreateTeamRequest, LoginCodeResponse, LoginRequest, LoginResponse, Rule,
+        Team, TeamLoginRequest, TeamResponse, TeamSummary, TokenResponse, UpdateRuleRequest,
+        UpdateTeam, UpdateTeamRequest, VerifyLoginCodeRequest,
+    },
 };
-use crate::api::models::ErrorResponse;
+use crate::admins::{
+    interface::AdminsDb,
+    models::Admin,
+    utils::login_code::{send_code, CODE_VALIDITY_MINUTES},
+};
+use crate::api::{
+    models::{ErrorResponse, NotFoundAs, Resource},
+    utils::{parse_json_body, parse_uuid},
+};
+use crate::database::postgres::DbError;
 
 #[derive(Serialize, Debug)]
 #[serde(untagged)]
 pub enum ResponseWrapper {
     Login(LoginResponse),
-    Team(Team),
+    LoginCode(LoginCodeResponse),
+    Team(TeamResponse),
+    Rule(Rule),
+    Rules(Vec<Rule>),
+    TeamSummary(TeamSummary),
+    Token(TokenResponse),
+}
+
+fn authenticate<T>(
+    db: &T,
+    team_id: Uuid,
+    username: &str,
+    password: &str,
+    code: Option<&str>,
+) -> Result<Admin, ErrorResponse>
+where
+    T: AdminsDb,
+{
+    let admin = match db.verify_admin(team_id, username, password) {
+        Ok(admin) => admin,
+        Err(DbError::NotFound) => return Err(ErrorResponse::unauthorized()),
+        Err(error) => return Err(error.into()),
+    };
+
+    if !admin.two_factor_enabled {
+        return Ok(admin);
+    }
+
+    let code = code.ok_or_else(ErrorResponse::login_code_required)?;
+
+    match db.verify_login_code(team_id, username, code) {
+        Ok(admin) => Ok(admin),
+        Err(DbError::NotFound) => Err(ErrorResponse::unauthorized()),
+        Err(error) => Err(error.into()),
+    }
 }
 
-pub fn handle_request<T>(request: &Request, db: &T) -> Result<ResponseWrapper, ErrorResponse>
+pub fn handle_request<T>(
+    request: &Request,
+    db: &T,
+    password_min_length: usize,
+) -> Result<ResponseWrapper, ErrorResponse>
 where
-    T: TeamsDb,
+    T: TeamsDb + AdminsDb,
 {
     router!(request,
         (POST) (/login) => {
-            let input: LoginRequest = json_input(request)?;
+            let input: LoginRequest = parse_json_body(request)?;
+
+            let team_id = db.login(&input.name)?;
 
-            let team_id = db.login(&input.name, &input.admin_password)?;
+            if let Some(password) = &input.admin_password {
+                let username = input.admin_username.as_deref().unwrap_or_default();
+
+                authenticate(db, team_id, username, password, input.admin_code.as_deref())?;
+            }
 
             let result: LoginResponse = (input, team_id).into();
 
             Ok(ResponseWrapper::Login(result))
         },
         (POST) (/teams) => {
-            let input: Team = json_input::<UpdateTeamRequest>(request)?.into();
+            let input = parse_json_body::<CreateTeamRequest>(request)?;
+            input.validate(password_min_length)?;
+            let admin = input.admin(password_min_length)?;
+
+            let input: Team = input.team.into();
+
+            let result: Team = db.create_team(&input, &admin)?;
 
-            let result: Team = db.create_team(&input)?;
+            Ok(ResponseWrapper::Team(result.into()))
+        },
+        (GET) (/teams/{id: String}) => {
+            let id = parse_uuid(&id)?;
+
+            let result: Team = db.get_team(id).not_found_as(Resource::Team)?;
 
-            Ok(ResponseWrapper::Team(result))
+            Ok(ResponseWrapper::Team(result.into()))
         },
-        (GET) (/teams/{id:Uuid}) => {
-            let result: Team = db.get_team(id)?;
+        (POST) (/teams/{id: String}) => {
+            let id = parse_uuid(&id)?;
+
+            let input = parse_json_body::<UpdateTeamRequest>(request)?;
+            input.validate()?;
+
+            let input: UpdateTeam = input.into();
+
+            let result: Team = db.update_team(id, &input).not_found_as(Resource::Team)?;
+
+            Ok(ResponseWrapper::Team(result.into()))
+        },
+        (POST) (/teams/{id: String}/login) => {
+            let id = parse_uuid(&id)?;
+
+            let input: TeamLoginRequest = parse_json_body(request)?;
+
+            let team: Team = db.get_team(id).not_found_as(Resource::Team)?;
+
+            authenticate(db, id, &input.username, &input.password, input.code.as_deref())?;
+
+            Ok(ResponseWrapper::TeamSummary(team.into()))
+        },
+        (POST) (/teams/{id: String}/login/code) => {
+            let id = parse_uuid(&id)?;
+
+            let input: TeamLoginRequest = parse_json_body(request)?;
+
+            db.get_team(id).not_found_as(Resource::Team)?;
+
+            let (admin, code) = match db.request_login_code(id, &input.username, &input.password) {
+                Err(DbError::NotFound) => return Err(ErrorResponse::unauthorized()),
+                result => result?,
+            };
+            let email = admin.email.unwrap_or_default();
+
+            send_code(&email, &code).map_err(|err| {
+                error!("The login code can't be sent to {} : {}", email, err);
+                ErrorResponse::from(DbError::Unknown)
+            })?;
+
+            Ok(ResponseWrapper::LoginCode(LoginCodeResponse {
+                validity_minutes: CODE_VALIDITY_MINUTES,
+            }))
+        },
+        (POST) (/teams/{id: String}/login/verify) => {
+            let id = parse_uuid(&id)?;
+
+            let input: VerifyLoginCodeRequest = parse_json_body(request)?;
+
+            let team: Team = db.get_team(id).not_found_as(Resource::Team)?;
+
+            match db.verify_login_code(id, &input.username, &input.code) {
+                Err(DbError::NotFound) => return Err(ErrorResponse::unauthorized()),
+                result => result?,
+            };
+
+            Ok(ResponseWrapper::TeamSummary(team.into()))
+        },
+        (POST) (/teams/{id: String}/token) => {
+            let id = parse_uuid(&id)?;
+
+            let input: TeamLoginRequest = parse_json_body(request)?;
+
+            db.get_team(id).not_found_as(Resource::Team)?;
+
+            authenticate(db, id, &input.username, &input.password, input.code.as_deref())?;
+
+            let token = db.rotate_team_token(id).not_found_as(Resource::Team)?;
+
+            Ok(ResponseWrapper::Token(TokenResponse { token }))
+        },
+        (POST) (/teams/{id: String}/rules) => {
+            let id = parse_uuid(&id)?;
+
+            let input = parse_json_body::<UpdateRuleRequest>(request)?;
+            input.validate()?;
 
-            Ok(ResponseWrapper::Team(result))
+            let input: Rule = input.into();
+
+            let result: Rule = db.add_rule(id, &input).not_found_as(Resource::Team)?;
+
+            Ok(ResponseWrapper::Rule(result))
         },
-        (POST) (/teams/{id:Uuid}) => {
-            let input: UpdateTeam = json_input::<UpdateTeamRequest>(request)?.into();
+        (PUT) (/teams/{id: String}/rules) => {
+            let id = parse_uuid(&id)?;
+
+            let input: Vec<Rule> = parse_json_body::<Vec<UpdateRuleRequest>>(request)?
+                .into_iter()
+                .map(|rule| rule.into())
+                .collect();
+            validate_rules(&input)?;
 
-            let result: Team = db.update_team(id, &input)?;
+            let result: Vec<Rule> = db.replace_rules(id, &input).not_found_as(Resource::Team)?;
 
-            Ok(ResponseWrapper::Team(result))
+            Ok(ResponseWrapper::Rules(result))
         },
         _ => {
             Err(ErrorResponse::not_found())
@@ -57,10 +211,13 @@ where
 #[cfg(test)]
 mod tests {
     use serde_json::json;
+    use uuid::Uuid;
 
     use super::*;
+    use crate::admins::utils::login_code::SENT_MESSAGES;
+    use crate::admins::utils::password::DEFAULT_MIN_LENGTH;
     use crate::api::models::{test_utils::RequestBuilder, ErrorKind};
-    use crate::test_utils::routes::{DbMock, TeamsDbMock};
+    use crate::test_utils::routes::{AdminsDbMock, DbMock, TeamsDbMock};
 
     #[test]
     fn test_login() {
@@ -71,26 +228,30 @@ mod tests {
         let response = json!(handle_request(
             &RequestBuilder::post(String::from("/login"), &login_request),
             &DbMock::default(),
+            DEFAULT_MIN_LENGTH
         )
         .unwrap());
 
-        assert_eq!(response["admin_password"], serde_json::Value::Null);
+        assert_eq!(response["adminPassword"], serde_json::Value::Null);
     }
 
     #[test]
     fn test_login_with_password() {
         let login_request = json!({
             "name": "CHBC",
+            "admin_username": "coach",
             "admin_password": "password"
         });
 
         let response = json!(handle_request(
             &RequestBuilder::post(String::from("/login"), &login_request),
             &DbMock::default(),
+            DEFAULT_MIN_LENGTH
         )
         .unwrap());
 
-        assert_eq!(response["admin_password"], login_request["admin_password"]);
+        assert_eq!(response["adminUsername"], login_request["admin_username"]);
+        assert_eq!(response["adminPassword"], login_request["admin_password"]);
     }
 
     #[test]
@@ -105,6 +266,7 @@ mod tests {
                 teams_db: TeamsDbMock::NotFound,
                 ..Default::default()
             },
+            DEFAULT_MIN_LENGTH,
         )
         .unwrap_err();
 
@@ -118,10 +280,26 @@ mod tests {
                 teams_db: TeamsDbMock::NotFound,
                 ..Default::default()
             },
+            DEFAULT_MIN_LENGTH,
         )
         .unwrap_err();
 
         assert_eq!(error.kind, ErrorKind::NotFound);
+
+        login_request["admin_username"] = json!("coach");
+        login_request["admin_password"] = json!("wrong password");
+
+        let error = handle_request(
+            &RequestBuilder::post(String::from("/login"), &login_request),
+            &DbMock {
+                admins_db: AdminsDbMock::SuccessWithPassword(String::from("password")),
+                ..Default::default()
+            },
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::Unauthorized);
     }
 
     #[test]
@@ -131,6 +309,7 @@ mod tests {
         let response = json!(handle_request(
             &RequestBuilder::get(format!("/teams/{}", id)),
             &DbMock::default(),
+            DEFAULT_MIN_LENGTH
         )
         .unwrap());
 
@@ -147,10 +326,12 @@ mod tests {
                 teams_db: TeamsDbMock::NotFound,
                 ..Default::default()
             },
+            DEFAULT_MIN_LENGTH,
         )
         .unwrap_err();
 
         assert_eq!(error.kind, ErrorKind::NotFound);
+        assert_eq!(error.description, "Team not found");
 
         let error = handle_request(
             &RequestBuilder::get(format!("/teams/{}", id)),
@@ -158,6 +339,7 @@ mod tests {
                 teams_db: TeamsDbMock::Unknown,
                 ..Default::default()
             },
+            DEFAULT_MIN_LENGTH,
         )
         .unwrap_err();
 
@@ -171,6 +353,7 @@ mod tests {
         let team = json!({
             "id": team_id,
             "name": "Test_team",
+            "admin_username": "coach",
             "admin_password": "password",
             "rules": [{
                 "name": "Rule_Test",
@@ -187,16 +370,19 @@ mod tests {
         let response = json!(handle_request(
             &RequestBuilder::post(String::from("/teams"), &team),
             &DbMock::default(),
+            DEFAULT_MIN_LENGTH
         )
         .unwrap());
 
         assert_eq!(response["id"], json!(team_id));
+        assert!(response.get("adminPassword").is_none());
     }
 
     #[test]
     fn test_create_team_fails() {
         let team = json!({
             "name": "Test_team",
+            "admin_username": "coach",
             "admin_password": "password",
             "rules": []
         });
@@ -207,6 +393,7 @@ mod tests {
                 teams_db: TeamsDbMock::Unknown,
                 ..Default::default()
             },
+            DEFAULT_MIN_LENGTH,
         )
         .unwrap_err();
 
@@ -217,10 +404,124 @@ mod tests {
         let error = handle_request(
             &RequestBuilder::post(String::from("/teams"), &invalid_json),
             &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
         )
         .unwrap_err();
 
         assert_eq!(error.kind, ErrorKind::Json);
+
+        let team = json!({
+            "name": "Test_team",
+            "admin_password": "password",
+            "rules": []
+        });
+
+        let error = handle_request(
+            &RequestBuilder::post(String::from("/teams"), &team),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::NotValid);
+        assert_eq!(
+            json!(error)["fields"],
+            json!({ "admin_username": ["must be given to create a team"] })
+        );
+
+        let team = json!({
+            "name": "Test_team",
+            "admin_username": "coach",
+            "rules": []
+        });
+
+        let error = handle_request(
+            &RequestBuilder::post(String::from("/teams"), &team),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::NotValid);
+        assert_eq!(
+            json!(error)["fields"],
+            json!({ "admin_password": ["must be given to create a team"] })
+        );
+
+        let team = json!({
+            "name": "Test_team",
+            "admin_username": "coach",
+            "admin_password": "short",
+            "rules": []
+        });
+
+        let error = handle_request(
+            &RequestBuilder::post(String::from("/teams"), &team),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::NotValid);
+        assert_eq!(
+            json!(error)["fields"],
+            json!({ "admin_password": ["must be at least 8 characters long"] })
+        );
+
+        let team = json!({
+            "name": "Test_team",
+            "admin_username": "coach",
+            "admin_password": "password",
+            "rules": []
+        });
+
+        let error = handle_request(
+            &RequestBuilder::post(String::from("/teams"), &team),
+            &DbMock::default(),
+            12,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            json!(error)["fields"],
+            json!({ "admin_password": ["must be at least 12 characters long"] })
+        );
+
+        let team = json!({
+            "name": "Test_team",
+            "admin_password": "password",
+            "rules": [],
+            "current_season": "2019-2021"
+        });
+
+        let error = handle_request(
+            &RequestBuilder::post(String::from("/teams"), &team),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::NotValid);
+
+        let team = json!({
+            "name": "Test_team",
+            "admin_password": "password",
+            "rules": [],
+            "default_rule_id": Uuid::new_v4()
+        });
+
+        let error = handle_request(
+            &RequestBuilder::post(String::from("/teams"), &team),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::NotValid);
+        assert_eq!(
+            error.description,
+            "The field default_rule_id must refer to one of the team's rules"
+        );
     }
 
     #[test]
@@ -229,17 +530,42 @@ mod tests {
 
         let team = json!({
             "name": "Test_team",
-            "admin_password": "password",
             "rules": []
         });
 
         let response = json!(handle_request(
             &RequestBuilder::post(format!("/teams/{}", id), &team),
             &DbMock::default(),
+            DEFAULT_MIN_LENGTH
         )
         .unwrap());
 
         assert_eq!(response["id"], json!(id));
+
+        let rule_id = Uuid::new_v4();
+        let team = json!({
+            "name": "Test_team",
+            "rules": [{
+                "id": rule_id,
+                "name": "Late",
+                "category": "GAME_DAY",
+                "description": "Being late on game day",
+                "kind": {
+                    "type": "BASIC",
+                    "price": 2.5
+                }
+            }],
+            "default_rule_id": rule_id
+        });
+
+        let response = json!(handle_request(
+            &RequestBuilder::post(format!("/teams/{}", id), &team),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH
+        )
+        .unwrap());
+
+        assert_eq!(response["defaultRuleId"], json!(rule_id));
     }
 
     #[test]
@@ -247,7 +573,6 @@ mod tests {
         let id = Uuid::new_v4();
         let team = json!({
             "name": "Test_team",
-            "admin_password": "password",
             "rules": []
         });
 
@@ -257,6 +582,7 @@ mod tests {
                 teams_db: TeamsDbMock::NotFound,
                 ..Default::default()
             },
+            DEFAULT_MIN_LENGTH,
         )
         .unwrap_err();
 
@@ -268,9 +594,460 @@ mod tests {
                 teams_db: TeamsDbMock::Unknown,
                 ..Default::default()
             },
+            DEFAULT_MIN_LENGTH,
         )
         .unwrap_err();
 
         assert_eq!(error.kind, ErrorKind::Unknown);
     }
+
+    #[test]
+    fn test_add_rule() {
+        let id = Uuid::new_v4();
+
+        let rule = json!({
+            "name": "Late",
+            "category": "GAME_DAY",
+            "description": "Being late on game day",
+            "kind": {
+                "type": "BASIC",
+                "price": 2.5
+            }
+        });
+
+        let response = json!(handle_request(
+            &RequestBuilder::post(format!("/teams/{}/rules", id), &rule),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH
+        )
+        .unwrap());
+
+        assert_eq!(response["name"], rule["name"]);
+        assert_eq!(response["kind"], rule["kind"]);
+    }
+
+    #[test]
+    fn test_add_rule_with_unknown_category() {
+        let rule = json!({
+            "name": "Late",
+            "category": "WEEKEND",
+            "description": "Being late on game day",
+            "kind": {
+                "type": "BASIC",
+                "price": 2.5
+            }
+        });
+
+        let error = handle_request(
+            &RequestBuilder::post(format!("/teams/{}/rules", Uuid::new_v4()), &rule),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::InvalidValue);
+        assert_eq!(error.kind.status_code(), 422);
+        assert!(error.description.starts_with(
+            "The request body has an invalid value: unknown variant `WEEKEND`, \
+             expected `GAME_DAY` or `TRAINING_DAY`"
+        ));
+    }
+
+    #[test]
+    fn test_add_rule_fails() {
+        let id = Uuid::new_v4();
+
+        let rule = json!({
+            "name": "Late",
+            "category": "GAME_DAY",
+            "description": "Being late on game day",
+            "kind": {
+                "type": "BASIC",
+                "price": 2.5
+            }
+        });
+
+        let error = handle_request(
+            &RequestBuilder::post(format!("/teams/{}/rules", id), &rule),
+            &DbMock {
+                teams_db: TeamsDbMock::NotFound,
+                ..Default::default()
+            },
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::NotFound);
+
+        let rule = json!({
+            "name": "Win bonus",
+            "category": "GAME_DAY",
+            "description": "A share of the win bonus",
+            "kind": {
+                "type": "PERCENTAGE",
+                "rate": 1.5
+            }
+        });
+
+        let error = handle_request(
+            &RequestBuilder::post(format!("/teams/{}/rules", id), &rule),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::NotValid);
+        assert_eq!(error.description, "The field rate must be between 0 and 1");
+    }
+
+    #[test]
+    fn test_add_rule_price_validation() {
+        let id = Uuid::new_v4();
+        let rule = |kind: serde_json::Value| {
+            json!({
+                "name": "Late",
+                "category": "GAME_DAY",
+                "description": "Being late on game day",
+                "kind": kind
+            })
+        };
+
+        for kind in &[
+            json!({ "type": "BASIC", "price": 0.0 }),
+            json!({ "type": "BASIC", "price": 2.5 }),
+            json!({ "type": "MONTHLY", "price": 10.0 }),
+        ] {
+            assert!(handle_request(
+                &RequestBuilder::post(format!("/teams/{}/rules", id), &rule(kind.clone())),
+                &DbMock::default(),
+                DEFAULT_MIN_LENGTH
+            )
+            .is_ok());
+        }
+
+        let error = handle_request(
+            &RequestBuilder::post(
+                format!("/teams/{}/rules", id),
+                &rule(json!({ "type": "BASIC", "price": -2.5 })),
+            ),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::NotValid);
+        assert_eq!(error.description, "The field price must not be negative");
+
+        let error = handle_request(
+            &RequestBuilder::post(
+                format!("/teams/{}/rules", id),
+                &rule(json!({
+                    "type": "TIME_MULTIPLICATION",
+                    "price_per_time_unit": -0.5,
+                    "time_unit": "MINUTE"
+                })),
+            ),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error.description,
+            "The field price_per_time_unit must not be negative"
+        );
+    }
+
+    #[test]
+    fn test_replace_rules() {
+        let id = Uuid::new_v4();
+        let rule = |name: &str| {
+            json!({
+                "name": name,
+                "category": "GAME_DAY",
+                "description": "Being late on game day",
+                "kind": {
+                    "type": "BASIC",
+                    "price": 2.5
+                }
+            })
+        };
+
+        let response = json!(handle_request(
+            &RequestBuilder::put(
+                format!("/teams/{}/rules", id),
+                &json!([rule("Late"), rule("Forgotten jersey")])
+            ),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH
+        )
+        .unwrap());
+
+        assert_eq!(response[0]["name"], json!("Late"));
+        assert_eq!(response[1]["name"], json!("Forgotten jersey"));
+
+        let error = handle_request(
+            &RequestBuilder::put(
+                format!("/teams/{}/rules", id),
+                &json!([rule("Late"), rule("Late")]),
+            ),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::NotValid);
+        assert_eq!(
+            error.description,
+            "The field name must be unique but Late is used twice"
+        );
+
+        let error = handle_request(
+            &RequestBuilder::put(format!("/teams/{}/rules", id), &json!([rule("Late")])),
+            &DbMock {
+                teams_db: TeamsDbMock::NotFound,
+                ..Default::default()
+            },
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_malformed_team_id() {
+        let error = handle_request(
+            &RequestBuilder::get(String::from("/teams/not-an-id")),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::BadRequest);
+    }
+
+    #[test]
+    fn test_team_login() {
+        let id = Uuid::new_v4();
+
+        let response = json!(handle_request(
+            &RequestBuilder::post(
+                format!("/teams/{}/login", id),
+                &json!({ "username": "coach", "password": "password" })
+            ),
+            &DbMock {
+                admins_db: AdminsDbMock::SuccessWithPassword(String::from("password")),
+                ..Default::default()
+            },
+            DEFAULT_MIN_LENGTH
+        )
+        .unwrap());
+
+        assert_eq!(response["id"], json!(id));
+        assert_eq!(response.get("admin_password"), None);
+    }
+
+    #[test]
+    fn test_team_login_fails() {
+        let id = Uuid::new_v4();
+
+        let error = handle_request(
+            &RequestBuilder::post(
+                format!("/teams/{}/login", id),
+                &json!({ "username": "coach", "password": "wrong password" }),
+            ),
+            &DbMock {
+                admins_db: AdminsDbMock::SuccessWithPassword(String::from("password")),
+                ..Default::default()
+            },
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::Unauthorized);
+        assert_eq!(error.kind.status_code(), 401);
+    }
+
+    #[test]
+    fn test_rotate_team_token() {
+        let id = Uuid::new_v4();
+        let db = DbMock {
+            admins_db: AdminsDbMock::SuccessWithPassword(String::from("password")),
+            ..Default::default()
+        };
+
+        let response = json!(handle_request(
+            &RequestBuilder::post(
+                format!("/teams/{}/token", id),
+                &json!({ "username": "coach", "password": "password" })
+            ),
+            &db,
+            DEFAULT_MIN_LENGTH
+        )
+        .unwrap());
+
+        assert_eq!(response["token"], json!("token"));
+
+        let error = handle_request(
+            &RequestBuilder::post(
+                format!("/teams/{}/token", id),
+                &json!({ "username": "coach", "password": "wrong password" }),
+            ),
+            &db,
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::Unauthorized);
+    }
+
+    #[test]
+    fn test_team_login_with_two_factor() {
+        let id = Uuid::new_v4();
+        let db = DbMock {
+            admins_db: AdminsDbMock::SuccessWithTwoFactor(String::from("123456")),
+            ..Default::default()
+        };
+
+        let error = handle_request(
+            &RequestBuilder::post(
+                format!("/teams/{}/login", id),
+                &json!({ "username": "coach", "password": "password" }),
+            ),
+            &db,
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::Unauthorized);
+        assert_eq!(error.description, "A login code is required");
+
+        let error = handle_request(
+            &RequestBuilder::post(
+                format!("/teams/{}/token", id),
+                &json!({ "username": "coach", "password": "password", "code": "654321" }),
+            ),
+            &db,
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::Unauthorized);
+        assert_eq!(error.description, "The credentials are not valid");
+
+        let response = json!(handle_request(
+            &RequestBuilder::post(
+                format!("/teams/{}/login", id),
+                &json!({ "username": "coach", "password": "password", "code": "123456" }),
+            ),
+            &db,
+            DEFAULT_MIN_LENGTH
+        )
+        .unwrap());
+
+        assert_eq!(response["id"], json!(id));
+
+        let error = handle_request(
+            &RequestBuilder::post(
+                String::from("/login"),
+                &json!({
+                    "name": "CHBC",
+                    "admin_username": "coach",
+                    "admin_password": "password"
+                }),
+            ),
+            &db,
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.description, "A login code is required");
+    }
+
+    #[test]
+    fn test_request_login_code() {
+        let id = Uuid::new_v4();
+
+        let response = json!(handle_request(
+            &RequestBuilder::post(
+                format!("/teams/{}/login/code", id),
+                &json!({ "username": "coach", "password": "password" }),
+            ),
+            &DbMock {
+                admins_db: AdminsDbMock::SuccessWithTwoFactor(String::from("123456")),
+                ..Default::default()
+            },
+            DEFAULT_MIN_LENGTH
+        )
+        .unwrap());
+
+        assert_eq!(response["validityMinutes"], json!(CODE_VALIDITY_MINUTES));
+
+        let message = SENT_MESSAGES.with(|messages| messages.borrow_mut().pop().unwrap());
+
+        assert!(message.starts_with("To: coach@example.com\n"));
+        assert!(message.contains("123456"));
+
+        let error = handle_request(
+            &RequestBuilder::post(
+                format!("/teams/{}/login/code", id),
+                &json!({ "username": "coach", "password": "password" }),
+            ),
+            &DbMock::default(),
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::Forbidden);
+
+        let error = handle_request(
+            &RequestBuilder::post(
+                format!("/teams/{}/login/code", id),
+                &json!({ "username": "coach", "password": "wrong password" }),
+            ),
+            &DbMock {
+                admins_db: AdminsDbMock::SuccessWithPassword(String::from("password")),
+                ..Default::default()
+            },
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::Unauthorized);
+        assert!(SENT_MESSAGES.with(|messages| messages.borrow().is_empty()));
+    }
+
+    #[test]
+    fn test_verify_login_code() {
+        let id = Uuid::new_v4();
+        let db = DbMock {
+            admins_db: AdminsDbMock::SuccessWithTwoFactor(String::from("123456")),
+            ..Default::default()
+        };
+
+        let response = json!(handle_request(
+            &RequestBuilder::post(
+                format!("/teams/{}/login/verify", id),
+                &json!({ "username": "coach", "code": "123456" }),
+            ),
+            &db,
+            DEFAULT_MIN_LENGTH
+        )
+        .unwrap());
+
+        assert_eq!(response["id"], json!(id));
+
+        let error = handle_request(
+            &RequestBuilder::post(
+                format!("/teams/{}/login/verify", id),
+                &json!({ "username": "coach", "code": "654321" }),
+            ),
+            &db,
+            DEFAULT_MIN_LENGTH,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::Unauthorized);
+    }
 }
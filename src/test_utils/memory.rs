@@ -0,0 +1,1129 @@
+use chrono::{naive::NaiveDate, Utc};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::admins::{
+    models::NewAdmin,
+    utils::{
+        password::{hash_password, verify_password},
+        token::generate_token,
+    },
+};
+use crate::database::{
+    amount::Amount,
+    pagination::{Paginated, Pagination},
+    postgres::DbError,
+};
+use crate::sanctions::{
+    checks::{check_sanctions, SanctionChecksDb},
+    interface::SanctionsDb,
+    models::*,
+};
+use crate::teams::{
+    interface::TeamsDb,
+    models::{
+        normalize_tags, validate_max_balance, validate_rules, validate_rules_count,
+        validate_season, Rule, RuleInput, Team, UpdateTeam,
+    },
+};
+use crate::users::{
+    interface::UsersDb,
+    models::{ExportedSanction, UpdateUser, User, UserDataExport, UserPatch},
+    postgres::{ANONYMIZED_FIRSTNAME, ANONYMIZED_LASTNAME},
+};
+
+fn foreign_key_violation(key: &str) -> DbError {
+    DbError::ForeignKeyViolation(format!("The key {} doesn't refer to anything", key))
+}
+
+fn unique_violation(field: &str) -> DbError {
+    DbError::UniqueViolation(format!(
+        "The field {} is already used by another user",
+        field
+    ))
+}
+
+fn sort_by_name(users: &mut [User]) {
+    users.sort_by(|a, b| (&a.lastname, &a.firstname, a.id).cmp(&(&b.lastname, &b.firstname, b.id)));
+}
+
+/// An in-memory database returning the same errors as Postgres, for the tests of the handlers.
+#[derive(Default)]
+pub struct MemoryDb {
+    pub teams: RefCell<Vec<Team>>,
+    pub users: RefCell<Vec<User>>,
+    pub sanctions: RefCell<Vec<Sanction>>,
+}
+
+impl MemoryDb {
+    fn update_user_with<F>(&self, team_id: Uuid, user_id: Uuid, update: F) -> Result<User, DbError>
+    where
+        F: FnOnce(&mut User),
+    {
+        let mut users = self.users.borrow_mut();
+        let user = users
+            .iter_mut()
+            .find(|user| user.team_id == team_id && user.id == user_id)
+            .ok_or(DbError::NotFound)?;
+
+        update(user);
+
+        Ok(user.clone())
+    }
+
+    fn update_team_with<F>(&self, team_id: Uuid, update: F) -> Result<Team, DbError>
+    where
+        F: FnOnce(&mut Team),
+    {
+        let mut teams = self.teams.borrow_mut();
+        let team = teams
+            .iter_mut()
+            .find(|team| team.id == team_id)
+            .ok_or(DbError::NotFound)?;
+
+        update(team);
+
+        Ok(team.clone())
+    }
+
+    fn check_rules_replacement(
+        &self,
+        team: &Team,
+        rules: &[Rule],
+        default_rule_id: Option<Uuid>,
+    ) -> Result<Vec<Rule>, DbError> {
+        validate_rules(rules)?;
+        let rules: Vec<Rule> = rules
+            .iter()
+            .map(|rule| Rule {
+                tags: normalize_tags(&rule.tags),
+                ..rule.clone()
+            })
+            .collect();
+        validate_rules_count(rules.len(), team.max_rules)?;
+
+        let sanctions = self.sanctions.borrow();
+        for rule in team
+            .rules
+            .iter()
+            .filter(|rule| !rules.iter().any(|new_rule| new_rule.id == rule.id))
+        {
+            if default_rule_id == Some(rule.id) {
+                return Err(DbError::Forbidden(format!(
+                    "The rule {} can't be removed as it is the team's default rule",
+                    rule.name
+                )));
+            }
+            if sanctions.iter().any(|sanction| {
+                sanction.team_id == team.id && sanction.sanction_info.associated_rule == rule.id
+            }) {
+                return Err(DbError::Forbidden(format!(
+                    "The rule {} can't be removed as sanctions refer to it",
+                    rule.name
+                )));
+            }
+        }
+
+        Ok(rules)
+    }
+
+    fn check_email(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+        email: &Option<String>,
+    ) -> Result<(), DbError> {
+        let users = self.users.borrow();
+        match email {
+            Some(email)
+                if users.iter().any(|user| {
+                    user.id != user_id
+                        && user.team_id == team_id
+                        && user.email.as_ref().map(|other| other.to_lowercase())
+                            == Some(email.to_lowercase())
+                }) =>
+            {
+                Err(unique_violation("email"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl UsersDb for MemoryDb {
+    fn get_users(&self, team_id: Uuid) -> Result<Vec<User>, DbError> {
+        Ok(self
+            .users
+            .borrow()
+            .iter()
+            .filter(|user| user.team_id == team_id)
+            .cloned()
+            .collect())
+    }
+
+    fn get_users_page(
+        &self,
+        team_id: Uuid,
+        pagination: &Pagination,
+    ) -> Result<Paginated<User>, DbError> {
+        let mut users = self.get_users(team_id)?;
+        let total = users.len() as i64;
+        sort_by_name(&mut users);
+
+        let users = users
+            .into_iter()
+            .skip(pagination.offset as usize)
+            .take(pagination.limit as usize)
+            .collect();
+
+        Ok(Paginated::new(users, total, pagination))
+    }
+
+    fn get_user(&self, team_id: Uuid, user_id: Uuid) -> Result<User, DbError> {
+        self.users
+            .borrow()
+            .iter()
+            .find(|user| user.team_id == team_id && user.id == user_id)
+            .cloned()
+            .ok_or(DbError::NotFound)
+    }
+
+    fn create_user(&self, user: &User) -> Result<User, DbError> {
+        if !self
+            .teams
+            .borrow()
+            .iter()
+            .any(|team| team.id == user.team_id)
+        {
+            return Err(foreign_key_violation("team_id"));
+        }
+        if self.users.borrow().iter().any(|other| other.id == user.id) {
+            return Err(unique_violation("users_pkey"));
+        }
+        self.check_email(user.team_id, user.id, &user.email)?;
+
+        self.users.borrow_mut().push(user.clone());
+
+        Ok(user.clone())
+    }
+
+    fn update_user(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+        user: &UpdateUser,
+    ) -> Result<User, DbError> {
+        self.check_email(team_id, user_id, &user.email)?;
+
+        self.update_user_with(team_id, user_id, |stored| {
+            stored.firstname = user.firstname.clone();
+            stored.lastname = user.lastname.clone();
+            stored.fine_multiplier = user.fine_multiplier;
+            if let Some(nickname) = &user.nickname {
+                stored.nickname = Some(nickname.clone());
+            }
+            if let Some(email) = &user.email {
+                stored.email = Some(email.clone());
+            }
+            if let Some(role) = &user.role {
+                stored.role = Some(role.clone());
+            }
+        })
+    }
+
+    fn patch_user(&self, team_id: Uuid, user_id: Uuid, patch: &UserPatch) -> Result<User, DbError> {
+        if let Some(email) = &patch.email {
+            self.check_email(team_id, user_id, email)?;
+        }
+
+        self.update_user_with(team_id, user_id, |stored| patch.apply_to(stored))
+    }
+
+    fn find_duplicate_users(&self, team_id: Uuid) -> Result<Vec<Vec<User>>, DbError> {
+        let mut users: Vec<User> = self
+            .get_users(team_id)?
+            .into_iter()
+            .filter(|user| !user.anonymized)
+            .collect();
+        sort_by_name(&mut users);
+
+        let mut groups: Vec<((String, String), Vec<User>)> = vec![];
+        for user in users {
+            let key = (user.firstname.to_lowercase(), user.lastname.to_lowercase());
+
+            match groups.iter_mut().find(|(group_key, _)| *group_key == key) {
+                Some((_, group)) => group.push(user),
+                None => groups.push((key, vec![user])),
+            }
+        }
+
+        Ok(groups
+            .into_iter()
+            .map(|(_, group)| group)
+            .filter(|group| group.len() > 1)
+            .collect())
+    }
+
+    fn export_user_data(&self, team_id: Uuid, user_id: Uuid) -> Result<UserDataExport, DbError> {
+        let user = self.get_user(team_id, user_id)?;
+        let team = self.get_team(team_id)?;
+
+        let mut sanctions: Vec<Sanction> = self
+            .sanctions
+            .borrow()
+            .iter()
+            .filter(|sanction| sanction.team_id == team_id && sanction.user_id == user_id)
+            .cloned()
+            .collect();
+        sanctions.sort_by_key(|sanction| (sanction.created_at, sanction.id));
+
+        let sanctions = sanctions
+            .into_iter()
+            .map(|sanction| ExportedSanction {
+                rule: team
+                    .rules
+                    .iter()
+                    .find(|rule| rule.id == sanction.sanction_info.associated_rule)
+                    .cloned(),
+                sanction,
+            })
+            .collect();
+
+        Ok(UserDataExport { user, sanctions })
+    }
+
+    fn anonymize_user(&self, team_id: Uuid, user_id: Uuid) -> Result<User, DbError> {
+        self.update_user_with(team_id, user_id, |user| {
+            user.firstname = String::from(ANONYMIZED_FIRSTNAME);
+            user.lastname = String::from(ANONYMIZED_LASTNAME);
+            user.nickname = None;
+            user.email = None;
+            user.anonymized = true;
+        })
+    }
+
+    fn get_users_without_email(&self, team_id: Uuid) -> Result<Vec<User>, DbError> {
+        let mut users: Vec<User> = self
+            .get_users(team_id)?
+            .into_iter()
+            .filter(|user| !user.anonymized && user.email.as_deref().unwrap_or("").is_empty())
+            .collect();
+        sort_by_name(&mut users);
+
+        Ok(users)
+    }
+
+    fn get_users_by_role(&self, team_id: Uuid, role: &str) -> Result<Vec<User>, DbError> {
+        let mut users: Vec<User> = self
+            .get_users(team_id)?
+            .into_iter()
+            .filter(|user| user.role.as_deref() == Some(role.trim()))
+            .collect();
+        sort_by_name(&mut users);
+
+        Ok(users)
+    }
+
+    fn upsert_user(&self, user: &User) -> Result<User, DbError> {
+        let existing_id = self
+            .users
+            .borrow()
+            .iter()
+            .find(|other| {
+                user.external_id.is_some()
+                    && other.team_id == user.team_id
+                    && other.external_id == user.external_id
+            })
+            .map(|other| other.id);
+
+        match existing_id {
+            Some(user_id) => {
+                self.check_email(user.team_id, user_id, &user.email)?;
+
+                self.update_user_with(user.team_id, user_id, |stored| {
+                    *stored = User {
+                        id: stored.id,
+                        anonymized: stored.anonymized,
+                        ..user.clone()
+                    }
+                })
+            }
+            None => self.create_user(user),
+        }
+    }
+}
+
+impl TeamsDb for MemoryDb {
+    fn login(&self, name: &str) -> Result<Uuid, DbError> {
+        self.teams
+            .borrow()
+            .iter()
+            .find(|team| team.name == name)
+            .map(|team| team.id)
+            .ok_or(DbError::NotFound)
+    }
+
+    fn get_team(&self, id: Uuid) -> Result<Team, DbError> {
+        self.teams
+            .borrow()
+            .iter()
+            .find(|team| team.id == id)
+            .cloned()
+            .ok_or(DbError::NotFound)
+    }
+
+    fn create_team(&self, team: &Team, _admin: &NewAdmin) -> Result<Team, DbError> {
+        {
+            let teams = self.teams.borrow();
+            if teams.iter().any(|other| other.id == team.id) {
+                return Err(unique_violation("teams_pkey"));
+            }
+            if teams.iter().any(|other| other.name == team.name) {
+                return Err(unique_violation("name"));
+            }
+        }
+
+        self.teams.borrow_mut().push(team.clone());
+
+        Ok(team.clone())
+    }
+
+    fn update_team(&self, id: Uuid, team: &UpdateTeam) -> Result<Team, DbError> {
+        if self
+            .teams
+            .borrow()
+            .iter()
+            .any(|other| other.id != id && other.name == team.name)
+        {
+            return Err(unique_violation("name"));
+        }
+        let stored_team = self.get_team(id)?;
+        let rules = self.check_rules_replacement(
+            &stored_team,
+            &team.rules,
+            team.default_rule_id.or(stored_team.default_rule_id),
+        )?;
+
+        self.update_team_with(id, |stored| {
+            stored.name = team.name.clone();
+            stored.rules = rules;
+            if let Some(current_season) = &team.current_season {
+                stored.current_season = Some(current_season.clone());
+            }
+            if let Some(default_rule_id) = team.default_rule_id {
+                stored.default_rule_id = Some(default_rule_id);
+            }
+            if let Some(allow_self_fine) = team.allow_self_fine {
+                stored.allow_self_fine = allow_self_fine;
+            }
+        })
+    }
+
+    fn add_rule(&self, team_id: Uuid, rule: &Rule) -> Result<Rule, DbError> {
+        let rule = Rule {
+            tags: normalize_tags(&rule.tags),
+            ..rule.clone()
+        };
+        let team = self.get_team(team_id)?;
+        validate_rules_count(team.rules.len() + 1, team.max_rules)?;
+
+        self.update_team_with(team_id, |team| team.rules.push(rule.clone()))?;
+
+        Ok(rule)
+    }
+
+    fn copy_rules(&self, source_team: Uuid, target_team: Uuid) -> Result<Vec<Rule>, DbError> {
+        let source = self.get_team(source_team)?;
+        let target = self.get_team(target_team)?;
+        validate_rules_count(target.rules.len() + source.rules.len(), target.max_rules)?;
+
+        let team = self.update_team_with(target_team, |team| {
+            team.rules.extend(source.rules.into_iter().map(|rule| Rule {
+                id: Uuid::new_v4(),
+                ..rule
+            }))
+        })?;
+
+        Ok(team.rules)
+    }
+
+    fn import_rules(&self, team_id: Uuid, rules: Vec<RuleInput>) -> Result<Vec<Rule>, DbError> {
+        for rule in &rules {
+            rule.validate()?;
+        }
+        let imported_rules: Vec<Rule> = rules.into_iter().map(Rule::from).collect();
+
+        let team = self.get_team(team_id)?;
+        let mut rules = team.rules;
+        rules.extend(imported_rules.iter().cloned());
+        validate_rules(&rules)?;
+        validate_rules_count(rules.len(), team.max_rules)?;
+
+        self.update_team_with(team_id, |team| team.rules = rules)?;
+
+        Ok(imported_rules)
+    }
+
+    fn set_current_season(&self, team_id: Uuid, season: Option<&str>) -> Result<Team, DbError> {
+        if let Some(season) = season {
+            validate_season(season)?;
+        }
+
+        self.update_team_with(team_id, |team| {
+            team.current_season = season.map(String::from)
+        })
+    }
+
+    fn get_team_with_users(&self, team_id: Uuid) -> Result<(Team, Vec<User>), DbError> {
+        let team = self.get_team(team_id)?;
+        let mut users = self.get_users(team_id)?;
+        sort_by_name(&mut users);
+
+        Ok((team, users))
+    }
+
+    fn get_rules_by_tag(&self, team_id: Uuid, tag: &str) -> Result<Vec<Rule>, DbError> {
+        let tag = tag.trim();
+
+        Ok(self
+            .get_team(team_id)?
+            .rules
+            .into_iter()
+            .filter(|rule| rule.tags.iter().any(|rule_tag| rule_tag == tag))
+            .collect())
+    }
+
+    fn replace_rules(&self, team_id: Uuid, rules: &[Rule]) -> Result<Vec<Rule>, DbError> {
+        let team = self.get_team(team_id)?;
+        let rules = self.check_rules_replacement(&team, rules, team.default_rule_id)?;
+
+        let team = self.update_team_with(team_id, |team| team.rules = rules)?;
+
+        Ok(team.rules)
+    }
+
+    fn set_max_balance(
+        &self,
+        team_id: Uuid,
+        max_balance: Option<Amount>,
+        clamp: bool,
+    ) -> Result<Team, DbError> {
+        if let Some(max_balance) = max_balance {
+            validate_max_balance(max_balance)?;
+        }
+
+        self.update_team_with(team_id, |team| {
+            team.max_balance = max_balance;
+            team.clamp_to_max_balance = clamp;
+        })
+    }
+
+    fn set_max_rules(&self, team_id: Uuid, max_rules: u32) -> Result<Team, DbError> {
+        self.update_team_with(team_id, |team| team.max_rules = max_rules as i32)
+    }
+
+    fn set_grace_days(&self, team_id: Uuid, grace_days: u32) -> Result<Team, DbError> {
+        self.update_team_with(team_id, |team| team.grace_days = grace_days as i32)
+    }
+
+    fn rotate_team_token(&self, team_id: Uuid) -> Result<String, DbError> {
+        let token = generate_token();
+        let token_hash = hash_password(&token)?;
+
+        self.update_team_with(team_id, |team| team.token_hash = Some(token_hash))?;
+
+        Ok(token)
+    }
+
+    fn verify_team_token(&self, team_id: Uuid, token: &str) -> Result<Uuid, DbError> {
+        match self.get_team(team_id)?.token_hash {
+            Some(token_hash) if verify_password(token, &token_hash) => Ok(team_id),
+            _ => Err(DbError::NotFound),
+        }
+    }
+
+    fn get_teams_for_email(&self, email: &str) -> Result<Vec<Team>, DbError> {
+        let team_ids: Vec<Uuid> = self
+            .users
+            .borrow()
+            .iter()
+            .filter(|user| {
+                user.email.as_ref().map(|other| other.to_lowercase()) == Some(email.to_lowercase())
+            })
+            .map(|user| user.team_id)
+            .collect();
+
+        let mut teams: Vec<Team> = self
+            .teams
+            .borrow()
+            .iter()
+            .filter(|team| team_ids.contains(&team.id))
+            .map(|team| Team {
+                token_hash: None,
+                ..team.clone()
+            })
+            .collect();
+        teams.sort_by(|a, b| (&a.name, a.id).cmp(&(&b.name, b.id)));
+
+        Ok(teams)
+    }
+}
+
+impl SanctionChecksDb for MemoryDb {
+    fn get_users_teams(&self, user_ids: &[Uuid]) -> Result<HashMap<Uuid, Uuid>, DbError> {
+        Ok(self
+            .users
+            .borrow()
+            .iter()
+            .filter(|user| user_ids.contains(&user.id))
+            .map(|user| (user.id, user.team_id))
+            .collect())
+    }
+
+    fn get_leaves(&self, user_ids: &[Uuid]) -> Result<HashMap<Uuid, NaiveDate>, DbError> {
+        Ok(self
+            .users
+            .borrow()
+            .iter()
+            .filter(|user| user_ids.contains(&user.id))
+            .filter_map(|user| {
+                user.on_leave_until
+                    .map(|on_leave_until| (user.id, on_leave_until))
+            })
+            .collect())
+    }
+
+    fn get_day_rules(&self, user_id: Uuid, date: NaiveDate) -> Result<Vec<(Uuid, bool)>, DbError> {
+        Ok(self
+            .sanctions
+            .borrow()
+            .iter()
+            .filter(|sanction| sanction.user_id == user_id && sanction.created_on() == date)
+            .map(|sanction| {
+                (
+                    sanction.sanction_info.associated_rule,
+                    sanction.waived_at.is_some(),
+                )
+            })
+            .collect())
+    }
+
+    fn get_admins_users(&self, _admin_ids: &[Uuid]) -> Result<HashMap<Uuid, Uuid>, DbError> {
+        Ok(HashMap::new())
+    }
+
+    fn get_unpaid_total_and_multiplier(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(Amount, f32), DbError> {
+        let fine_multiplier = self
+            .users
+            .borrow()
+            .iter()
+            .find(|user| user.id == user_id && user.team_id == team_id)
+            .ok_or(DbError::NotFound)?
+            .fine_multiplier;
+        let unpaid_total = self
+            .sanctions
+            .borrow()
+            .iter()
+            .filter(|sanction| {
+                sanction.team_id == team_id
+                    && sanction.user_id == user_id
+                    && sanction.paid_at.is_none()
+                    && sanction.waived_at.is_none()
+                    && sanction.archived_at.is_none()
+            })
+            .map(|sanction| sanction.price)
+            .sum();
+
+        Ok((unpaid_total, fine_multiplier))
+    }
+}
+
+impl MemoryDb {
+    /// No admin is stored, so the sanctions created by one break the foreign key.
+    fn insert_sanctions(
+        &self,
+        sanctions: &[CreateSanction],
+        override_leave: bool,
+    ) -> Result<Vec<Sanction>, DbError> {
+        let teams: HashMap<Uuid, Team> = self
+            .teams
+            .borrow()
+            .iter()
+            .filter(|team| sanctions.iter().any(|sanction| sanction.team_id == team.id))
+            .map(|team| (team.id, team.clone()))
+            .collect();
+        let sanctions = check_sanctions(self, &teams, sanctions, override_leave)?;
+
+        let mut stored = self.sanctions.borrow_mut();
+        let users = self.users.borrow();
+        let now = Utc::now();
+        let mut created: Vec<Sanction> = vec![];
+        for sanction in sanctions {
+            if !teams.contains_key(&sanction.team_id) {
+                return Err(foreign_key_violation("team_id"));
+            }
+            if !users.iter().any(|user| user.id == sanction.user_id) {
+                return Err(foreign_key_violation("user_id"));
+            }
+            if sanction.created_by.is_some() {
+                return Err(foreign_key_violation("sanctions_created_by_fkey"));
+            }
+            if stored
+                .iter()
+                .chain(&created)
+                .any(|other| other.id == sanction.id)
+            {
+                return Err(unique_violation("sanctions_pkey"));
+            }
+
+            created.push(Sanction {
+                id: sanction.id,
+                user_id: sanction.user_id,
+                team_id: sanction.team_id,
+                sanction_info: sanction.sanction_info,
+                price: sanction.price,
+                created_at: sanction.created_at.unwrap_or(now),
+                paid_at: sanction.paid_at,
+                waived_at: sanction.waived_at,
+                season: sanction.season,
+                archived_at: None,
+                disputed: false,
+                dispute_note: None,
+                recorded_at: sanction.recorded_at.unwrap_or(now),
+                created_by: None,
+                paid_by: None,
+                waive_reason: None,
+                updated_at: now,
+            });
+        }
+        stored.extend(created.iter().cloned());
+
+        Ok(created)
+    }
+}
+
+impl SanctionsDb for MemoryDb {
+    fn get_sanctions(
+        &self,
+        team_id: Uuid,
+        date_interval: Option<(NaiveDate, NaiveDate)>,
+        filter: &SanctionFilter,
+    ) -> Result<Vec<Sanction>, DbError> {
+        Ok(self
+            .sanctions
+            .borrow()
+            .iter()
+            .filter(|sanction| {
+                sanction.team_id == team_id
+                    && date_interval
+                        .is_none_or(|(min, max)| (min..=max).contains(&sanction.created_on()))
+                    && (filter.include_paid || sanction.paid_at.is_none())
+                    && (filter.include_waived || sanction.waived_at.is_none())
+                    && (filter.season.is_none() || sanction.season == filter.season)
+                    && filter
+                        .user_id
+                        .is_none_or(|user_id| sanction.user_id == user_id)
+                    && filter
+                        .rule_id
+                        .is_none_or(|rule_id| sanction.sanction_info.associated_rule == rule_id)
+            })
+            .cloned()
+            .collect())
+    }
+
+    fn create_sanctions(&self, sanctions: &[CreateSanction]) -> Result<Vec<Sanction>, DbError> {
+        self.insert_sanctions(sanctions, false)
+    }
+
+    fn create_sanctions_overriding_leave(
+        &self,
+        sanctions: &[CreateSanction],
+    ) -> Result<Vec<Sanction>, DbError> {
+        self.insert_sanctions(sanctions, true)
+    }
+
+    fn delete_sanction(
+        &self,
+        team_id: Uuid,
+        sanction_id: Uuid,
+        _actor: Option<&str>,
+    ) -> Result<Sanction, DbError> {
+        let mut sanctions = self.sanctions.borrow_mut();
+        let index = sanctions
+            .iter()
+            .position(|sanction| sanction.team_id == team_id && sanction.id == sanction_id)
+            .ok_or(DbError::NotFound)?;
+
+        Ok(sanctions.remove(index))
+    }
+
+    fn get_fine_leaderboard(
+        &self,
+        team_id: Uuid,
+        pagination: Option<&Pagination>,
+    ) -> Result<Paginated<(User, Amount)>, DbError> {
+        self.get_team(team_id)?;
+
+        let sanctions = self.sanctions.borrow();
+        let mut leaderboard: Vec<(User, Amount)> = self
+            .users
+            .borrow()
+            .iter()
+            .filter(|user| user.team_id == team_id && !user.anonymized)
+            .map(|user| {
+                let total: Amount = sanctions
+                    .iter()
+                    .filter(|sanction| {
+                        sanction.team_id == team_id
+                            && sanction.user_id == user.id
+                            && sanction.waived_at.is_none()
+                    })
+                    .map(|sanction| sanction.price)
+                    .sum();
+
+                (user.clone(), total)
+            })
+            .collect();
+        leaderboard.sort_by(|(a, a_total), (b, b_total)| {
+            b_total
+                .cmp(a_total)
+                .then_with(|| a.lastname.cmp(&b.lastname))
+                .then_with(|| a.firstname.cmp(&b.firstname))
+                .then_with(|| a.id.cmp(&b.id))
+        });
+
+        let total = leaderboard.len() as i64;
+        let pagination = match pagination {
+            Some(pagination) => pagination.clone(),
+            None => Pagination {
+                limit: total,
+                offset: 0,
+            },
+        };
+        let items: Vec<(User, Amount)> = leaderboard
+            .into_iter()
+            .skip(pagination.offset as usize)
+            .take(pagination.limit as usize)
+            .collect();
+
+        Ok(Paginated::new(items, total, &pagination))
+    }
+
+    fn query_sanctions(
+        &self,
+        team_id: Uuid,
+        query: SanctionQuery,
+    ) -> Result<Paginated<Sanction>, DbError> {
+        query.validate()?;
+        let pagination = query.pagination();
+
+        let mut sanctions: Vec<Sanction> = self
+            .sanctions
+            .borrow()
+            .iter()
+            .filter(|sanction| {
+                sanction.team_id == team_id
+                    && query
+                        .user_id
+                        .is_none_or(|user_id| sanction.user_id == user_id)
+                    && query.from.is_none_or(|from| sanction.created_on() >= from)
+                    && query.to.is_none_or(|to| sanction.created_on() <= to)
+                    && query
+                        .paid
+                        .is_none_or(|paid| sanction.paid_at.is_some() == paid)
+                    && query
+                        .waived
+                        .is_none_or(|waived| sanction.waived_at.is_some() == waived)
+            })
+            .cloned()
+            .collect();
+        match query.order {
+            SanctionOrder::NewestFirst => {
+                sanctions.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(a.id.cmp(&b.id)))
+            }
+            SanctionOrder::OldestFirst => {
+                sanctions.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)))
+            }
+            SanctionOrder::HighestPriceFirst => {
+                sanctions.sort_by(|a, b| b.price.cmp(&a.price).then(a.id.cmp(&b.id)))
+            }
+        }
+
+        let total = sanctions.len() as i64;
+        let sanctions: Vec<Sanction> = sanctions
+            .into_iter()
+            .skip(pagination.offset as usize)
+            .take(pagination.limit as usize)
+            .collect();
+
+        Ok(Paginated::new(sanctions, total, &pagination))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::{result::Error, Connection};
+    use serde_json::{json, Value};
+    use std::ops::Deref;
+
+    use rouille::Request;
+
+    use super::*;
+    use crate::api::models::test_utils::RequestBuilder;
+    use crate::sanctions::routes::handle_request as handle_sanctions_request;
+    use crate::teams::models::RuleKind;
+    use crate::test_utils::postgres::{init_connection, unique_admin};
+    use crate::users::routes::handle_request;
+
+    const TEAM_ID: &str = "7ec7ba5e-7a3a-4a0d-9221-0a2ae6a2a9e1";
+    const USER_ID: &str = "f7a2f0b3-0fcb-4c8a-9b7e-1c1ad3d0f6a4";
+    const OTHER_TEAM_ID: &str = "0d5b0b58-5a8b-4f1e-8a44-8d2c8b4f3c7e";
+    const OTHER_USER_ID: &str = "3b1e8f3a-6d0c-4a55-9f8e-2c4b7a1d5e90";
+    const RULE_ID: &str = "c2d4e6f8-1a3b-4c5d-8e7f-9a0b1c2d3e4f";
+    const SANCTION_ID: &str = "5a6b7c8d-9e0f-4a1b-8c2d-3e4f5a6b7c8d";
+    const OTHER_SANCTION_ID: &str = "9f8e7d6c-5b4a-4392-8170-6f5e4d3c2b1a";
+
+    fn create_user_request(team_id: &str, body: Value) -> Request {
+        RequestBuilder::post(format!("/teams/{}/users", team_id), &body)
+    }
+
+    /// Postgres aborts the transaction on a constraint violation, so only the last request
+    /// of a scenario may break one.
+    fn run_users_scenario<T: UsersDb + TeamsDb>(db: &T, requests: &[Request]) -> Vec<Value> {
+        db.create_team(
+            &Team {
+                id: Uuid::parse_str(TEAM_ID).unwrap(),
+                name: String::from("Les Arbres"),
+                ..Default::default()
+            },
+            &unique_admin(),
+        )
+        .unwrap();
+
+        requests
+            .iter()
+            .map(|request| match handle_request(request, db) {
+                Ok(response) => json!(response),
+                Err(error) => json!(error),
+            })
+            .collect()
+    }
+
+    /// Request bodies can only be read once.
+    fn users_scenarios() -> Vec<Vec<Request>> {
+        let john = json!({
+            "id": USER_ID,
+            "firstname": " John ",
+            "lastname": "Snow",
+            "email": "john@snow.com"
+        });
+        vec![
+            vec![
+                create_user_request(TEAM_ID, john.clone()),
+                RequestBuilder::post(
+                    format!("/teams/{}/users/{}", TEAM_ID, USER_ID),
+                    &json!({
+                        "firstname": "John",
+                        "lastname": "Targaryen"
+                    }),
+                ),
+                RequestBuilder::get(format!("/teams/{}/users?limit=10", TEAM_ID)),
+                RequestBuilder::get(format!("/teams/{}/users/{}", OTHER_TEAM_ID, USER_ID)),
+            ],
+            vec![
+                create_user_request(TEAM_ID, john),
+                create_user_request(
+                    TEAM_ID,
+                    json!({
+                        "firstname": "Jon",
+                        "lastname": "Snow",
+                        "email": "john@snow.com"
+                    }),
+                ),
+            ],
+            vec![create_user_request(
+                OTHER_TEAM_ID,
+                json!({
+                    "firstname": "Arya",
+                    "lastname": "Stark"
+                }),
+            )],
+        ]
+    }
+
+    #[test]
+    fn test_users_handler_parity() {
+        let conn = init_connection();
+
+        for (memory_requests, postgres_requests) in users_scenarios().iter().zip(users_scenarios())
+        {
+            let memory_responses = run_users_scenario(&MemoryDb::default(), memory_requests);
+
+            conn.deref().test_transaction::<_, Error, _>(|| {
+                assert_eq!(
+                    run_users_scenario(&conn, &postgres_requests),
+                    memory_responses
+                );
+
+                Ok(())
+            });
+        }
+    }
+
+    /// The instants the sanctions are recorded and updated at depend on the clock.
+    fn run_sanctions_scenario<T: SanctionsDb + TeamsDb + UsersDb>(
+        db: &T,
+        requests: &[Request],
+    ) -> Vec<Value> {
+        let team_id = Uuid::parse_str(TEAM_ID).unwrap();
+        db.create_team(
+            &Team {
+                id: team_id,
+                name: String::from("Les Arbres"),
+                rules: vec![Rule {
+                    id: Uuid::parse_str(RULE_ID).unwrap(),
+                    name: String::from("Late"),
+                    kind: RuleKind::Basic {
+                        price: Amount::from_cents(250),
+                    },
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            &unique_admin(),
+        )
+        .unwrap();
+        for (id, firstname, lastname) in
+            &[(USER_ID, "John", "Snow"), (OTHER_USER_ID, "Arya", "Stark")]
+        {
+            db.create_user(&User {
+                id: Uuid::parse_str(id).unwrap(),
+                team_id,
+                firstname: String::from(*firstname),
+                lastname: String::from(*lastname),
+                ..Default::default()
+            })
+            .unwrap();
+        }
+
+        requests
+            .iter()
+            .map(|request| {
+                let mut response = match handle_sanctions_request(request, db) {
+                    Ok(response) => json!(response),
+                    Err(error) => json!(error),
+                };
+                remove_clock_fields(&mut response);
+
+                response
+            })
+            .collect()
+    }
+
+    fn remove_clock_fields(value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                object.remove("recordedAt");
+                object.remove("updatedAt");
+                object.values_mut().for_each(remove_clock_fields);
+            }
+            Value::Array(array) => array.iter_mut().for_each(remove_clock_fields),
+            _ => {}
+        }
+    }
+
+    fn create_sanction(id: &str, user_id: &str, created_at: &str) -> Value {
+        json!({
+            "id": id,
+            "user_id": user_id,
+            "sanction_info": {
+                "associated_rule": RULE_ID,
+                "extra_info": {
+                    "type": "NONE"
+                }
+            },
+            "created_at": created_at
+        })
+    }
+
+    fn sanctions_scenarios() -> Vec<Vec<Request>> {
+        let sanctions_url = format!("/teams/{}/sanctions", TEAM_ID);
+        vec![
+            vec![
+                RequestBuilder::post(
+                    sanctions_url.clone(),
+                    &json!([
+                        create_sanction(SANCTION_ID, USER_ID, "2019-10-16"),
+                        create_sanction(OTHER_SANCTION_ID, OTHER_USER_ID, "2019-11-03")
+                    ]),
+                ),
+                RequestBuilder::get(sanctions_url.clone()),
+                RequestBuilder::get(format!("{}?year=2019&month=11", sanctions_url)),
+                RequestBuilder::get(format!("/teams/{}/leaderboard", TEAM_ID)),
+                RequestBuilder::delete(format!("{}/{}", sanctions_url, SANCTION_ID)),
+                RequestBuilder::delete(format!("{}/{}", sanctions_url, SANCTION_ID)),
+                RequestBuilder::get(format!("/teams/{}/leaderboard?limit=1", TEAM_ID)),
+                RequestBuilder::get(format!("/teams/{}/leaderboard", OTHER_TEAM_ID)),
+            ],
+            vec![
+                RequestBuilder::post(
+                    sanctions_url.clone(),
+                    &json!([create_sanction(SANCTION_ID, USER_ID, "2019-10-16")]),
+                ),
+                RequestBuilder::post(
+                    sanctions_url.clone(),
+                    &json!([create_sanction(SANCTION_ID, OTHER_USER_ID, "2019-10-17")]),
+                ),
+            ],
+            vec![RequestBuilder::post(
+                sanctions_url,
+                &json!([create_sanction(SANCTION_ID, OTHER_TEAM_ID, "2019-10-16")]),
+            )],
+        ]
+    }
+
+    #[test]
+    fn test_sanctions_handler_parity() {
+        let conn = init_connection();
+
+        for (memory_requests, postgres_requests) in
+            sanctions_scenarios().iter().zip(sanctions_scenarios())
+        {
+            let memory_responses = run_sanctions_scenario(&MemoryDb::default(), memory_requests);
+
+            conn.deref().test_transaction::<_, Error, _>(|| {
+                assert_eq!(
+                    run_sanctions_scenario(&conn, &postgres_requests),
+                    memory_responses
+                );
+
+                Ok(())
+            });
+        }
+    }
+
+    #[test]
+    fn test_team_scoping() {
+        let db = MemoryDb::default();
+        let team = db.create_team(&Team::default(), &unique_admin()).unwrap();
+        let user = db
+            .create_user(&User {
+                team_id: team.id,
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(db.get_user(Uuid::new_v4(), user.id), Err(DbError::NotFound));
+        assert_eq!(
+            db.anonymize_user(Uuid::new_v4(), user.id),
+            Err(DbError::NotFound)
+        );
+        assert_eq!(db.get_users(Uuid::new_v4()), Ok(vec![]));
+        assert_eq!(db.get_team_with_users(team.id), Ok((team, vec![user])));
+    }
+}
@@ -1,4 +1,6 @@
 #[cfg(test)]
+pub mod memory;
+#[cfg(test)]
 pub mod postgres;
 #[cfg(test)]
 pub mod routes;
@@ -1,6 +1,8 @@
+use crate::admins::models::NewAdmin;
 use crate::database::postgres::{init_db_connection, DbConnection};
 use dotenv::dotenv;
 use std::env::var;
+use uuid::Uuid;
 
 pub fn init_connection() -> DbConnection {
     dotenv().ok();
@@ -8,3 +10,15 @@ pub fn init_connection() -> DbConnection {
     let database_url = var("DATABASE_URL").expect("DATABASE_URL must be set for tests");
     init_db_connection(&database_url).expect("Something went wrong while getting the connection")
 }
+
+/// The usernames are unique across the teams and the tests run concurrently.
+pub fn unique_username() -> String {
+    format!("admin-{}", Uuid::new_v4())
+}
+
+pub fn unique_admin() -> NewAdmin {
+    NewAdmin {
+        username: unique_username(),
+        password: String::from("password"),
+    }
+}
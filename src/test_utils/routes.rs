@@ -1,21 +1,155 @@
-use chrono::{naive::NaiveDate, Local};
+use chrono::{naive::NaiveDate, DateTime, Local, Utc};
+use std::collections::HashMap;
 use uuid::Uuid;
 
-use crate::database::postgres::DbError;
-use crate::sanctions::{interface::SanctionsDb, models::*};
+use crate::admins::{
+    interface::AdminsDb,
+    models::{Admin, NewAdmin},
+};
+use crate::database::{
+    amount::Amount,
+    pagination::{Paginated, Pagination},
+    postgres::DbError,
+};
+use crate::sanctions::{
+    interface::{SanctionsDb, SanctionsLedgerDb},
+    models::*,
+};
 use crate::teams::{interface::TeamsDb, models::*};
 use crate::users::{interface::UsersDb, models::*};
 
 #[derive(Default)]
 pub struct DbMock {
+    pub admins_db: AdminsDbMock,
     pub teams_db: TeamsDbMock,
     pub users_db: UsersDbMock,
     pub sanctions_db: SanctionsDbMock,
 }
 
+#[derive(Default)]
+pub enum AdminsDbMock {
+    #[default]
+    Success,
+    SuccessWithPassword(String),
+    SuccessWithTwoFactor(String),
+    NotFound,
+}
+
+impl AdminsDbMock {
+    fn admin(&self, team_id: Uuid, username: &str) -> Result<Admin, DbError> {
+        let admin = Admin {
+            id: Uuid::new_v4(),
+            team_id,
+            username: String::from(username),
+            ..Default::default()
+        };
+
+        match self {
+            AdminsDbMock::Success | AdminsDbMock::SuccessWithPassword(_) => Ok(admin),
+            AdminsDbMock::SuccessWithTwoFactor(_) => Ok(Admin {
+                email: Some(format!("{}@example.com", username)),
+                two_factor_enabled: true,
+                ..admin
+            }),
+            AdminsDbMock::NotFound => Err(DbError::NotFound),
+        }
+    }
+}
+
+impl AdminsDb for DbMock {
+    fn add_admin(&self, team_id: Uuid, username: &str, _password: &str) -> Result<Admin, DbError> {
+        self.admins_db.admin(team_id, username)
+    }
+
+    fn verify_admin(
+        &self,
+        team_id: Uuid,
+        username: &str,
+        password: &str,
+    ) -> Result<Admin, DbError> {
+        match &self.admins_db {
+            AdminsDbMock::SuccessWithPassword(admin_password) if password != admin_password => {
+                Err(DbError::NotFound)
+            }
+            _ => self.admins_db.admin(team_id, username),
+        }
+    }
+
+    fn remove_admin(&self, team_id: Uuid, username: &str) -> Result<Admin, DbError> {
+        self.admins_db.admin(team_id, username)
+    }
+
+    fn set_admin_user(
+        &self,
+        team_id: Uuid,
+        username: &str,
+        user_id: Option<Uuid>,
+    ) -> Result<Admin, DbError> {
+        Ok(Admin {
+            user_id,
+            ..self.admins_db.admin(team_id, username)?
+        })
+    }
+
+    fn find_teams_without_admin(&self) -> Result<Vec<Uuid>, DbError> {
+        Ok(vec![])
+    }
+
+    fn set_two_factor(
+        &self,
+        team_id: Uuid,
+        username: &str,
+        enabled: bool,
+        email: Option<&str>,
+    ) -> Result<Admin, DbError> {
+        Ok(Admin {
+            email: email.map(String::from),
+            two_factor_enabled: enabled,
+            ..self.admins_db.admin(team_id, username)?
+        })
+    }
+
+    fn request_login_code(
+        &self,
+        team_id: Uuid,
+        username: &str,
+        password: &str,
+    ) -> Result<(Admin, String), DbError> {
+        let admin = self.verify_admin(team_id, username, password)?;
+
+        match &self.admins_db {
+            AdminsDbMock::SuccessWithTwoFactor(code) => Ok((admin, code.clone())),
+            _ => Err(DbError::Forbidden(String::from(
+                "The two-factor login is not enabled for this admin",
+            ))),
+        }
+    }
+
+    fn verify_login_code(
+        &self,
+        team_id: Uuid,
+        username: &str,
+        code: &str,
+    ) -> Result<Admin, DbError> {
+        match &self.admins_db {
+            AdminsDbMock::SuccessWithTwoFactor(admin_code) if code == admin_code => {
+                self.admins_db.admin(team_id, username)
+            }
+            _ => Err(DbError::NotFound),
+        }
+    }
+
+    fn find_team_by_admin_username(&self, _username: &str) -> Result<Uuid, DbError> {
+        self.admins_db
+            .admin(Uuid::new_v4(), "")
+            .map(|admin| admin.team_id)
+    }
+}
+
 pub enum TeamsDbMock {
     Success,
     SuccessWithRules(Vec<Rule>),
+    SuccessWithDefaultRule(Rule),
     NotFound,
     Unknown,
 }
@@ -27,7 +161,7 @@ impl Default for TeamsDbMock {
 }
 
 impl TeamsDb for DbMock {
-    fn login(&self, _name: &str, _admin_password: &Option<String>) -> Result<Uuid, DbError> {
+    fn login(&self, _name: &str) -> Result<Uuid, DbError> {
         match self.teams_db {
             TeamsDbMock::Success => Ok(Uuid::new_v4()),
             TeamsDbMock::NotFound => Err(DbError::NotFound),
@@ -46,12 +180,18 @@ impl TeamsDb for DbMock {
                 rules: rules.clone(),
                 ..Default::default()
             }),
+            TeamsDbMock::SuccessWithDefaultRule(rule) => Ok(Team {
+                id,
+                rules: vec![rule.clone()],
+                default_rule_id: Some(rule.id),
+                ..Default::default()
+            }),
             TeamsDbMock::NotFound => Err(DbError::NotFound),
             TeamsDbMock::Unknown => Err(DbError::Unknown),
         }
     }
 
-    fn create_team(&self, team: &Team) -> Result<Team, DbError> {
+    fn create_team(&self, team: &Team, _admin: &NewAdmin) -> Result<Team, DbError> {
         match self.teams_db {
             TeamsDbMock::Success => Ok(team.clone()),
             TeamsDbMock::Unknown => Err(DbError::Unknown),
@@ -64,14 +204,129 @@ impl TeamsDb for DbMock {
             TeamsDbMock::Success => Ok(Team {
                 id,
                 name: team.name.clone(),
-                admin_password: team.admin_password.clone(),
                 rules: team.rules.clone(),
+                current_season: team.current_season.clone(),
+                default_rule_id: team.default_rule_id,
+                allow_self_fine: team.allow_self_fine.unwrap_or(true),
+                ..Default::default()
             }),
             TeamsDbMock::NotFound => Err(DbError::NotFound),
             TeamsDbMock::Unknown => Err(DbError::Unknown),
             _ => unimplemented!(),
         }
     }
+
+    fn add_rule(&self, _team_id: Uuid, rule: &Rule) -> Result<Rule, DbError> {
+        match self.teams_db {
+            TeamsDbMock::Success | TeamsDbMock::SuccessWithRules(_) => Ok(rule.clone()),
+            TeamsDbMock::NotFound => Err(DbError::NotFound),
+            TeamsDbMock::Unknown => Err(DbError::Unknown),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn copy_rules(&self, _source_team: Uuid, _target_team: Uuid) -> Result<Vec<Rule>, DbError> {
+        match &self.teams_db {
+            TeamsDbMock::Success => Ok(vec![]),
+            TeamsDbMock::SuccessWithRules(rules) => Ok(rules
+                .iter()
+                .map(|rule| Rule {
+                    id: Uuid::new_v4(),
+                    ..rule.clone()
+                })
+                .collect()),
+            TeamsDbMock::NotFound => Err(DbError::NotFound),
+            TeamsDbMock::Unknown => Err(DbError::Unknown),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn import_rules(&self, _team_id: Uuid, rules: Vec<RuleInput>) -> Result<Vec<Rule>, DbError> {
+        match self.teams_db {
+            TeamsDbMock::Success | TeamsDbMock::SuccessWithRules(_) => {
+                Ok(rules.into_iter().map(Rule::from).collect())
+            }
+            TeamsDbMock::NotFound => Err(DbError::NotFound),
+            TeamsDbMock::Unknown => Err(DbError::Unknown),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn set_current_season(&self, team_id: Uuid, season: Option<&str>) -> Result<Team, DbError> {
+        Ok(Team {
+            current_season: season.map(String::from),
+            ..self.get_team(team_id)?
+        })
+    }
+
+    fn get_team_with_users(&self, team_id: Uuid) -> Result<(Team, Vec<User>), DbError> {
+        Ok((self.get_team(team_id)?, self.get_users(team_id)?))
+    }
+
+    fn get_rules_by_tag(&self, team_id: Uuid, tag: &str) -> Result<Vec<Rule>, DbError> {
+        Ok(self
+            .get_team(team_id)?
+            .rules
+            .into_iter()
+            .filter(|rule| rule.tags.iter().any(|rule_tag| rule_tag == tag))
+            .collect())
+    }
+
+    fn replace_rules(&self, _team_id: Uuid, rules: &[Rule]) -> Result<Vec<Rule>, DbError> {
+        match self.teams_db {
+            TeamsDbMock::Success | TeamsDbMock::SuccessWithRules(_) => Ok(rules.to_vec()),
+            TeamsDbMock::NotFound => Err(DbError::NotFound),
+            TeamsDbMock::Unknown => Err(DbError::Unknown),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn set_max_balance(
+        &self,
+        team_id: Uuid,
+        max_balance: Option<Amount>,
+        clamp: bool,
+    ) -> Result<Team, DbError> {
+        Ok(Team {
+            max_balance,
+            clamp_to_max_balance: clamp,
+            ..self.get_team(team_id)?
+        })
+    }
+
+    fn set_max_rules(&self, team_id: Uuid, max_rules: u32) -> Result<Team, DbError> {
+        Ok(Team {
+            max_rules: max_rules as i32,
+            ..self.get_team(team_id)?
+        })
+    }
+
+    fn set_grace_days(&self, team_id: Uuid, grace_days: u32) -> Result<Team, DbError> {
+        Ok(Team {
+            grace_days: grace_days as i32,
+            ..self.get_team(team_id)?
+        })
+    }
+
+    fn rotate_team_token(&self, team_id: Uuid) -> Result<String, DbError> {
+        self.get_team(team_id)?;
+
+        Ok(String::from("token"))
+    }
+
+    fn verify_team_token(&self, team_id: Uuid, _token: &str) -> Result<Uuid, DbError> {
+        Ok(self.get_team(team_id)?.id)
+    }
+
+    fn get_teams_for_email(&self, _email: &str) -> Result<Vec<Team>, DbError> {
+        match self.teams_db {
+            TeamsDbMock::NotFound => Ok(vec![]),
+            _ => Ok(vec![Team {
+                id: Uuid::new_v4(),
+                ..Default::default()
+            }]),
+        }
+    }
 }
 
 pub enum UsersDbMock {
@@ -98,6 +353,17 @@ impl UsersDb for DbMock {
         }
     }
 
+    fn get_users_page(
+        &self,
+        team_id: Uuid,
+        pagination: &Pagination,
+    ) -> Result<Paginated<User>, DbError> {
+        let users = self.get_users(team_id)?;
+        let total = users.len() as i64;
+
+        Ok(Paginated::new(users, total, pagination))
+    }
+
     fn get_user(&self, team_id: Uuid, user_id: Uuid) -> Result<User, DbError> {
         match self.users_db {
             UsersDbMock::Success => Ok(User {
@@ -133,11 +399,76 @@ impl UsersDb for DbMock {
                 lastname: user.lastname.clone(),
                 nickname: user.nickname.clone(),
                 email: user.email.clone(),
+                fine_multiplier: user.fine_multiplier,
+                anonymized: false,
+                role: user.role.clone(),
+                external_id: None,
+                on_leave_until: None,
             }),
             UsersDbMock::NotFound => Err(DbError::NotFound),
             _ => unimplemented!(),
         }
     }
+
+    fn patch_user(&self, team_id: Uuid, user_id: Uuid, patch: &UserPatch) -> Result<User, DbError> {
+        match self.users_db {
+            UsersDbMock::Success => {
+                let mut user = User {
+                    id: user_id,
+                    team_id,
+                    firstname: String::from("John"),
+                    lastname: String::from("Snow"),
+                    nickname: Some(String::from("King of the north")),
+                    ..Default::default()
+                };
+                patch.apply_to(&mut user);
+
+                Ok(user)
+            }
+            UsersDbMock::NotFound => Err(DbError::NotFound),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn find_duplicate_users(&self, _team_id: Uuid) -> Result<Vec<Vec<User>>, DbError> {
+        match self.users_db {
+            UsersDbMock::Success => Ok(vec![]),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn export_user_data(&self, team_id: Uuid, user_id: Uuid) -> Result<UserDataExport, DbError> {
+        Ok(UserDataExport {
+            user: self.get_user(team_id, user_id)?,
+            sanctions: vec![],
+        })
+    }
+
+    fn anonymize_user(&self, team_id: Uuid, user_id: Uuid) -> Result<User, DbError> {
+        Ok(User {
+            anonymized: true,
+            ..self.get_user(team_id, user_id)?
+        })
+    }
+
+    fn get_users_without_email(&self, _team_id: Uuid) -> Result<Vec<User>, DbError> {
+        match self.users_db {
+            UsersDbMock::Success => Ok(vec![]),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn get_users_by_role(&self, team_id: Uuid, role: &str) -> Result<Vec<User>, DbError> {
+        Ok(self
+            .get_users(team_id)?
+            .into_iter()
+            .filter(|user| user.role.as_deref() == Some(role.trim()))
+            .collect())
+    }
+
+    fn upsert_user(&self, user: &User) -> Result<User, DbError> {
+        self.create_user(user)
+    }
 }
 
 pub enum SanctionsDbMock {
@@ -156,6 +487,7 @@ impl SanctionsDb for DbMock {
         &self,
         team_id: Uuid,
         date_interval: Option<(NaiveDate, NaiveDate)>,
+        filter: &SanctionFilter,
     ) -> Result<Vec<Sanction>, DbError> {
         match self.sanctions_db {
             SanctionsDbMock::Success => {
@@ -163,29 +495,38 @@ impl SanctionsDb for DbMock {
                     Sanction {
                         team_id,
                         user_id: Uuid::new_v4(),
-                        created_at: NaiveDate::from_ymd(2019, 10, 5),
+                        created_at: start_of_day(NaiveDate::from_ymd(2019, 10, 5)),
                         ..Default::default()
                     },
                     Sanction {
                         team_id,
                         user_id: Uuid::new_v4(),
-                        created_at: NaiveDate::from_ymd(2019, 10, 15),
+                        created_at: start_of_day(NaiveDate::from_ymd(2019, 10, 15)),
                         ..Default::default()
                     },
                     Sanction {
                         team_id,
                         user_id: Uuid::new_v4(),
-                        created_at: NaiveDate::from_ymd(2019, 11, 5),
+                        created_at: start_of_day(NaiveDate::from_ymd(2019, 11, 5)),
                         ..Default::default()
                     },
                 ];
-                Ok(match date_interval {
-                    Some((min, max)) => basic_result
-                        .into_iter()
-                        .filter(|sanction| sanction.created_at >= min && sanction.created_at <= max)
-                        .collect(),
-                    None => basic_result,
-                })
+                Ok(basic_result
+                    .into_iter()
+                    .filter(|sanction| match date_interval {
+                        Some((min, max)) => {
+                            sanction.created_on() >= min && sanction.created_on() <= max
+                        }
+                        None => true,
+                    })
+                    .filter(|sanction| filter.include_paid || sanction.paid_at.is_none())
+                    .filter(|sanction| filter.include_waived || sanction.waived_at.is_none())
+                    .filter(|sanction| {
+                        filter
+                            .user_id
+                            .is_none_or(|user_id| sanction.user_id == user_id)
+                    })
+                    .collect())
             }
             _ => unimplemented!(),
         }
@@ -201,16 +542,37 @@ impl SanctionsDb for DbMock {
                     team_id: create_sanction.team_id,
                     sanction_info: create_sanction.sanction_info.clone(),
                     price: create_sanction.price,
-                    created_at: create_sanction
-                        .created_at
-                        .unwrap_or_else(|| Local::today().naive_local()),
+                    created_at: create_sanction.created_at.unwrap_or_else(Utc::now),
+                    paid_at: create_sanction.paid_at,
+                    waived_at: create_sanction.waived_at,
+                    season: create_sanction.season.clone(),
+                    archived_at: None,
+                    disputed: false,
+                    dispute_note: None,
+                    recorded_at: create_sanction.recorded_at.unwrap_or_else(Utc::now),
+                    created_by: create_sanction.created_by,
+                    paid_by: None,
+                    waive_reason: None,
+                    updated_at: Utc::now(),
                 })
                 .collect()),
             SanctionsDbMock::NotFound => Err(DbError::ForeignKeyViolation(String::from("Error"))),
         }
     }
 
-    fn delete_sanction(&self, team_id: Uuid, sanction_id: Uuid) -> Result<Sanction, DbError> {
+    fn create_sanctions_overriding_leave(
+        &self,
+        sanctions: &[CreateSanction],
+    ) -> Result<Vec<Sanction>, DbError> {
+        self.create_sanctions(sanctions)
+    }
+
+    fn delete_sanction(
+        &self,
+        team_id: Uuid,
+        sanction_id: Uuid,
+        _actor: Option<&str>,
+    ) -> Result<Sanction, DbError> {
         match self.sanctions_db {
             SanctionsDbMock::Success => Ok(Sanction {
                 id: sanction_id,
@@ -220,4 +582,392 @@ impl SanctionsDb for DbMock {
             SanctionsDbMock::NotFound => Err(DbError::NotFound),
         }
     }
+
+    fn get_fine_leaderboard(
+        &self,
+        _team_id: Uuid,
+        pagination: Option<&Pagination>,
+    ) -> Result<Paginated<(User, Amount)>, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(Paginated::new(
+                vec![],
+                0,
+                pagination.unwrap_or(&Pagination::default()),
+            )),
+            SanctionsDbMock::NotFound => Err(DbError::NotFound),
+        }
+    }
+
+    fn query_sanctions(
+        &self,
+        _team_id: Uuid,
+        query: SanctionQuery,
+    ) -> Result<Paginated<Sanction>, DbError> {
+        query.validate()?;
+
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(Paginated::new(vec![], 0, &query.pagination())),
+            SanctionsDbMock::NotFound => Err(DbError::NotFound),
+        }
+    }
+}
+
+impl SanctionsLedgerDb for DbMock {
+    fn get_unpaid_older_than(
+        &self,
+        _team_id: Uuid,
+        _days: i64,
+    ) -> Result<Vec<(User, Vec<Sanction>)>, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(vec![]),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn get_rule_revenue(&self, _team_id: Uuid) -> Result<Vec<(Rule, i64, Amount)>, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(vec![]),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn get_balance(&self, _team_id: Uuid, _user_id: Uuid) -> Result<Amount, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(Default::default()),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn mark_user_sanctions_paid(
+        &self,
+        _team_id: Uuid,
+        _user_id: Uuid,
+        _date: NaiveDate,
+        _paid_by: Option<Uuid>,
+    ) -> Result<u64, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(0),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn get_user_timeline(
+        &self,
+        _team_id: Uuid,
+        _user_id: Uuid,
+    ) -> Result<Vec<TimelineEvent>, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(vec![]),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn get_sanctions_by_category(
+        &self,
+        _team_id: Uuid,
+        _category: RuleCategory,
+    ) -> Result<Vec<Sanction>, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(vec![]),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn preview_balance_with(
+        &self,
+        _team_id: Uuid,
+        _user_id: Uuid,
+        _rule_id: Uuid,
+        _extra_info: &ExtraInfo,
+    ) -> Result<Amount, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(Default::default()),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn get_unbroken_rules(&self, _team_id: Uuid, _user_id: Uuid) -> Result<Vec<Rule>, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(vec![]),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn get_collection_rate(&self, _team_id: Uuid) -> Result<f32, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(0.0),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn archive_season(&self, _team_id: Uuid, season: &str) -> Result<SeasonArchive, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(SeasonArchive {
+                season: season.to_string(),
+                users: Vec::new(),
+                total: Amount::from_cents(0),
+                collection_rate: 0.0,
+            }),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn get_daily_sanction_counts(
+        &self,
+        _team_id: Uuid,
+        from: NaiveDate,
+        _to: NaiveDate,
+    ) -> Result<Vec<(NaiveDate, i64)>, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(vec![(from, 1)]),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn get_rule_trend(
+        &self,
+        _team_id: Uuid,
+        _rule_id: Uuid,
+        from: NaiveDate,
+        _to: NaiveDate,
+        bucket: Bucket,
+    ) -> Result<Vec<(NaiveDate, i64)>, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(vec![(bucket.start_of(from), 1)]),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn get_biggest_fine(
+        &self,
+        _team_id: Uuid,
+    ) -> Result<Option<(User, Sanction, Amount)>, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(None),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn get_global_unpaid_total(&self) -> Result<Amount, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(Amount::from_cents(0)),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn get_clean_streak(&self, _team_id: Uuid, _user_id: Uuid) -> Result<i64, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(0),
+            SanctionsDbMock::NotFound => Err(DbError::NotFound),
+        }
+    }
+
+    fn get_balances(
+        &self,
+        _team_id: Uuid,
+        user_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Amount>, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(user_ids
+                .iter()
+                .map(|user_id| (*user_id, Amount::from_cents(0)))
+                .collect()),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn find_future_dated_sanctions(&self, _team_id: Uuid) -> Result<Vec<Sanction>, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(vec![]),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn undo_last_sanction(&self, team_id: Uuid, user_id: Uuid) -> Result<Sanction, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(Sanction {
+                user_id,
+                team_id,
+                ..Default::default()
+            }),
+            SanctionsDbMock::NotFound => Err(DbError::NotFound),
+        }
+    }
+
+    fn get_fine_distribution(&self, _team_id: Uuid) -> Result<FineStats, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(FineStats::default()),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn dispute_sanction(
+        &self,
+        team_id: Uuid,
+        sanction_id: Uuid,
+        note: Option<&str>,
+    ) -> Result<Sanction, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(Sanction {
+                id: sanction_id,
+                team_id,
+                disputed: true,
+                dispute_note: note.map(String::from),
+                ..Default::default()
+            }),
+            SanctionsDbMock::NotFound => Err(DbError::NotFound),
+        }
+    }
+
+    fn resolve_dispute(
+        &self,
+        team_id: Uuid,
+        sanction_id: Uuid,
+        upheld: bool,
+    ) -> Result<Sanction, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(Sanction {
+                id: sanction_id,
+                team_id,
+                waived_at: if upheld {
+                    None
+                } else {
+                    Some(Local::today().naive_local())
+                },
+                ..Default::default()
+            }),
+            SanctionsDbMock::NotFound => Err(DbError::NotFound),
+        }
+    }
+
+    fn get_amount_by_category(
+        &self,
+        _team_id: Uuid,
+    ) -> Result<Vec<(Option<RuleCategory>, Amount)>, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(vec![(
+                Some(RuleCategory::TrainingDay),
+                Amount::from_cents(250),
+            )]),
+            SanctionsDbMock::NotFound => Err(DbError::NotFound),
+        }
+    }
+
+    fn get_recent_sanctions(&self, team_id: Uuid, _hours: i64) -> Result<Vec<Sanction>, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(vec![Sanction {
+                team_id,
+                recorded_at: Utc::now(),
+                ..Default::default()
+            }]),
+            SanctionsDbMock::NotFound => Err(DbError::NotFound),
+        }
+    }
+
+    fn get_unused_rules(&self, _team_id: Uuid, _since: NaiveDate) -> Result<Vec<Rule>, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(vec![]),
+            SanctionsDbMock::NotFound => Err(DbError::NotFound),
+        }
+    }
+
+    fn simulate_rule_price_change(
+        &self,
+        _team_id: Uuid,
+        _rule_id: Uuid,
+        _new_price: Amount,
+    ) -> Result<(Amount, Amount), DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok((Amount::default(), Amount::default())),
+            SanctionsDbMock::NotFound => Err(DbError::NotFound),
+        }
+    }
+
+    fn get_sanction_details(&self, _team_id: Uuid) -> Result<Vec<SanctionDetail>, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(vec![]),
+            SanctionsDbMock::NotFound => Err(DbError::NotFound),
+        }
+    }
+
+    fn get_average_balance(&self, _team_id: Uuid) -> Result<Amount, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(Amount::default()),
+            SanctionsDbMock::NotFound => Err(DbError::NotFound),
+        }
+    }
+
+    fn waive_sanctions_where(
+        &self,
+        team_id: Uuid,
+        date_interval: Option<(NaiveDate, NaiveDate)>,
+        filter: &SanctionFilter,
+        _reason: &str,
+    ) -> Result<u64, DbError> {
+        Ok(self.get_sanctions(team_id, date_interval, filter)?.len() as u64)
+    }
+
+    fn get_rules_with_usage(&self, _team_id: Uuid) -> Result<Vec<(Rule, i64)>, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(vec![]),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn get_sanctions_modified_since(
+        &self,
+        _team_id: Uuid,
+        _since: DateTime<Utc>,
+    ) -> Result<Vec<Sanction>, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(vec![]),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn get_sanction_audit(
+        &self,
+        _team_id: Uuid,
+        _sanction_id: Uuid,
+    ) -> Result<Vec<AuditEvent>, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(vec![]),
+            SanctionsDbMock::NotFound => Err(DbError::NotFound),
+        }
+    }
+
+    fn get_user_vs_average(
+        &self,
+        _team_id: Uuid,
+        _user_id: Uuid,
+    ) -> Result<(Amount, Amount, f32), DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok((Amount::default(), Amount::default(), 0.0)),
+            SanctionsDbMock::NotFound => Err(DbError::NotFound),
+        }
+    }
+
+    fn get_fine_gini(&self, _team_id: Uuid) -> Result<f32, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(0.0),
+            SanctionsDbMock::NotFound => Err(DbError::NotFound),
+        }
+    }
+
+    fn rule_deletion_impact(&self, _team_id: Uuid, _rule_id: Uuid) -> Result<RuleImpact, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(RuleImpact::default()),
+            SanctionsDbMock::NotFound => Err(DbError::NotFound),
+        }
+    }
+
+    fn get_top_rule_per_user(
+        &self,
+        _team_id: Uuid,
+    ) -> Result<Vec<(User, Option<Rule>, i64)>, DbError> {
+        match self.sanctions_db {
+            SanctionsDbMock::Success => Ok(vec![]),
+            SanctionsDbMock::NotFound => Err(DbError::NotFound),
+        }
+    }
 }
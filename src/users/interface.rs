@@ -1,15 +1,40 @@
 use uuid::Uuid;
 
-use super::models::{UpdateUser, User};
-use crate::database::postgres::DbError;
+use super::models::{UpdateUser, User, UserDataExport, UserPatch};
+use crate::database::{
+    pagination::{Paginated, Pagination},
+    postgres::DbError,
+};
 
 pub trait UsersDb {
     fn get_users(&self, team_id: Uuid) -> Result<Vec<User>, DbError>;
 
+    fn get_users_page(
+        &self,
+        team_id: Uuid,
+        pagination: &Pagination,
+    ) -> Result<Paginated<User>, DbError>;
+
     fn get_user(&self, team_id: Uuid, user_id: Uuid) -> Result<User, DbError>;
 
     fn create_user(&self, user: &User) -> Result<User, DbError>;
 
     fn update_user(&self, team_id: Uuid, user_id: Uuid, user: &UpdateUser)
         -> Result<User, DbError>;
+
+    fn patch_user(&self, team_id: Uuid, user_id: Uuid, patch: &UserPatch) -> Result<User, DbError>;
+
+    fn find_duplicate_users(&self, team_id: Uuid) -> Result<Vec<Vec<User>>, DbError>;
+
+    fn export_user_data(&self, team_id: Uuid, user_id: Uuid) -> Result<UserDataExport, DbError>;
+
+    /// The sanctions are kept so that the team totals stay accurate.
+    fn anonymize_user(&self, team_id: Uuid, user_id: Uuid) -> Result<User, DbError>;
+
+    fn get_users_without_email(&self, team_id: Uuid) -> Result<Vec<User>, DbError>;
+
+    fn get_users_by_role(&self, team_id: Uuid, role: &str) -> Result<Vec<User>, DbError>;
+
+    /// A user without external id is always created.
+    fn upsert_user(&self, user: &User) -> Result<User, DbError>;
 }
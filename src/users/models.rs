@@ -1,8 +1,12 @@
+use chrono::NaiveDate;
 use diesel::{Insertable, Queryable};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use uuid::Uuid;
 
+use crate::api::models::{ValidationError, ValidationErrors};
 use crate::database::schema::users;
+use crate::sanctions::models::Sanction;
+use crate::teams::models::Rule;
 
 #[derive(Serialize, Deserialize)]
 pub struct UpdateUserRequest {
@@ -11,6 +15,170 @@ pub struct UpdateUserRequest {
     pub lastname: String,
     pub nickname: Option<String>,
     pub email: Option<String>,
+    pub fine_multiplier: Option<f32>,
+    #[serde(default)]
+    pub role: Option<String>,
+}
+
+pub fn normalize_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+pub fn normalize_role(role: &str) -> Option<String> {
+    match role.trim() {
+        "" => None,
+        role => Some(role.to_string()),
+    }
+}
+
+pub fn is_valid_email(email: &str) -> bool {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.split('.').count() > 1
+                && domain.split('.').all(|label| !label.is_empty())
+                && !email.chars().any(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+fn validate_user_fields(
+    names: &[(&str, Option<&String>)],
+    email: Option<&String>,
+    fine_multiplier: Option<f32>,
+) -> Result<(), ValidationErrors> {
+    let mut errors = ValidationErrors::default();
+
+    for (field, name) in names.iter() {
+        if let Some(name) = name {
+            if normalize_name(name).is_empty() {
+                errors.push(ValidationError {
+                    field: String::from(*field),
+                    reason: String::from("must not be empty"),
+                });
+            }
+        }
+    }
+
+    match email {
+        Some(email) if !is_valid_email(email) => errors.push(ValidationError {
+            field: String::from("email"),
+            reason: String::from("must be a valid email address"),
+        }),
+        _ => {}
+    }
+
+    match fine_multiplier {
+        Some(fine_multiplier) if fine_multiplier <= 0.0 || fine_multiplier.is_nan() => {
+            errors.push(ValidationError {
+                field: String::from("fine_multiplier"),
+                reason: String::from("must be positive"),
+            })
+        }
+        _ => {}
+    }
+
+    errors.into_result()
+}
+
+impl UpdateUserRequest {
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        validate_user_fields(
+            &[
+                ("firstname", Some(&self.firstname)),
+                ("lastname", Some(&self.lastname)),
+                ("nickname", self.nickname.as_ref()),
+            ],
+            self.email.as_ref(),
+            self.fine_multiplier,
+        )
+    }
+}
+
+/// Absent fields never reach it and keep their default None.
+fn deserialize_present<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// The nullable fields are doubly optional so that an explicit null clears them.
+#[derive(Deserialize, AsChangeset, Debug, Default, PartialEq)]
+#[table_name = "users"]
+pub struct UserPatch {
+    pub firstname: Option<String>,
+    pub lastname: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub nickname: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub email: Option<Option<String>>,
+    pub fine_multiplier: Option<f32>,
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub role: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub on_leave_until: Option<Option<NaiveDate>>,
+}
+
+impl UserPatch {
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        validate_user_fields(
+            &[
+                ("firstname", self.firstname.as_ref()),
+                ("lastname", self.lastname.as_ref()),
+                ("nickname", self.nickname.as_ref().and_then(Option::as_ref)),
+            ],
+            self.email.as_ref().and_then(Option::as_ref),
+            self.fine_multiplier,
+        )
+    }
+
+    pub fn normalize(self) -> UserPatch {
+        UserPatch {
+            firstname: self.firstname.as_deref().map(normalize_name),
+            lastname: self.lastname.as_deref().map(normalize_name),
+            nickname: self
+                .nickname
+                .map(|nickname| nickname.as_deref().map(normalize_name)),
+            email: self.email,
+            fine_multiplier: self.fine_multiplier,
+            role: self
+                .role
+                .map(|role| role.as_deref().and_then(normalize_role)),
+            on_leave_until: self.on_leave_until,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == UserPatch::default()
+    }
+
+    pub fn apply_to(&self, user: &mut User) {
+        if let Some(firstname) = &self.firstname {
+            user.firstname = firstname.clone();
+        }
+        if let Some(lastname) = &self.lastname {
+            user.lastname = lastname.clone();
+        }
+        if let Some(nickname) = &self.nickname {
+            user.nickname = nickname.clone();
+        }
+        if let Some(email) = &self.email {
+            user.email = email.clone();
+        }
+        if let Some(fine_multiplier) = self.fine_multiplier {
+            user.fine_multiplier = fine_multiplier;
+        }
+        if let Some(role) = &self.role {
+            user.role = role.clone();
+        }
+        if let Some(on_leave_until) = self.on_leave_until {
+            user.on_leave_until = on_leave_until;
+        }
+    }
 }
 
 impl From<(UpdateUserRequest, Uuid)> for User {
@@ -18,10 +186,15 @@ impl From<(UpdateUserRequest, Uuid)> for User {
         User {
             id: update_request.id.unwrap_or_else(Uuid::new_v4),
             team_id,
-            firstname: update_request.firstname,
-            lastname: update_request.lastname,
-            nickname: update_request.nickname,
+            firstname: normalize_name(&update_request.firstname),
+            lastname: normalize_name(&update_request.lastname),
+            nickname: update_request.nickname.as_deref().map(normalize_name),
             email: update_request.email,
+            fine_multiplier: update_request.fine_multiplier.unwrap_or(1.0),
+            anonymized: false,
+            role: update_request.role.as_deref().and_then(normalize_role),
+            external_id: None,
+            on_leave_until: None,
         }
     }
 }
@@ -29,15 +202,17 @@ impl From<(UpdateUserRequest, Uuid)> for User {
 impl From<UpdateUserRequest> for UpdateUser {
     fn from(update_request: UpdateUserRequest) -> UpdateUser {
         UpdateUser {
-            firstname: update_request.firstname,
-            lastname: update_request.lastname,
-            nickname: update_request.nickname,
+            firstname: normalize_name(&update_request.firstname),
+            lastname: normalize_name(&update_request.lastname),
+            nickname: update_request.nickname.as_deref().map(normalize_name),
             email: update_request.email,
+            fine_multiplier: update_request.fine_multiplier.unwrap_or(1.0),
+            role: update_request.role.as_deref().and_then(normalize_role),
         }
     }
 }
 
-#[derive(Debug, Clone, Queryable, Insertable, PartialEq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Queryable, Insertable, PartialEq, Serialize, Deserialize)]
 #[table_name = "users"]
 pub struct User {
     pub id: Uuid,
@@ -46,13 +221,103 @@ pub struct User {
     pub lastname: String,
     pub nickname: Option<String>,
     pub email: Option<String>,
+    pub fine_multiplier: f32,
+    #[serde(default)]
+    pub anonymized: bool,
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub external_id: Option<String>,
+    /// The last day of the leave, included.
+    #[serde(default)]
+    pub on_leave_until: Option<NaiveDate>,
 }
 
-#[derive(AsChangeset, Default)]
+impl Default for User {
+    fn default() -> User {
+        User {
+            id: Default::default(),
+            team_id: Default::default(),
+            firstname: Default::default(),
+            lastname: Default::default(),
+            nickname: Default::default(),
+            email: Default::default(),
+            fine_multiplier: 1.0,
+            anonymized: false,
+            role: None,
+            external_id: None,
+            on_leave_until: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UserResponse {
+    pub id: Uuid,
+    pub team_id: Uuid,
+    pub firstname: String,
+    pub lastname: String,
+    pub nickname: Option<String>,
+    pub email: Option<String>,
+    pub fine_multiplier: f32,
+    pub anonymized: bool,
+    pub role: Option<String>,
+    pub external_id: Option<String>,
+    pub on_leave_until: Option<NaiveDate>,
+}
+
+impl From<User> for UserResponse {
+    fn from(user: User) -> UserResponse {
+        UserResponse {
+            id: user.id,
+            team_id: user.team_id,
+            firstname: user.firstname,
+            lastname: user.lastname,
+            nickname: user.nickname,
+            email: user.email,
+            fine_multiplier: user.fine_multiplier,
+            anonymized: user.anonymized,
+            role: user.role,
+            external_id: user.external_id,
+            on_leave_until: user.on_leave_until,
+        }
+    }
+}
+
+#[derive(AsChangeset)]
 #[table_name = "users"]
 pub struct UpdateUser {
     pub firstname: String,
     pub lastname: String,
     pub nickname: Option<String>,
     pub email: Option<String>,
+    pub fine_multiplier: f32,
+    pub role: Option<String>,
+}
+
+impl Default for UpdateUser {
+    fn default() -> UpdateUser {
+        UpdateUser {
+            firstname: Default::default(),
+            lastname: Default::default(),
+            nickname: Default::default(),
+            email: Default::default(),
+            fine_multiplier: 1.0,
+            role: Default::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ExportedSanction {
+    #[serde(flatten)]
+    pub sanction: Sanction,
+    pub rule: Option<Rule>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct UserDataExport {
+    pub user: User,
+    pub sanctions: Vec<ExportedSanction>,
 }
@@ -1,15 +1,22 @@
+use diesel::pg::upsert::excluded;
 use diesel::prelude::*;
 use std::ops::Deref;
 use uuid::Uuid;
 
 use super::{
     interface::UsersDb,
-    models::{UpdateUser, User},
+    models::{ExportedSanction, UpdateUser, User, UserDataExport, UserPatch},
 };
 use crate::database::{
+    pagination::{Paginated, Pagination},
     postgres::{DbConnection, DbError},
-    schema::users,
+    schema::{sanctions, teams, users},
 };
+use crate::sanctions::models::Sanction;
+use crate::teams::models::Team;
+
+pub(crate) const ANONYMIZED_FIRSTNAME: &str = "Anonymous";
+pub(crate) const ANONYMIZED_LASTNAME: &str = "Member";
 
 impl UsersDb for DbConnection {
     fn get_users(&self, team_id: Uuid) -> Result<Vec<User>, DbError> {
@@ -20,6 +27,26 @@ impl UsersDb for DbConnection {
         Ok(users)
     }
 
+    fn get_users_page(
+        &self,
+        team_id: Uuid,
+        pagination: &Pagination,
+    ) -> Result<Paginated<User>, DbError> {
+        let total: i64 = users::table
+            .filter(users::team_id.eq(team_id))
+            .count()
+            .get_result(self.deref())?;
+
+        let users: Vec<User> = users::table
+            .filter(users::team_id.eq(team_id))
+            .order_by((users::lastname, users::firstname, users::id))
+            .limit(pagination.limit)
+            .offset(pagination.offset)
+            .get_results(self.deref())?;
+
+        Ok(Paginated::new(users, total, pagination))
+    }
+
     fn get_user(&self, team_id: Uuid, user_id: Uuid) -> Result<User, DbError> {
         let user: User = users::table
             .filter(users::team_id.eq(team_id).and(users::id.eq(user_id)))
@@ -50,22 +77,155 @@ impl UsersDb for DbConnection {
 
         Ok(user)
     }
+
+    fn patch_user(&self, team_id: Uuid, user_id: Uuid, patch: &UserPatch) -> Result<User, DbError> {
+        // Diesel refuses to run an update without any column to set.
+        if patch.is_empty() {
+            return self.get_user(team_id, user_id);
+        }
+
+        let user: User = diesel::update(
+            users::table.filter(users::team_id.eq(team_id).and(users::id.eq(user_id))),
+        )
+        .set(patch)
+        .get_result(self.deref())?;
+
+        Ok(user)
+    }
+
+    fn find_duplicate_users(&self, team_id: Uuid) -> Result<Vec<Vec<User>>, DbError> {
+        let users: Vec<User> = users::table
+            .filter(users::team_id.eq(team_id).and(users::anonymized.eq(false)))
+            .order_by((users::lastname, users::firstname, users::id))
+            .get_results(self.deref())?;
+
+        let mut groups: Vec<((String, String), Vec<User>)> = vec![];
+
+        users.into_iter().for_each(|user| {
+            let key = (user.firstname.to_lowercase(), user.lastname.to_lowercase());
+
+            match groups.iter_mut().find(|(group_key, _)| *group_key == key) {
+                Some((_, group)) => group.push(user),
+                None => groups.push((key, vec![user])),
+            }
+        });
+
+        Ok(groups
+            .into_iter()
+            .map(|(_, group)| group)
+            .filter(|group| group.len() > 1)
+            .collect())
+    }
+
+    fn export_user_data(&self, team_id: Uuid, user_id: Uuid) -> Result<UserDataExport, DbError> {
+        let user = self.get_user(team_id, user_id)?;
+        let team: Team = teams::table.find(team_id).get_result(self.deref())?;
+
+        let sanctions: Vec<Sanction> = sanctions::table
+            .filter(
+                sanctions::team_id
+                    .eq(team_id)
+                    .and(sanctions::user_id.eq(user_id)),
+            )
+            .order_by((sanctions::created_at, sanctions::id))
+            .get_results(self.deref())?;
+
+        let sanctions = sanctions
+            .into_iter()
+            .map(|sanction| ExportedSanction {
+                rule: team
+                    .rules
+                    .iter()
+                    .find(|rule| rule.id == sanction.sanction_info.associated_rule)
+                    .cloned(),
+                sanction,
+            })
+            .collect();
+
+        Ok(UserDataExport { user, sanctions })
+    }
+
+    fn anonymize_user(&self, team_id: Uuid, user_id: Uuid) -> Result<User, DbError> {
+        let user: User = diesel::update(
+            users::table.filter(users::team_id.eq(team_id).and(users::id.eq(user_id))),
+        )
+        .set((
+            users::firstname.eq(ANONYMIZED_FIRSTNAME),
+            users::lastname.eq(ANONYMIZED_LASTNAME),
+            users::nickname.eq(None::<String>),
+            users::email.eq(None::<String>),
+            users::anonymized.eq(true),
+        ))
+        .get_result(self.deref())?;
+
+        Ok(user)
+    }
+
+    fn get_users_without_email(&self, team_id: Uuid) -> Result<Vec<User>, DbError> {
+        let users: Vec<User> = users::table
+            .filter(
+                users::team_id
+                    .eq(team_id)
+                    .and(users::anonymized.eq(false))
+                    .and(users::email.is_null().or(users::email.eq(""))),
+            )
+            .order_by((users::lastname, users::firstname, users::id))
+            .get_results(self.deref())?;
+
+        Ok(users)
+    }
+
+    fn get_users_by_role(&self, team_id: Uuid, role: &str) -> Result<Vec<User>, DbError> {
+        let users: Vec<User> = users::table
+            .filter(users::team_id.eq(team_id).and(users::role.eq(role.trim())))
+            .order_by((users::lastname, users::firstname, users::id))
+            .get_results(self.deref())?;
+
+        Ok(users)
+    }
+
+    fn upsert_user(&self, user: &User) -> Result<User, DbError> {
+        let user: User = diesel::insert_into(users::table)
+            .values(user)
+            .on_conflict((users::team_id, users::external_id))
+            .do_update()
+            .set((
+                users::firstname.eq(excluded(users::firstname)),
+                users::lastname.eq(excluded(users::lastname)),
+                users::nickname.eq(excluded(users::nickname)),
+                users::email.eq(excluded(users::email)),
+                users::fine_multiplier.eq(excluded(users::fine_multiplier)),
+                users::role.eq(excluded(users::role)),
+            ))
+            .get_result(self.deref())?;
+
+        Ok(user)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use diesel::result::Error;
+    use serde_json::json;
 
     use super::*;
-    use crate::teams::{interface::TeamsDb, models::Team};
-    use crate::test_utils::postgres::init_connection;
+    use crate::database::amount::Amount;
+    use crate::sanctions::{
+        interface::SanctionsDb,
+        models::{CreateSanction, ExtraInfo, SanctionInfo},
+    };
+    use crate::teams::{interface::TeamsDb, models::Rule};
+    use crate::test_utils::postgres::{init_connection, unique_admin};
 
     #[test]
     fn test_get_users() {
         let conn = init_connection();
 
         conn.deref().test_transaction::<_, Error, _>(|| {
-            let team_id = conn.create_team(&Team::default()).unwrap().id;
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
             let user = conn
                 .create_user(&User {
                     team_id,
@@ -74,11 +234,14 @@ mod tests {
                 .unwrap();
 
             let team_id_2 = conn
-                .create_team(&Team {
-                    id: Uuid::new_v4(),
-                    name: String::from("CHBC"),
-                    ..Default::default()
-                })
+                .create_team(
+                    &Team {
+                        id: Uuid::new_v4(),
+                        name: String::from("CHBC"),
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
                 .unwrap()
                 .id;
             let user_2 = conn
@@ -99,12 +262,53 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_get_users_page() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+            for lastname in &["A", "B", "C"] {
+                conn.create_user(&User {
+                    id: Uuid::new_v4(),
+                    team_id,
+                    lastname: String::from(*lastname),
+                    ..Default::default()
+                })
+                .unwrap();
+            }
+
+            let page = conn
+                .get_users_page(team_id, &Pagination::new(Some(2), Some(1), 2))
+                .unwrap();
+
+            assert_eq!(page.total, 3);
+            assert_eq!(page.limit, 2);
+            assert_eq!(page.offset, 1);
+            assert_eq!(
+                page.items
+                    .iter()
+                    .map(|user| user.lastname.clone())
+                    .collect::<Vec<String>>(),
+                vec![String::from("B"), String::from("C")]
+            );
+
+            Ok(())
+        })
+    }
+
     #[test]
     fn test_get_user() {
         let conn = init_connection();
 
         conn.deref().test_transaction::<_, Error, _>(|| {
-            let team_id = conn.create_team(&Team::default()).unwrap().id;
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
             let user_id = conn
                 .create_user(&User {
                     team_id,
@@ -137,7 +341,10 @@ mod tests {
 
         conn.deref().test_transaction::<_, Error, _>(|| {
             let id = Uuid::new_v4();
-            let team_id = conn.create_team(&Team::default()).unwrap().id;
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
 
             let user = conn
                 .create_user(&User {
@@ -172,7 +379,10 @@ mod tests {
         });
 
         conn.deref().test_transaction::<_, Error, _>(|| {
-            let team_id = conn.create_team(&Team::default()).unwrap().id;
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
 
             conn.create_user(&User {
                 team_id,
@@ -206,7 +416,10 @@ mod tests {
         let conn = init_connection();
 
         conn.deref().test_transaction::<_, Error, _>(|| {
-            let team_id = conn.create_team(&Team::default()).unwrap().id;
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
             let user_id = conn
                 .create_user(&User {
                     team_id,
@@ -244,4 +457,366 @@ mod tests {
 
         assert_eq!(error, DbError::NotFound);
     }
+
+    #[test]
+    fn test_patch_user() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+            let user = conn
+                .create_user(&User {
+                    team_id,
+                    firstname: String::from("John"),
+                    nickname: Some(String::from("King of the north")),
+                    email: Some(String::from("john@snow.com")),
+                    ..Default::default()
+                })
+                .unwrap();
+            let patch =
+                |value: serde_json::Value| -> UserPatch { serde_json::from_value(value).unwrap() };
+
+            let untouched = conn
+                .patch_user(team_id, user.id, &patch(json!({ "firstname": "Jon" })))
+                .unwrap();
+            assert_eq!(
+                untouched,
+                User {
+                    firstname: String::from("Jon"),
+                    ..user.clone()
+                }
+            );
+
+            let cleared = conn
+                .patch_user(team_id, user.id, &patch(json!({ "nickname": null })))
+                .unwrap();
+            assert_eq!(cleared.nickname, None);
+            assert_eq!(cleared.firstname, String::from("Jon"));
+            assert_eq!(cleared.email, user.email);
+
+            assert_eq!(
+                conn.patch_user(team_id, user.id, &patch(json!({})))
+                    .unwrap(),
+                cleared
+            );
+            assert_eq!(
+                conn.patch_user(team_id, Uuid::new_v4(), &patch(json!({})))
+                    .unwrap_err(),
+                DbError::NotFound
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_find_duplicate_users() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+            let create_user = |firstname: &str, lastname: &str| {
+                conn.create_user(&User {
+                    id: Uuid::new_v4(),
+                    team_id,
+                    firstname: String::from(firstname),
+                    lastname: String::from(lastname),
+                    ..Default::default()
+                })
+                .unwrap()
+            };
+
+            let john = create_user("John", "Smith");
+            let other_john = create_user("john", "SMITH");
+            create_user("Jane", "Smith");
+
+            let duplicates = conn.find_duplicate_users(team_id).unwrap();
+
+            assert_eq!(duplicates.len(), 1);
+            assert_eq!(duplicates[0].len(), 2);
+            assert!(duplicates[0].contains(&john));
+            assert!(duplicates[0].contains(&other_john));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_export_user_data() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let rule = Rule {
+                id: Uuid::new_v4(),
+                name: String::from("Late"),
+                ..Default::default()
+            };
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: vec![rule.clone()],
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user = conn
+                .create_user(&User {
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap();
+            let other_user_id = conn
+                .create_user(&User {
+                    id: Uuid::new_v4(),
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+
+            let sanction = |user_id| CreateSanction {
+                id: Uuid::new_v4(),
+                user_id,
+                team_id,
+                sanction_info: SanctionInfo {
+                    associated_rule: rule.id,
+                    extra_info: ExtraInfo::None,
+                },
+                ..Default::default()
+            };
+            conn.create_sanctions(&[
+                sanction(user.id),
+                sanction(user.id),
+                sanction(other_user_id),
+            ])
+            .unwrap();
+
+            let export = conn.export_user_data(team_id, user.id).unwrap();
+
+            assert_eq!(export.user, user);
+            assert_eq!(export.sanctions.len(), 2);
+            assert!(export.sanctions.iter().all(|exported| {
+                exported.sanction.user_id == user.id && exported.rule == Some(rule.clone())
+            }));
+            assert_eq!(
+                conn.export_user_data(team_id, Uuid::new_v4()).unwrap_err(),
+                DbError::NotFound
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_anonymize_user() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(
+                    &Team {
+                        rules: vec![Rule::default()],
+                        ..Default::default()
+                    },
+                    &unique_admin(),
+                )
+                .unwrap()
+                .id;
+            let user_id = conn
+                .create_user(&User {
+                    team_id,
+                    firstname: String::from("John"),
+                    lastname: String::from("Doe"),
+                    nickname: Some(String::from("JD")),
+                    email: Some(String::from("john@doe.com")),
+                    ..Default::default()
+                })
+                .unwrap()
+                .id;
+            conn.create_sanctions(&[CreateSanction {
+                user_id,
+                team_id,
+                price: Amount::from_cents(250),
+                ..Default::default()
+            }])
+            .unwrap();
+
+            let user = conn.anonymize_user(team_id, user_id).unwrap();
+
+            assert!(user.anonymized);
+            assert_eq!(user.firstname, ANONYMIZED_FIRSTNAME);
+            assert_eq!(user.lastname, ANONYMIZED_LASTNAME);
+            assert_eq!(user.nickname, None);
+            assert_eq!(user.email, None);
+
+            let export = conn.export_user_data(team_id, user_id).unwrap();
+
+            assert_eq!(export.sanctions.len(), 1);
+            assert_eq!(export.sanctions[0].sanction.price, Amount::from_cents(250));
+            assert_eq!(
+                conn.anonymize_user(team_id, Uuid::new_v4()).unwrap_err(),
+                DbError::NotFound
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_get_users_without_email() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+            conn.create_user(&User {
+                id: Uuid::new_v4(),
+                team_id,
+                email: Some(String::from("john@snow.com")),
+                ..Default::default()
+            })
+            .unwrap();
+            let without_email = conn
+                .create_user(&User {
+                    id: Uuid::new_v4(),
+                    team_id,
+                    ..Default::default()
+                })
+                .unwrap();
+
+            assert_eq!(
+                conn.get_users_without_email(team_id).unwrap(),
+                vec![without_email]
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_get_users_by_role() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+            let goalkeeper = conn
+                .create_user(&User {
+                    id: Uuid::new_v4(),
+                    team_id,
+                    role: Some(String::from("goalkeeper")),
+                    ..Default::default()
+                })
+                .unwrap();
+            conn.create_user(&User {
+                id: Uuid::new_v4(),
+                team_id,
+                role: Some(String::from("captain")),
+                ..Default::default()
+            })
+            .unwrap();
+
+            assert_eq!(
+                conn.get_users_by_role(team_id, " goalkeeper ").unwrap(),
+                vec![goalkeeper]
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_upsert_user() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+
+            let user = conn
+                .upsert_user(&User {
+                    id: Uuid::new_v4(),
+                    team_id,
+                    firstname: String::from("John"),
+                    lastname: String::from("Doe"),
+                    external_id: Some(String::from("roster-42")),
+                    ..Default::default()
+                })
+                .unwrap();
+
+            let updated_user = conn
+                .upsert_user(&User {
+                    id: Uuid::new_v4(),
+                    team_id,
+                    firstname: String::from("Johnny"),
+                    lastname: String::from("Doe"),
+                    email: Some(String::from("johnny@example.com")),
+                    external_id: Some(String::from("roster-42")),
+                    ..Default::default()
+                })
+                .unwrap();
+
+            assert_eq!(updated_user.id, user.id);
+            assert_eq!(updated_user.firstname, String::from("Johnny"));
+            assert_eq!(updated_user.email, Some(String::from("johnny@example.com")));
+            assert_eq!(conn.get_users(team_id).unwrap(), vec![updated_user]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_email_uniqueness_ignores_case() {
+        let conn = init_connection();
+
+        conn.deref().test_transaction::<_, Error, _>(|| {
+            let team_id = conn
+                .create_team(&Team::default(), &unique_admin())
+                .unwrap()
+                .id;
+
+            let user = conn
+                .create_user(&User {
+                    id: Uuid::new_v4(),
+                    team_id,
+                    email: Some(String::from("John@example.com")),
+                    ..Default::default()
+                })
+                .unwrap();
+
+            assert_eq!(user.email, Some(String::from("John@example.com")));
+
+            let error = conn
+                .create_user(&User {
+                    id: Uuid::new_v4(),
+                    team_id,
+                    email: Some(String::from("john@example.com")),
+                    ..Default::default()
+                })
+                .unwrap_err();
+
+            assert_eq!(
+                error,
+                DbError::UniqueViolation(String::from(
+                    "The field email is already used by another user"
+                ))
+            );
+
+            Ok(())
+        });
+    }
 }
@@ -1,18 +1,31 @@
-use rouille::{input::json::json_input, router, Request};
+use rouille::{router, Request};
 use serde::Serialize;
-use uuid::Uuid;
 
 use super::{
     interface::UsersDb,
-    models::{UpdateUser, UpdateUserRequest, User},
+    models::{UpdateUser, UpdateUserRequest, User, UserPatch, UserResponse},
 };
-use crate::api::models::ErrorResponse;
+use crate::api::{
+    models::{ErrorResponse, NotFoundAs, Resource},
+    utils::{parse_json_body, parse_pagination, parse_uuid},
+};
+use crate::database::pagination::Paginated;
 
 #[derive(Serialize, Debug, PartialEq)]
 #[serde(untagged)]
 pub enum ResultWrapper {
-    Users(Vec<User>),
-    User(User),
+    Users(Vec<UserResponse>),
+    PaginatedUsers(Paginated<UserResponse>),
+    User(UserResponse),
+}
+
+impl ResultWrapper {
+    pub fn link_header(&self, path: &str) -> Option<String> {
+        match self {
+            ResultWrapper::PaginatedUsers(page) => page.link_header(path),
+            _ => None,
+        }
+    }
 }
 
 #[allow(clippy::cognitive_complexity)]
@@ -21,30 +34,70 @@ where
     T: UsersDb,
 {
     router!(request,
-        (GET) (/teams/{team_id: Uuid}/users) => {
-            let result = db.get_users(team_id)?;
-
-            Ok(ResultWrapper::Users(result))
+        (GET) (/teams/{team_id: String}/users) => {
+            let team_id = parse_uuid(&team_id)?;
+
+            match parse_pagination(request)? {
+                Some(pagination) => {
+                    let result = db.get_users_page(team_id, &pagination)?;
+
+                    Ok(ResultWrapper::PaginatedUsers(result.map(UserResponse::from)))
+                }
+                None => {
+                    let result = db.get_users(team_id)?;
+
+                    Ok(ResultWrapper::Users(
+                        result.into_iter().map(UserResponse::from).collect(),
+                    ))
+                }
+            }
         },
-        (POST) (/teams/{team_id: Uuid}/users) => {
-            let input: User = (json_input::<UpdateUserRequest>(request)?, team_id).into();
+        (POST) (/teams/{team_id: String}/users) => {
+            let team_id = parse_uuid(&team_id)?;
+
+            let input = parse_json_body::<UpdateUserRequest>(request)?;
+            input.validate()?;
+
+            let input: User = (input, team_id).into();
 
             let result = db.create_user(&input)?;
 
-            Ok(ResultWrapper::User(result))
+            Ok(ResultWrapper::User(result.into()))
 
         },
-        (GET) (/teams/{team_id: Uuid}/users/{user_id: Uuid}) => {
-            let result = db.get_user(team_id, user_id)?;
+        (GET) (/teams/{team_id: String}/users/{user_id: String}) => {
+            let team_id = parse_uuid(&team_id)?;
+            let user_id = parse_uuid(&user_id)?;
 
-            Ok(ResultWrapper::User(result))
+            let result = db.get_user(team_id, user_id).not_found_as(Resource::User)?;
+
+            Ok(ResultWrapper::User(result.into()))
         },
-        (POST) (/teams/{team_id: Uuid}/users/{user_id: Uuid}) => {
-            let input: UpdateUser = json_input::<UpdateUserRequest>(request)?.into();
+        (POST) (/teams/{team_id: String}/users/{user_id: String}) => {
+            let team_id = parse_uuid(&team_id)?;
+            let user_id = parse_uuid(&user_id)?;
+
+            let input = parse_json_body::<UpdateUserRequest>(request)?;
+            input.validate()?;
 
-            let result = db.update_user(team_id, user_id, &input)?;
+            let input: UpdateUser = input.into();
 
-            Ok(ResultWrapper::User(result))
+            let result = db.update_user(team_id, user_id, &input).not_found_as(Resource::User)?;
+
+            Ok(ResultWrapper::User(result.into()))
+        },
+        (PATCH) (/teams/{team_id: String}/users/{user_id: String}) => {
+            let team_id = parse_uuid(&team_id)?;
+            let user_id = parse_uuid(&user_id)?;
+
+            let input = parse_json_body::<UserPatch>(request)?;
+            input.validate()?;
+
+            let result = db
+                .patch_user(team_id, user_id, &input.normalize())
+                .not_found_as(Resource::User)?;
+
+            Ok(ResultWrapper::User(result.into()))
         },
         _ => {
             Err(ErrorResponse::not_found())
@@ -55,6 +108,7 @@ where
 #[cfg(test)]
 mod tests {
     use serde_json::json;
+    use uuid::Uuid;
 
     use super::*;
     use crate::api::models::{test_utils::RequestBuilder, ErrorKind};
@@ -70,7 +124,22 @@ mod tests {
         )
         .unwrap());
 
-        assert_eq!(response[0]["team_id"], json!(team_id));
+        assert_eq!(response[0]["teamId"], json!(team_id));
+    }
+
+    #[test]
+    fn test_get_users_page() {
+        let team_id = Uuid::new_v4();
+
+        let response = json!(handle_request(
+            &RequestBuilder::get(format!("/teams/{}/users?limit=1000000", team_id)),
+            &DbMock::default(),
+        )
+        .unwrap());
+
+        assert_eq!(response["items"][0]["teamId"], json!(team_id));
+        assert_eq!(response["limit"], json!(200));
+        assert_eq!(response["offset"], json!(0));
     }
 
     #[test]
@@ -85,7 +154,42 @@ mod tests {
         .unwrap());
 
         assert_eq!(response["id"], json!(user_id));
-        assert_eq!(response["team_id"], json!(team_id));
+        assert_eq!(response["teamId"], json!(team_id));
+
+        let user = json!({
+            "firstname": " John  Paul ",
+            "lastname": "Snow"
+        });
+
+        let response = json!(handle_request(
+            &RequestBuilder::post(format!("/teams/{}/users/{}", team_id, user_id), &user),
+            &DbMock::default(),
+        )
+        .unwrap());
+
+        assert_eq!(response["firstname"], "John Paul");
+    }
+
+    #[test]
+    fn test_user_response_uses_camel_case() {
+        let response = json!(UserResponse::from(User::default()));
+
+        assert_eq!(
+            response,
+            json!({
+                "id": Uuid::nil(),
+                "teamId": Uuid::nil(),
+                "firstname": "",
+                "lastname": "",
+                "nickname": null,
+                "email": null,
+                "fineMultiplier": 1.0,
+                "anonymized": false,
+                "role": null,
+                "externalId": null,
+                "onLeaveUntil": null,
+            })
+        );
     }
 
     #[test]
@@ -103,6 +207,7 @@ mod tests {
         .unwrap_err();
 
         assert_eq!(error.kind, ErrorKind::NotFound);
+        assert_eq!(error.description, "User not found");
     }
 
     #[test]
@@ -123,11 +228,79 @@ mod tests {
         )
         .unwrap());
 
-        assert_eq!(response["team_id"], json!(team_id));
+        assert_eq!(response["teamId"], json!(team_id));
         assert_eq!(response["firstname"], "John");
         assert_eq!(response["email"], serde_json::Value::Null);
     }
 
+    #[test]
+    fn test_create_user_normalizes_names() {
+        let team_id = Uuid::new_v4();
+        let user = json!({
+            "firstname": "  John ",
+            "lastname": "Snow",
+            "nickname": "King   of the\tnorth "
+        });
+
+        let response = json!(handle_request(
+            &RequestBuilder::post(format!("/teams/{}/users", team_id), &user),
+            &DbMock::default(),
+        )
+        .unwrap());
+
+        assert_eq!(response["firstname"], "John");
+        assert_eq!(response["nickname"], "King of the north");
+
+        let user = json!({
+            "firstname": "John",
+            "lastname": "   "
+        });
+
+        let error = handle_request(
+            &RequestBuilder::post(format!("/teams/{}/users", team_id), &user),
+            &DbMock::default(),
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::NotValid);
+        assert_eq!(
+            error.description,
+            String::from("The field lastname must not be empty")
+        );
+    }
+
+    #[test]
+    fn test_create_user_with_role() {
+        let team_id = Uuid::new_v4();
+        let user = json!({
+            "firstname": "John",
+            "lastname": "Snow",
+            "role": " captain "
+        });
+
+        let response = json!(handle_request(
+            &RequestBuilder::post(format!("/teams/{}/users", team_id), &user),
+            &DbMock::default(),
+        )
+        .unwrap());
+
+        assert_eq!(response["role"], "captain");
+
+        let user = json!({
+            "firstname": "John",
+            "lastname": "Snow",
+            "role": "  "
+        });
+
+        let response = json!(handle_request(
+            &RequestBuilder::post(format!("/teams/{}/users", team_id), &user),
+            &DbMock::default(),
+        )
+        .unwrap());
+
+        assert_eq!(response["role"], serde_json::Value::Null);
+    }
+
     #[test]
     fn test_create_user_fails() {
         let team_id = Uuid::new_v4();
@@ -171,6 +344,24 @@ mod tests {
         .unwrap_err();
 
         assert_eq!(error.kind, ErrorKind::Json);
+
+        let negative_multiplier = json!({
+            "firstname": "John",
+            "lastname": "Snow",
+            "fine_multiplier": -1.0
+        });
+
+        let error = handle_request(
+            &RequestBuilder::post(format!("/teams/{}/users", team_id), &negative_multiplier),
+            &DbMock::default(),
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::NotValid);
+        assert_eq!(
+            error.description,
+            String::from("The field fine_multiplier must be positive")
+        );
     }
 
     #[test]
@@ -194,7 +385,7 @@ mod tests {
         .unwrap());
 
         assert_eq!(response["id"], json!(user_id));
-        assert_eq!(response["team_id"], json!(team_id));
+        assert_eq!(response["teamId"], json!(team_id));
     }
 
     #[test]
@@ -222,4 +413,93 @@ mod tests {
 
         assert_eq!(error.kind, ErrorKind::NotFound);
     }
+
+    #[test]
+    fn test_patch_user() {
+        let user_id = Uuid::new_v4();
+        let team_id = Uuid::new_v4();
+        let url = format!("/teams/{}/users/{}", team_id, user_id);
+
+        let untouched = json!(handle_request(
+            &RequestBuilder::patch(url.clone(), &json!({ "firstname": " Jon " })),
+            &DbMock::default(),
+        )
+        .unwrap());
+
+        assert_eq!(untouched["firstname"], json!("Jon"));
+        assert_eq!(untouched["lastname"], json!("Snow"));
+        assert_eq!(untouched["nickname"], json!("King of the north"));
+
+        let cleared = json!(handle_request(
+            &RequestBuilder::patch(url, &json!({ "nickname": null })),
+            &DbMock::default(),
+        )
+        .unwrap());
+
+        assert_eq!(cleared["firstname"], json!("John"));
+        assert_eq!(cleared["nickname"], json!(null));
+    }
+
+    #[test]
+    fn test_patch_user_fails() {
+        let url = format!("/teams/{}/users/{}", Uuid::new_v4(), Uuid::new_v4());
+
+        let error = handle_request(
+            &RequestBuilder::patch(url.clone(), &json!({ "lastname": " " })),
+            &DbMock::default(),
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::NotValid);
+        assert_eq!(
+            error.description,
+            String::from("The field lastname must not be empty")
+        );
+
+        let error = handle_request(
+            &RequestBuilder::patch(url, &json!({})),
+            &DbMock {
+                users_db: UsersDbMock::NotFound,
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_create_user_reports_every_error() {
+        let user = json!({
+            "firstname": " ",
+            "lastname": "Snow",
+            "email": "jon.snow@",
+            "fine_multiplier": -1.0
+        });
+
+        let error = handle_request(
+            &RequestBuilder::post(format!("/teams/{}/users", Uuid::new_v4()), &user),
+            &DbMock::default(),
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::NotValid);
+        assert_eq!(error.kind.status_code(), 422);
+        assert_eq!(
+            json!(error)["fields"],
+            json!({
+                "firstname": ["must not be empty"],
+                "email": ["must be a valid email address"],
+                "fine_multiplier": ["must be positive"]
+            })
+        );
+        assert_eq!(
+            error.description,
+            String::from(
+                "The field firstname must not be empty, \
+                 The field email must be a valid email address, \
+                 The field fine_multiplier must be positive"
+            )
+        );
+    }
 }